@@ -0,0 +1,47 @@
+use std::{env,fs,process};
+
+use opinionated_rust_to_typescript::lexer::lexemize;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 2 && args[1] == "--help" {
+        println!("Prints each Lexeme in a Rust file as a line of JSON. Try:");
+        println!(r#"    echo "const FOUR: u8 = 4;" > four.rs"#);
+        println!("    cargo run --example lex-file -- four.rs");
+        return;
+    }
+    if args.len() != 2 {
+        eprintln!("ERROR: Expected 2 args, got {}. Try:", args.len());
+        eprintln!(r#"    echo "const FOUR: u8 = 4;" > four.rs"#);
+        eprintln!("    cargo run --example lex-file -- four.rs");
+        process::exit(1);
+    }
+    let contents = fs::read_to_string(&args[1]).unwrap_or_else(|err| {
+        eprintln!("ERROR: Problem reading the file:\n    {}", err);
+        process::exit(2);
+    });
+    for lexeme in &lexemize(&contents).lexemes {
+        println!(r#"{{"kind":"{}","pos":{},"line":{},"col":{},"text":"{}"}}"#,
+            lexeme.kind, lexeme.pos, lexeme.line_number, lexeme.column,
+            escape_json(lexeme.snippet));
+    }
+}
+
+// Escapes a snippet for use inside a JSON string, without needing serde.
+fn escape_json(snippet: &str) -> String {
+    let mut out = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // Other control chars, like U+0000, use a unicode escape.
+            c if (c as u32) < 0x20 =>
+                out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -12,5 +12,11 @@ fn main() {
         process::exit(1);
     }
     let result = rs_to_ts(&args[1], Config::new());
-    println!("{}", result.main_lines[0]);
+    for line in &result.main_lines {
+        println!("{}", line);
+    }
+    if ! result.errors.is_empty() {
+        eprintln!("{}", result.format_errors());
+        process::exit(2);
+    }
 }
@@ -0,0 +1,31 @@
+//! Tools for dividing Rust code into Lexemes, without transpiling it.
+//! 
+//! These are re-exported from `rs2018_ts4::lexemize`, so that the lexer can be
+//! used without reaching into the transpiler’s modules.
+//! ```
+//! use opinionated_rust_to_typescript::lexer::{lexemize,LexemeKind};
+//! let lexemes = lexemize("let x = 1;");
+//! assert_eq!(lexemes.lexemes.len(), 8);
+//! assert_eq!(lexemes.lexemes[0].snippet, "let");
+//! assert!(lexemes.lexemes[0].kind == LexemeKind::Keyword);
+//! assert!(lexemes.lexemes[2].kind == LexemeKind::Identifier);
+//! assert!(lexemes.lexemes[6].kind == LexemeKind::Number);
+//! assert_eq!(lexemes.end_pos, 10);
+//! ```
+
+pub use crate::rs2018_ts4::lexemize::lexeme::{
+    DisplayOpts,
+    Lexeme,
+    LexemeKind,
+    Lexemes,
+    LexemeStats,
+};
+pub use crate::rs2018_ts4::lexemize::lexemize::{
+    lexemize,
+    lexemize_checked,
+    lexemize_with_edition,
+    lexemize_with_limit,
+    lexemize_with_tab_width,
+    Lexemizer,
+    next_lexeme_kind,
+};
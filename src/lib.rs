@@ -3,6 +3,7 @@
 //! `opinionated-rust-to-typescript` is a library for transpiling Rust code
 //! to TypeScript.
 
+pub mod lexer;
 /// 
 pub mod transpile;
 pub mod rs2018_ts4;
@@ -0,0 +1,127 @@
+//! Detects an attribute, like `#[derive(Debug)]` or `#![no_std]`.
+
+use super::character::detect_character;
+use super::comment::detect_comment;
+use super::identifier::detect_identifier;
+use super::scanner::{Scanner,ascii_or_tilde as get_aot};
+use super::string::detect_string;
+
+/// Detects an attribute, like `#[derive(Debug)]` or `#![no_std]`.
+/// 
+/// The attribute ends at the `]` which matches its opening `[`. Brackets inside
+/// nested brackets, strings, chars and comments are skipped over.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a valid looking attribute, `detect_attribute()` returns
+/// the character position after the closing square bracket.  
+/// Otherwise, `detect_attribute()` just returns the `pos` argument. So an
+/// unterminated attribute falls back to being detected as punctuation.
+pub fn detect_attribute(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
+    // If `pos` does not begin "#[" or "#![", it does not begin an attribute.
+    let open = if orig.get(pos..pos+2) == Some("#[") { pos + 1 }
+        else if orig.get(pos..pos+3) == Some("#![") { pos + 2 }
+        else { return pos };
+    // Track how deep into nested square brackets we are.
+    let mut depth = 0;
+    // Step through each char, from the opening "[" to the end of `orig`.
+    let len = orig.len();
+    let mut i = open;
+    while i < len {
+        // If this char begins a comment, string, char or identifier, skip it.
+        // That means that brackets in `"]"` or `r#"]"#` are ignored.
+        let mut next = detect_comment(orig, i);
+        if next == i { next = detect_string(orig, i) }
+        if next == i { next = detect_character(orig, i) }
+        if next == i { next = detect_identifier(orig, i) }
+        if next != i { i = next; continue }
+        // Otherwise, if this char is a square bracket, adjust the depth.
+        match get_aot(orig, i) {
+            "[" => depth += 1,
+            "]" => {
+                depth -= 1;
+                // If this closes the opening "[", advance past it.
+                if depth == 0 { return i + 1 }
+            },
+            _ => {},
+        }
+        // Step forward to the start of the next char, even if it’s non-ascii.
+        i += 1;
+        while ! orig.is_char_boundary(i) { i += 1 }
+    }
+    // The closing "]" was not found, so this is not an attribute.
+    pos
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_attribute as detect;
+
+    #[test]
+    fn detect_attribute_off_char_boundary() {
+        assert_eq!(detect("€#[a]", 1), 1);
+        assert_eq!(detect("€#[a]", 2), 2);
+        assert_eq!(detect("€#[a]", 3), 7);
+    }
+
+    #[test]
+    fn detect_attribute_correct() {
+        // Outer and inner attributes.
+        let orig = "#[derive(Debug)] #![feature(x)]";
+        assert_eq!(detect(orig, 0), 16);  // #[derive(Debug)]
+        assert_eq!(detect(orig, 1), 1);   // [derive(Debug)] has no #
+        assert_eq!(detect(orig, 17), 31); // #![feature(x)]
+        assert_eq!(detect(orig, 18), 18); // ![feature(x)] has no #
+        // Nesting.
+        assert_eq!(detect("#[cfg(all(a, b))]", 0), 17);
+        assert_eq!(detect("#[a[b[c]]d] e]", 0), 11); // nested brackets
+        // Brackets inside strings, chars and comments are ignored.
+        assert_eq!(detect("#[doc = \"]\"]", 0), 12); // "]"
+        assert_eq!(detect("#[doc = r#\"]\"#]", 0), 15); // r#"]"#
+        assert_eq!(detect("#[a(']')]", 0), 9); // ']'
+        assert_eq!(detect("#[a /* ] */]", 0), 12); // /* ] */
+        // Multiline.
+        assert_eq!(detect("#[cfg(\n    a\n)]\nfn", 0), 15);
+        // Non-ascii.
+        assert_eq!(detect("#[doc = \"€\"]", 0), 14);
+        assert_eq!(detect("#[é]", 0), 5);
+    }
+
+    #[test]
+    fn detect_attribute_incorrect() {
+        // Unterminated.
+        assert_eq!(detect("#[derive(Debug)", 0), 0); // no ]
+        assert_eq!(detect("#[a[b]", 0), 0); // the inner ] is not enough
+        assert_eq!(detect("#[doc = \"]\"", 0), 0); // the ] is in a string
+        // Not attributes.
+        assert_eq!(detect("# [a]", 0), 0); // space after #
+        assert_eq!(detect("#!/bin/x", 0), 0); // shebang
+        assert_eq!(detect("[a]", 0), 0); // no #
+    }
+
+    #[test]
+    fn detect_attribute_will_not_panic() {
+        // Near the end of `orig`.
+        assert_eq!(detect("", 0), 0); // empty string
+        assert_eq!(detect("#", 0), 0); // #
+        assert_eq!(detect("#[", 0), 0); // #[
+        assert_eq!(detect("#!", 0), 0); // #!
+        assert_eq!(detect("#![", 0), 0); // #![
+        assert_eq!(detect("#[]", 0), 3); // #[]
+        // Invalid `pos`.
+        assert_eq!(detect("abc", 2), 2); // 2 is before "c", so in range
+        assert_eq!(detect("abc", 3), 3); // 3 is after "c", so incorrect
+        assert_eq!(detect("abc", 4), 4); // 4 is out of range
+        assert_eq!(detect("abc", 100), 100); // 100 is way out of range
+        // Non-ascii.
+        assert_eq!(detect("€", 1), 1); // part way through the three eurobytes
+        assert_eq!(detect("#[€", 0), 0); // unterminated non-ascii
+        assert_eq!(detect("#€", 0), 0); // non-ascii after #
+    }
+
+}
@@ -1,8 +1,10 @@
 //! Detects a char literal, like `'A'` or `\u{03aB}`.
 
+use super::scanner::{Scanner,ascii_or_tilde as get_aot};
+
 /// Detects a char literal, like `'A'` or `\u{03aB}`.
 /// 
-/// @TODO `b` prefix, eg `b'A'`
+/// Byte char literals, like `b'A'` or `b'\xFF'`, are also detected.
 /// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
@@ -13,9 +15,14 @@
 /// returns the character position after the closing single quote.  
 /// Otherwise, `detect_character()` just returns the `pos` argument.
 pub fn detect_character(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
     // Avoid panicking, if there would not be enough room for a char.
     let len = orig.len();
     if len < pos + 3 { return pos } // pos + ' + A + '
+    // If the current char is a lowercase "b", it could begin a byte char.
+    if get_aot(orig, pos) == "b" {
+        return detect_byte_character(orig, pos, len)
+    }
     // If the current char is not a single-quote, then it does not begin a char.
     let c0 = get_aot(orig, pos);
     if c0 != "'" { return pos }
@@ -51,11 +58,10 @@ pub fn detect_character(orig: &str, pos: usize) -> usize {
                 { 4 } else { 0 },
         // ...lowercase x, signifying a 7-bit char code:
         "x" =>
-            // Advance 6 places if the chars after that are 0-7 and 0-9A-Fa-f.
+            // Advance 6 places if the chars after that are two hex digits, up
+            // to 7F, followed by a single quote.
             pos +
-                if len >= pos + 6
-                && get_aot(orig, pos+3).chars().all(|c| c >= '0' && c <= '7')
-                && get_aot(orig, pos+4).chars().all(|c| c.is_ascii_hexdigit())
+                if matches!(parse_hex_escape(orig, pos+3), Some(b) if b < 0x80)
                 && get_aot(orig, pos+5) == "'"
                 { 6 } else { 0 },
         // ...lowercase u, signifying a unicode char code:
@@ -69,10 +75,123 @@ pub fn detect_character(orig: &str, pos: usize) -> usize {
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, p: usize) -> &str { orig.get(p..p+1).unwrap_or("~") }
+/// Detects a char literal which contains more than one char, like `'ab'`.
+/// 
+/// This is a common mistake, where a string literal was probably intended. It
+/// is not detected by `detect_character()`, so would otherwise become several
+/// confusing Lexemes. The scan runs from the opening single quote to the next
+/// unescaped single quote on the same line. An escape, like `\n` or `\u{41}`,
+/// counts as one char.
+/// 
+/// Like `rustc`, an identifier after the opening single quote which is not
+/// followed by a single quote or backslash is a lifetime or label, like `'a`
+/// in `<'a, 'b>`, so it is not detected.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a char literal with more than one char,
+/// `detect_multi_char_character()` returns the character position after the
+/// closing single quote.  
+/// Otherwise, `detect_multi_char_character()` just returns the `pos` argument.
+pub fn detect_multi_char_character(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
+    // A valid char literal is not a multi-char literal.
+    if get_aot(orig, pos) != "'" || detect_character(orig, pos) != pos {
+        return pos
+    }
+    let is_hex = |&(_, c): &(usize, char)| c.is_ascii_hexdigit();
+    let mut chars = orig[pos+1..].char_indices().peekable();
+    let mut count = 0;
+    // Whether every char so far could be part of an identifier.
+    let mut identifier = true;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            // The closing single quote.
+            '\'' => return if count > 1 { pos + i + 2 } else { pos },
+            // Char literals cannot span lines.
+            '\n' => return pos,
+            // An escape counts as one char, however long it is.
+            '\\' => {
+                identifier = false;
+                match chars.next() {
+                    Some((_, 'u')) => {
+                        while chars.next_if(|&(_, c)| c == '{'
+                            || c.is_ascii_hexdigit()).is_some() {}
+                        chars.next_if(|&(_, c)| c == '}');
+                    },
+                    Some((_, 'x')) => {
+                        chars.next_if(is_hex);
+                        chars.next_if(is_hex);
+                    },
+                    Some((_, '\n')) | None => return pos,
+                    Some(_) => {},
+                }
+            },
+            c if identifier && (c == '_' || c.is_alphabetic()
+                || (count > 0 && c.is_numeric())) => {},
+            // An identifier followed by anything else is a lifetime or label.
+            _ if identifier && count > 0 => return pos,
+            _ => identifier = false,
+        }
+        count += 1;
+    }
+    pos
+}
+
+// doc.rust-lang.org/reference/tokens.html#byte-literals
+fn detect_byte_character(orig: &str, pos: usize, len: usize) -> usize {
+    // If there is not enough room for b'A', or the "b" is not followed by a
+    // single quote, then `pos` does not begin a byte char.
+    if len < pos + 4 || get_aot(orig, pos+1) != "'" { return pos }
+    // Get the char after the single quote. Byte chars must be ascii, so if the
+    // char is non-ascii (or past the end of `orig`), this is not a byte char.
+    let c2 = match orig.get(pos+2..pos+3) { Some(c) => c, None => return pos };
+    // If the char after the single quote is not a backslash:
+    if c2 != "\\" {
+        return
+            // If `c2` is a single quote, we have found "b''", which is invalid.
+            if c2 == "'" { pos }
+            // Otherwise, if the char after `c2` is a single quote, advance.
+            else if get_aot(orig, pos+3) == "'" { pos + 4 }
+            // Otherwise, this is not a valid byte char.
+            else { pos }
+    }
+    // Now we know `c2` is a backslash, if the char after it is...
+    match get_aot(orig, pos+3) {
+        // ...one of Rust’s simple backslashable chars:
+        "n" | "r" | "t" | "\\" | "0" | "\"" | "'" =>
+            // Advance five places if the char after that is a single-quote.
+            pos +
+                if get_aot(orig, pos+4) == "'" { 5 } else { 0 },
+        // ...lowercase x, signifying an 8-bit byte code:
+        "x" =>
+            // Advance 7 places if the chars after that are two hex digits.
+            // Unlike chars, byte chars allow values up to \xFF.
+            pos +
+                if parse_hex_escape(orig, pos+4).is_some()
+                && get_aot(orig, pos+6) == "'"
+                { 7 } else { 0 },
+        // ...anything else, including the "u" of a unicode escape:
+        _ =>
+            // `pos` does not begin a byte char.
+            pos
+    }
+}
+
+// Parses the two hex digits of a "\x" escape, eg the "7F" of '\x7F'. Returns
+// `None` if there are not two 0-9A-Fa-f chars at `p`.
+fn parse_hex_escape(orig: &str, p: usize) -> Option<u8> {
+    let digits = orig.get(p..p+2)?;
+    // `from_str_radix()` would also accept a leading "+", like in "+1".
+    if ! digits.chars().all(|c| c.is_ascii_hexdigit()) { return None }
+    u8::from_str_radix(digits, 16).ok()
+}
 
 // 24-bit Unicode character code, 1 to 6 digits, eg '\u{f}' to '\u{10abCD}'.
+// Underscores may follow any digit, eg '\u{1_F600}'.
 fn detect_unicode_char_length(orig: &str, pos: usize, len: usize) -> usize {
     // If `orig` is not even long enough for the shortest form, '\u{0}',
     // or if the "'\u" is not followed by an open curly bracket, return zero.
@@ -80,21 +199,24 @@ fn detect_unicode_char_length(orig: &str, pos: usize, len: usize) -> usize {
     // Initialise variables which will be modified by the loop, below.
     let mut found_closing_curly_bracket = false;
     let mut codepoint = "".to_string();
-    // Loop through the characters after "'\u{", to a maximum "'\u{123456}".
-    for i in 4..11 {
+    let mut i = 4;
+    // Loop through the characters after "'\u{", to the closing curly bracket.
+    while pos + i < len {
         let c = get_aot(orig, pos+i);
         if c == "}" { found_closing_curly_bracket = true; break }
-        // If the current character is 0-9A-Fa-f, append it to `codepoint`.
+        // If the current character is 0-9A-Fa-f, append it to `codepoint`. An
+        // underscore is skipped, unless it comes before the first digit.
         if c.chars().all(|c| c.is_ascii_hexdigit()) {
             codepoint.push_str(c)
-        } else {
+        } else if c != "_" || codepoint.is_empty() {
             return 0
         }
+        i += 1;
     }
     // Guard against an overlong unicode escape. Must have at most 6 hex digits.
-    if ! found_closing_curly_bracket { return 0 }
+    if ! found_closing_curly_bracket || codepoint.len() > 6 { return 0 }
     // Get the position of the character which should be a closing single-quote.
-    let l = codepoint.len() + 5;
+    let l = i + 1;
     // If that char is not a single-quote, return zero.
     if get_aot(orig, pos+l) != "'" { return 0 }
     // Parse the codepoint into a number.
@@ -112,6 +234,17 @@ fn detect_unicode_char_length(orig: &str, pos: usize, len: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::detect_character as detect;
+    use super::detect_multi_char_character as detect_multi;
+
+    #[test]
+    fn detect_character_off_char_boundary() {
+        assert_eq!(detect("€'a'", 1), 1);
+        assert_eq!(detect("€'a'", 2), 2);
+        assert_eq!(detect("€'a'", 3), 6);
+        assert_eq!(detect_multi("€'ab'", 1), 1);
+        assert_eq!(detect_multi("€'ab'", 2), 2);
+        assert_eq!(detect_multi("€'ab'", 3), 7);
+    }
 
     #[test]
     fn get_ascii_or_tilde() {
@@ -161,6 +294,9 @@ mod tests {
         assert_eq!(detect(orig, 5), 5); // '
         let orig = " - '\\x0f' - ";
         assert_eq!(detect(orig, 3), 9); // '\x0f' advance 6 places
+        assert_eq!(detect("'\\x00'", 0), 6); // '\x00' is the minimum
+        assert_eq!(detect("'\\x7F'", 0), 6); // '\x7F' is the maximum
+        assert_eq!(detect("'\\x7f'", 0), 6); // '\x7f' lowercase is ok
         // Unicode '\u{0}'.
         assert_eq!(detect("'\\u{0}'", 0), 7); // '\u{0}'
         assert_eq!(detect(" '\\u{C}'", 1), 8); // '\u{C}'
@@ -173,10 +309,23 @@ mod tests {
         assert_eq!(detect("'\\u{100abC}'", 0), 12); // '\u{100abC}'
         assert_eq!(detect(" - '\\u{10FFFF}'", 3), 15); // maximum
         assert_eq!(detect("'\\u{123}'€", 0), 9); // '\u{123}'
+        assert_eq!(detect("'\\u{1_F6_00}'", 0), 13); // '\u{1_F6_00}'
+        assert_eq!(detect("'\\u{10FF__FF_}'", 0), 15); // six digits
         let orig = "'\\u{30aF}'";
         assert_eq!(detect(orig, 0), 10); // '\u{30aF}' advance to end
         assert_eq!(detect(orig, 1), 1); // \u{30aF}'
         assert_eq!(detect(orig, 2), 2); // u{30aF}'
+        // Byte.
+        let orig = "b'A' b'~' b'\\n' b'\\x7F' b'\\xFF'";
+        assert_eq!(detect(orig, 0), 4);   // b'A'
+        assert_eq!(detect(orig, 1), 4);   // 'A'
+        assert_eq!(detect(orig, 5), 9);   // b'~'
+        assert_eq!(detect(orig, 10), 15); // b'\n'
+        assert_eq!(detect(orig, 16), 23); // b'\x7F'
+        assert_eq!(detect(orig, 24), 31); // b'\xFF' is valid for a byte
+        assert_eq!(detect("b'\\''", 0), 5); // b'\''
+        assert_eq!(detect("b'\\\\'", 0), 5); // b'\\'
+        assert_eq!(detect("b'\\0'", 0), 5); // b'\0'
     }
 
     #[test]
@@ -195,6 +344,10 @@ mod tests {
         assert_eq!(detect("'\\x3f - ", 0), 0); // '\x3f has no end quote
         assert_eq!(detect("'\\x0G'", 0), 0); // '\x0G' is not valid
         assert_eq!(detect("'\\x81'", 0), 0); // '\x81' is out of range
+        assert_eq!(detect("'\\x80'", 0), 0); // '\x80' is just out of range
+        assert_eq!(detect("'\\x7G'", 0), 0); // '\x7G' G is not hex
+        assert_eq!(detect("'\\x+1'", 0), 0); // '\x+1' + is not hex
+        assert_eq!(detect("'\\x8'", 0), 0); // '\x8' has no 2nd digit
         // Incorrect Unicode '\u{0}'.
         assert_eq!(detect("'\\uxyz", 0), 0); // missing {0}
         assert_eq!(detect("'\\u{xyz", 0), 0); // missing 0}
@@ -209,6 +362,37 @@ mod tests {
         assert_eq!(detect("'\\u{1234}", 0), 0); // missing ' at end
         assert_eq!(detect("'\\u{1234} ", 0), 0); // no closing quote
         assert_eq!(detect("'\\u{110000}'", 0), 0); // too high
+        assert_eq!(detect("'\\u{_1}'", 0), 0); // underscore first
+        assert_eq!(detect("'\\u{1_0_0_a_b_C_d}'", 0), 0); // too long
+        // Incorrect byte.
+        assert_eq!(detect("b'' ", 0), 0); // b'' missing char
+        assert_eq!(detect("b'\\u{1}'", 0), 0); // b'\u{1}' no unicode escapes
+        assert_eq!(detect("b'€'", 0), 0); // b'€' must be ascii
+        assert_eq!(detect("b'\\q'", 0), 0); // b'\q' no such backslash
+        assert_eq!(detect("b'\\xG0'", 0), 0); // b'\xG0' is not valid
+        assert_eq!(detect("b'\\x+F'", 0), 0); // b'\x+F' + is not hex
+        assert_eq!(detect("b'ab'", 0), 0); // b'ab' has two chars
+        assert_eq!(detect("bA'", 0), 0); // bA' is not a byte char
+        assert_eq!(detect("b\"A\"", 0), 0); // b"A" is a byte string
+    }
+
+    #[test]
+    fn detect_character_at_exact_end_of_input() {
+        // The char after the single quote ends exactly at the end of `orig`,
+        // so there is no room for the closing single quote.
+        assert_eq!(detect("'X", 0), 0); // 'X
+        assert_eq!(detect("ab'Z", 2), 2); // 'Z after two chars
+        assert_eq!(detect("'€", 0), 0); // '€ where the € ends the input
+        assert_eq!(detect("ab'€", 2), 2); // '€ after two chars
+        assert_eq!(detect("b'X", 0), 0); // b'X
+        assert_eq!(detect("ab b'Z", 3), 3); // b'Z after three chars
+        // Compared with the same chars, which are properly closed.
+        assert_eq!(detect("'X'", 0), 3); // 'X'
+        assert_eq!(detect("ab'Z'", 2), 5); // 'Z'
+        assert_eq!(detect("'€'", 0), 5); // '€'
+        assert_eq!(detect("ab'€'", 2), 7); // '€'
+        assert_eq!(detect("b'X'", 0), 4); // b'X'
+        assert_eq!(detect("ab b'Z'", 3), 7); // b'Z'
     }
 
     #[test]
@@ -241,6 +425,53 @@ mod tests {
         assert_eq!(detect("'\\u{€'", 0), 0); // non-ascii after '\u{
         assert_eq!(detect("'\\u{123€'", 0), 0); // non-ascii after '\u{123
         assert_eq!(detect("'\\u{123}€'", 0), 0); // non-ascii after '\u{123}
+        // Byte, near the end of `orig`.
+        assert_eq!(detect("b", 0), 0); // b
+        assert_eq!(detect("b'", 0), 0); // b'
+        assert_eq!(detect("b'a", 0), 0); // b'a
+        assert_eq!(detect("b'\\", 0), 0); // b'\
+        assert_eq!(detect("b'\\n", 0), 0); // b'\n
+        assert_eq!(detect("b'\\x", 0), 0); // b'\x
+        assert_eq!(detect("b'\\xF", 0), 0); // b'\xF
+        assert_eq!(detect("b'\\xFF", 0), 0); // b'\xFF
+        assert_eq!(detect("b'\\€", 0), 0); // non-ascii after b'\
+    }
+
+    #[test]
+    fn detect_multi_char_character_as_expected() {
+        // Several chars, which should probably have been a string.
+        assert_eq!(detect_multi("'ab'", 0), 4); // 'ab'
+        assert_eq!(detect_multi("= 'ab';", 2), 6); // 'ab'
+        assert_eq!(detect_multi("'a1'", 0), 4); // 'a1'
+        assert_eq!(detect_multi("'12'", 0), 4); // '12'
+        assert_eq!(detect_multi("' a'", 0), 4); // ' a'
+        assert_eq!(detect_multi("'€€'", 0), 8); // '€€' is 8 bytes wide
+        assert_eq!(detect_multi("'a\\'b'", 0), 6); // 'a\'b'
+        assert_eq!(detect_multi("'\\n\\n'", 0), 6); // '\n\n'
+        assert_eq!(detect_multi("'\\u{41}x'", 0), 9); // '\u{41}x'
+        assert_eq!(detect_multi("'\\x41x'", 0), 7); // '\x41x'
+        // A valid char is not a multi-char literal.
+        assert_eq!(detect_multi("'a'", 0), 0); // 'a'
+        assert_eq!(detect_multi("'\\u{41}'", 0), 0); // '\u{41}'
+        assert_eq!(detect_multi("b'a'", 0), 0); // b'a'
+        // Nor is an empty or invalid char.
+        assert_eq!(detect_multi("''", 0), 0); // ''
+        assert_eq!(detect_multi("'\\q'", 0), 0); // '\q' has one char
+        // Lifetimes and labels are not detected.
+        assert_eq!(detect_multi("<'a, 'b>", 1), 1); // 'a
+        assert_eq!(detect_multi("<'a,'b>", 1), 1); // 'a, then a comma
+        assert_eq!(detect_multi("'a b'", 0), 0); // 'a, then a space
+        assert_eq!(detect_multi("&'static str = 'x';", 1), 1); // 'static
+        assert_eq!(detect_multi("'outer: loop {}", 0), 0); // 'outer:
+        // No closing quote on the same line, or at all.
+        assert_eq!(detect_multi("'12\n'", 0), 0); // newline
+        assert_eq!(detect_multi("'\\\n'", 0), 0); // newline after \
+        assert_eq!(detect_multi("'12", 0), 0); // end of input
+        assert_eq!(detect_multi("'\\", 0), 0); // end of input after \
+        // Not a single quote.
+        assert_eq!(detect_multi("ab'", 0), 0);
+        assert_eq!(detect_multi("", 0), 0);
+        assert_eq!(detect_multi("'ab'", 9), 9); // out of range
     }
 
 }
@@ -1,7 +1,13 @@
 //! Detects a multiline or inline comment.
 
+use super::scanner::{Scanner,ascii_or_tilde as get_aot};
+
 /// Detects a multiline or inline comment.
 /// 
+/// Doc comments, like `/// Doc`, are detected too. `lexemize()` tries
+/// `detect_doc_comment()` first, so it only uses `detect_comment()` for plain
+/// comments.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// * `pos` The character position in `orig` to look at
@@ -11,14 +17,16 @@
 /// the character position after the comment ends.  
 /// Otherwise, `detect_comment()` just returns the `pos` argument.
 pub fn detect_comment(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
     // If the current char is the last or second-from-last in `orig`, it does not
     // begin a comment.
     let len = orig.len();
     if len < pos + 2 { return pos }
     // If the current char is not a forward slash, it does not begin a comment.
-    if get_aot(orig, pos) != "/" { return pos }
+    let scanner = Scanner::new(orig, pos);
+    if scanner.peek() != "/" { return pos }
     // If the next char is:
-    match get_aot(orig, pos+1) {
+    match scanner.peek_at(1) {
         // Also a forward slash, `pos` could begin an inline comment.
         "/" => detect_inline_comment(orig, pos, len),
         // An asterisk, `pos` could begin a multiline comment.
@@ -28,18 +36,132 @@ pub fn detect_comment(orig: &str, pos: usize) -> usize {
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, p: usize) -> &str { orig.get(p..p+1).unwrap_or("~") }
+/// Detects a doc comment, like `/// Doc`, `//! Doc`, `/** Doc */` or
+/// `/*! Doc */`.
+/// 
+/// Rust treats three slashes as a doc comment, but four or more, like
+/// `//// Not a doc`, as a plain comment. Likewise `/*** Not a doc */` and the
+/// empty `/**/` are plain comments.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a valid looking doc comment, `detect_doc_comment()` returns
+/// the character position after the comment ends.  
+/// Otherwise, `detect_doc_comment()` just returns the `pos` argument.
+pub fn detect_doc_comment(orig: &str, pos: usize) -> usize {
+    // `detect_comment()` bails out if `pos` is part way through a char.
+    let end = detect_comment(orig, pos);
+    if end != pos && is_doc_comment(&orig[pos..end]) { end } else { pos }
+}
+
+/// Checks whether a comment is a doc comment.
+/// 
+/// ### Arguments
+/// * `snippet` A comment, like `"/// Doc"` or `"// Plain"`
+/// 
+/// ### Returns
+/// `true` if `snippet` begins `///` (but not `////`), `//!`, `/**` (but not
+/// `/***` or `/**/`) or `/*!`. Otherwise, `false`.
+pub fn is_doc_comment(snippet: &str) -> bool {
+    (snippet.starts_with("///") && ! snippet.starts_with("////"))
+        || snippet.starts_with("//!")
+        || (snippet.starts_with("/**") && ! snippet.starts_with("/***")
+            && ! snippet.starts_with("/**/"))
+        || snippet.starts_with("/*!")
+}
+
+/// Quickly checks whether a comment could begin at `pos`.
+/// 
+/// This is cheaper than `detect_comment()`, because it only looks at the first
+/// two chars, rather than finding the end of the comment.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `false` if `detect_comment()` would definitely not find a comment at
+/// `pos`. Otherwise, `starts_comment()` returns `true`.
+pub fn starts_comment(orig: &str, pos: usize) -> bool {
+    // A forward slash followed by a forward slash or asterisk.
+    let scanner = Scanner::new(orig, pos);
+    let c1 = scanner.peek_at(1);
+    scanner.peek() == "/" && (c1 == "/" || c1 == "*")
+}
+
+/// Finds the maximum nesting depth of a multiline comment.
+/// 
+/// This is useful for building a folding map in an editor.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a multiline comment, the deepest nesting level inside it,
+/// eg 0 for `/* */`, and 1 for `/* /* */ */`.  
+/// Otherwise, or if the comment is unterminated, `None`.
+pub fn detect_multiline_comment_max_depth(
+    orig: &str,
+    pos: usize,
+) -> Option<usize> {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return None }
+    // If `pos` does not begin "/*", it does not begin a multiline comment.
+    if orig.get(pos..pos+2) != Some("/*") { return None }
+    scan_multiline_comment(orig, pos, orig.len()).map(|(_, depth)| depth)
+}
+
+/// The outcome of `detect_multiline_comment_status()`.
+#[derive(Clone,Copy,PartialEq)]
+pub enum CommentScan {
+    /// `pos` does not begin `/*`.
+    NotComment,
+    /// A multiline comment, which ends at the position before the `usize`.
+    Terminated(usize),
+    /// The outermost `*/` was not found before the end of the input.
+    Unterminated,
+}
+
+/// Detects a multiline comment, and distinguishes an unterminated comment
+/// from something which is not a comment at all.
+/// 
+/// `detect_comment()` returns `pos` in both cases, so an unterminated comment
+/// would otherwise become several Punctuation Lexemes, starting with `/`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `CommentScan::Terminated(end)` if `pos` begins a multiline comment, where
+/// `end` is the position after the outermost `*/`. Otherwise
+/// `CommentScan::Unterminated` if `pos` begins `/*`, or else
+/// `CommentScan::NotComment`.
+pub fn detect_multiline_comment_status(orig: &str, pos: usize) -> CommentScan {
+    if ! Scanner::new(orig, pos).is_at_boundary() {
+        return CommentScan::NotComment
+    }
+    // If `pos` does not begin "/*", it does not begin a multiline comment.
+    if orig.get(pos..pos+2) != Some("/*") { return CommentScan::NotComment }
+    match scan_multiline_comment(orig, pos, orig.len()) {
+        Some((end, _)) => CommentScan::Terminated(end),
+        None => CommentScan::Unterminated,
+    }
+}
 
 fn detect_inline_comment(orig: &str, pos: usize, len: usize) -> usize {
     // Step through each char, from `pos + 2` to the end of the input code.
     let mut i = pos + 2;
-    while i < len - 1 {
+    while i < len {
         // Get this character, even if it’s non-ascii.
         let mut j = i + 1;
         while !orig.is_char_boundary(j) { j += 1 }
-        // If this char is a newline:
-        if &orig[i..j] == "\n" { //@TODO maybe recognise Windows style "\r\n"?
+        let c = &orig[i..j];
+        // If this char is a newline, or begins a Windows style "\r\n":
+        if c == "\n" || (c == "\r" && get_aot(orig, j) == "\n") {
             // Advance to the start of the newline.
             return i
         }
@@ -51,8 +173,24 @@ fn detect_inline_comment(orig: &str, pos: usize, len: usize) -> usize {
 }
 
 fn detect_multiline_comment(orig: &str, pos: usize, len: usize) -> usize {
-    // Track how deep into a nested multiline comment we are.
+    // If the outermost "*/" is not found, this is not a multiline comment.
+    match scan_multiline_comment(orig, pos, len) {
+        Some((end, _)) => end,
+        None => pos,
+    }
+}
+
+// Returns the position after the outermost "*/" of a multiline comment, and the
+// maximum nesting depth reached, or `None` if the comment is unterminated.
+fn scan_multiline_comment(
+    orig: &str,
+    pos: usize,
+    len: usize,
+) -> Option<(usize, usize)> {
+    // Track how deep into a nested multiline comment we are, and how deep the
+    // nesting has gone so far.
     let mut depth = 0;
+    let mut max_depth = 0;
     // Slightly hacky way to to skip forward while looping.
     let mut i = pos + 2;
     // Step through each char, from `pos` to the end of the original input code.
@@ -68,7 +206,7 @@ fn detect_multiline_comment(orig: &str, pos: usize, len: usize) -> usize {
             // If the depth is zero (so we are at the outermost nesting level):
             if depth == 0 {
                 // Advance to the end of the "*/".
-                return i + 2
+                return Some((i + 2, max_depth))
             // Otherwise we are some way inside a nested multiline comment:
             } else {
                 // Decrement the nesting-depth.
@@ -78,22 +216,45 @@ fn detect_multiline_comment(orig: &str, pos: usize, len: usize) -> usize {
             }
         // If this char is a forward slash, and the next is an asterisk:
         } else if c0 == "/" && c1 == "*" {
-            // Increment the nesting-depth.
+            // Increment the nesting-depth, and record it if it’s the deepest.
             depth += 1;
+            if depth > max_depth { max_depth = depth }
             // Skip the asterisk (avoids confusion in "/*/*/ */ */").
             j += 1;
         }
         // Step forward, ready for the next iteration.
         i = j;
     }
-    // The outermost "*/" was not found, so this is not a multiline comment.
-    pos
+    // The outermost "*/" was not found.
+    None
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::detect_comment as detect;
+    use super::detect_multiline_comment_max_depth as max_depth;
+    use super::starts_comment;
+    use super::{detect_doc_comment,is_doc_comment};
+    use super::{CommentScan,detect_multiline_comment_status as status};
+
+    #[test]
+    fn detect_comment_off_char_boundary() {
+        assert_eq!(detect("€// a", 1), 1);
+        assert_eq!(detect("€// a", 2), 2);
+        assert_eq!(detect("€// a", 3), 7);
+        assert_eq!(detect_doc_comment("€/// a", 1), 1);
+        assert_eq!(detect_doc_comment("€/// a", 2), 2);
+        assert_eq!(detect_doc_comment("€/// a", 3), 8);
+        // Functions which do not return a position.
+        let orig = "€/* */";
+        assert_eq!(max_depth(orig, 1), None);
+        assert_eq!(max_depth(orig, 2), None);
+        assert_eq!(max_depth(orig, 3), Some(0));
+        assert!(status(orig, 1) == CommentScan::NotComment);
+        assert!(status(orig, 2) == CommentScan::NotComment);
+        assert!(status(orig, 3) == CommentScan::Terminated(8));
+    }
 
     #[test]
     fn detect_comment_inline() {
@@ -107,12 +268,22 @@ mod tests {
         assert_eq!(detect(orig, 2), 2);  // c//o
         assert_eq!(detect(orig, 3), 10); // //okxyz advance to the end
         assert_eq!(detect(orig, 4), 4);  // /okxyz
-        // With Windows line ending. The carriage return, '\r ', is treated like
-        // any other character.
+        // The entire input, with or without chars after the slashes.
+        assert_eq!(detect("//", 0), 2);
+        assert_eq!(detect("//x", 0), 3);
+        assert_eq!(detect("//xy", 0), 4);
+        // A newline as the very last char is not part of the comment.
+        assert_eq!(detect("//x\n", 0), 3);
+        assert_eq!(detect("//xy\r\n", 0), 4);
+        // With Windows line ending. The "\r\n" is not part of the comment.
         let orig = "abc//ok\r\nxyz";
         assert_eq!(detect(orig, 2), 2); // c//ok
-        assert_eq!(detect(orig, 3), 8); // //ok<CR> advance five places
+        assert_eq!(detect(orig, 3), 7); // //ok advance four places
         assert_eq!(detect(orig, 4), 4); // /ok<CR><NL>
+        // With a lone carriage return, which is treated like any other char.
+        let orig = "abc//o\rk\nxyz";
+        assert_eq!(detect(orig, 3), 8); // //o<CR>k advance five places
+        assert_eq!(detect("//ok\r", 0), 5); // //ok<CR> at end of input
         // Non-ascii.
         assert_eq!(detect("//€", 0), 5); // 3-byte non-ascii directly after //
         assert_eq!(detect("//abcd€", 0), 9); // 3-byte non-ascii after //abcd
@@ -139,7 +310,7 @@ mod tests {
         assert_eq!(detect(orig, 0), 2);  // //
         assert_eq!(detect(orig, 1), 1);  // /
         let orig = "//\n";
-        assert_eq!(detect(orig, 0), 3);  // //<NL>
+        assert_eq!(detect(orig, 0), 2);  // //
         assert_eq!(detect(orig, 1), 1);  // /<NL>
         let orig = "/**/";
         assert_eq!(detect(orig, 0), 4);  // /**/
@@ -179,6 +350,58 @@ mod tests {
         assert_eq!(detect(orig, 0), 0);
     }
 
+    #[test]
+    fn detect_multiline_comment_max_depth_as_expected() {
+        // No nesting.
+        assert_eq!(max_depth("/* */", 0), Some(0));
+        assert_eq!(max_depth("/**/", 0), Some(0));
+        // Single nesting.
+        assert_eq!(max_depth("/* /* */ */", 0), Some(1));
+        assert_eq!(max_depth("/* /* */ /* */ */", 0), Some(1)); // siblings
+        // Complex nesting.
+        let orig = "pre-/* 0 /* 1 */ 0 /* 2 /* 3 */ 2 */ 0 */-post";
+        assert_eq!(max_depth(orig, 4), Some(2));  // /* 0 ... 0 */
+        assert_eq!(max_depth(orig, 9), Some(0));  // /* 1 */
+        assert_eq!(max_depth(orig, 19), Some(1)); // /* 2 /* 3 */ 2 */
+        assert_eq!(max_depth("/*/*/ */ */", 0), Some(1)); // 3rd / edge case
+        assert_eq!(max_depth("/*/* */* */", 0), Some(1)); // 4th * edge case
+        // Unterminated, or not a multiline comment.
+        assert_eq!(max_depth("/* /* */", 0), None); // missing outer */
+        assert_eq!(max_depth("// x", 0), None); // inline comment
+        assert_eq!(max_depth("/* */", 1), None); // * */
+        // Will not panic.
+        assert_eq!(max_depth("", 0), None); // empty string
+        assert_eq!(max_depth("/", 0), None); // /
+        assert_eq!(max_depth("/*", 0), None); // /*
+        assert_eq!(max_depth("abc", 100), None); // way out of range
+        assert_eq!(max_depth("€", 1), None); // part way through the eurobytes
+    }
+
+    #[test]
+    fn detect_doc_comment_as_expected() {
+        // Doc comments.
+        assert_eq!(detect_doc_comment("/// doc\nx", 0), 7);
+        assert_eq!(detect_doc_comment("//! doc", 0), 7);
+        assert_eq!(detect_doc_comment("/** doc */x", 0), 10);
+        assert_eq!(detect_doc_comment("/*! doc */", 0), 10);
+        assert_eq!(detect_doc_comment("///", 0), 3);
+        assert_eq!(detect_doc_comment("/** /* nested */ */", 0), 19);
+        // Plain comments.
+        assert_eq!(detect_doc_comment("// normal", 0), 0);
+        assert_eq!(detect_doc_comment("//// not doc", 0), 0);
+        assert_eq!(detect_doc_comment("/* normal */", 0), 0);
+        assert_eq!(detect_doc_comment("/*** not doc */", 0), 0);
+        assert_eq!(detect_doc_comment("/**/", 0), 0);
+        // Not comments, or unterminated.
+        assert_eq!(detect_doc_comment("/", 0), 0);
+        assert_eq!(detect_doc_comment("/** unterminated", 0), 0);
+        assert_eq!(detect_doc_comment("", 0), 0);
+        assert_eq!(detect_doc_comment("///", 100), 100);
+        // `is_doc_comment()` only looks at the start of the snippet.
+        assert!(is_doc_comment("/// doc"));
+        assert!(! is_doc_comment("// normal /// not doc"));
+    }
+
     #[test]
     fn detect_comment_will_not_panic() {
         // Near the end of `orig`.
@@ -187,9 +410,9 @@ mod tests {
         assert_eq!(detect("xyz/", 3), 3); // /
         assert_eq!(detect("*", 0), 0); // *
         assert_eq!(detect("//", 0), 2); // //
-        assert_eq!(detect("//\n", 0), 3); // //<NL>
+        assert_eq!(detect("//\n", 0), 2); // //
         assert_eq!(detect("//abc", 0), 5); // //abc
-        assert_eq!(detect("//abc\n", 0), 6); // //abc<NL>
+        assert_eq!(detect("//abc\n", 0), 5); // //abc
         assert_eq!(detect("/*", 0), 0); // /*
         assert_eq!(detect("*/", 0), 0); // */
         assert_eq!(detect("/**/", 0), 4); // /**/
@@ -208,5 +431,43 @@ mod tests {
         assert_eq!(detect("/€", 0), 0); // non-ascii after /
         assert_eq!(detect("/*€", 0), 0); // non-ascii after /*
     }
-  
+
+    #[test]
+    fn starts_comment_as_expected() {
+        // Comments.
+        assert!(starts_comment("//", 0)); // //
+        assert!(starts_comment("/*", 0)); // /* even without the */
+        assert!(starts_comment("a//", 1)); // //
+        // Not comments.
+        assert!(! starts_comment("", 0)); // empty string
+        assert!(! starts_comment("/", 0)); // /
+        assert!(! starts_comment("/=", 0)); // /= is punctuation
+        assert!(! starts_comment("*/", 0)); // */
+        assert!(! starts_comment("abc", 3)); // 3 is after "c"
+        assert!(! starts_comment("€", 1)); // part way through the eurobytes
+        // Whenever `detect_comment()` finds a comment, so does
+        // `starts_comment()`.
+        let orig = "//a\n/*b*/ /*c /=d /";
+        for pos in 0..orig.len() {
+            if detect(orig, pos) != pos { assert!(starts_comment(orig, pos)) }
+        }
+    }
+
+    #[test]
+    fn detect_multiline_comment_status_as_expected() {
+        // Terminated.
+        assert!(status("/* a */", 0) == CommentScan::Terminated(7));
+        assert!(status("x/**/y", 1) == CommentScan::Terminated(5)); // /**/
+        assert!(status("/* a /* b */ */", 0) == CommentScan::Terminated(15));
+        // Unterminated.
+        assert!(status("/* a /* b */", 0) == CommentScan::Unterminated);
+        assert!(status("/*", 0) == CommentScan::Unterminated); // /*
+        assert!(status("/*/", 0) == CommentScan::Unterminated); // /*/
+        // Not comments.
+        assert!(status("// a", 0) == CommentScan::NotComment); // inline
+        assert!(status("/ *", 0) == CommentScan::NotComment); // / *
+        assert!(status("", 0) == CommentScan::NotComment); // empty string
+        assert!(status("abc", 100) == CommentScan::NotComment); // way out
+        assert!(status("€", 1) == CommentScan::NotComment); // eurobytes
+    }
 }
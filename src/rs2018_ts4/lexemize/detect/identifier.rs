@@ -1,8 +1,14 @@
 //! Detects an identifier, like `String` or `foo_bar`.
 
+use super::scanner::Scanner;
+
 /// Detects an identifier, like `String` or `foo_bar`.
 /// 
-/// @TODO raw identifiers, which have the `r#` prefix
+/// Raw identifiers, like `r#type`, are also detected. A raw identifier lets a
+/// keyword be used as a name.
+/// 
+/// Non-ascii identifiers, like `café` or `αβγ`, follow Unicode’s XID_Start and
+/// XID_Continue rules, approximately.
 /// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
@@ -13,41 +19,87 @@
 /// returns the character position after the identifier ends.  
 /// Otherwise, `detect_identifier()` just returns the `pos` argument.
 pub fn detect_identifier(orig: &str, pos: usize) -> usize {
-    // If the current char is past the last char in `orig`, bail out!
-    let len = orig.len();
-    if pos >= len { return pos }
-    // If the current char is not [_a-zA-Z], it does not begin an identifier.
-    let c = get_aot(orig, pos);
-    let starts_u = c == "_"; // true if the current char is an underscore
-    if ! starts_u && ! c.chars().all(char::is_alphabetic) { return pos }
-    // If the current char is the last in the input code:
-    if len == pos + 1 {
-        // A lone "_" is not an identifier, but anything ascii-alphabetic is.
-        return if starts_u { pos } else { len }
-    }
-    // If the next char is not an underscore, letter or digit:
-    let c = orig.get(pos+1..pos+2).unwrap_or("/");
-    if c != "_" && ! c.chars().all(char::is_alphanumeric) {
-        // A lone "_" is not an identifier. Else, advance after the first char.
-        return if starts_u { pos } else { pos + 1 }
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
+    // If `pos` begins "r#", it could begin a raw identifier, like "r#type".
+    if orig.get(pos..pos+2) == Some("r#") {
+        // If the "r#" is followed by an identifier, advance to the end of it.
+        // Otherwise, "r#" or "r#1" falls back to detecting just the "r".
+        let end = detect_plain_identifier(orig, pos + 2);
+        if end != pos + 2 { return end }
     }
-    // Step through each char, from `pos` to the end of the input code.
-    for i in pos+2..len-1 {
-        let c = get_aot(orig, i);
-        // If this char is not an underscore, letter or digit, advance to here.
-        if c != "_" && ! c.chars().all(char::is_alphanumeric) { return i }
+    detect_plain_identifier(orig, pos)
+}
+
+/// Detects an identifier in Rust 2015 code, like `String` or `async`.
+/// 
+/// Raw identifiers were introduced in the 2018 edition, so `r#type` is not
+/// detected. Just the `r` is detected, as an ordinary identifier.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2015 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a valid looking identifier, `detect_identifier_2015()`
+/// returns the character position after the identifier ends.  
+/// Otherwise, `detect_identifier_2015()` just returns the `pos` argument.
+pub fn detect_identifier_2015(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
+    detect_plain_identifier(orig, pos)
+}
+
+// Detects an identifier which does not have the `r#` prefix.
+fn detect_plain_identifier(orig: &str, pos: usize) -> usize {
+    // If `pos` is past the last char in `orig`, or is not on a character
+    // boundary, bail out!
+    let rest = match orig.get(pos..) { Some(rest) => rest, None => return pos };
+    // Step through each char, even if it’s non-ascii.
+    let mut chars = rest.char_indices();
+    // If the first char is not an underscore or XID_Start, it does not begin an
+    // identifier. For example, a digit, combining mark or emoji.
+    let first = match chars.next() { Some((_, c)) => c, None => return pos };
+    if first != '_' && ! is_xid_start(first) { return pos }
+    // Advance past each XID_Continue char which follows the first char.
+    let mut end = pos + first.len_utf8();
+    for (i, c) in chars {
+        if ! is_xid_continue(c) { break }
+        end = pos + i + c.len_utf8();
     }
-    // The last char in the input code is a valid identifier.
-    len
+    // A lone "_" is not an identifier.
+    if first == '_' && end == pos + 1 { pos } else { end }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, p: usize) -> &str { orig.get(p..p+1).unwrap_or("~") }
+// Rust identifiers start with a char which has the Unicode XID_Start property.
+// `char::is_alphabetic()` is a close approximation, which avoids a dependency.
+fn is_xid_start(c: char) -> bool { c.is_alphabetic() }
 
+// After the first char, identifiers can also contain digits, underscores and
+// combining marks, eg the U+0301 in "e\u{301}", which XID_Continue allows.
+fn is_xid_continue(c: char) -> bool {
+    c == '_'
+    || c.is_alphanumeric()
+    || ('\u{0300}'..='\u{036F}').contains(&c) // Combining Diacritical Marks
+    || ('\u{1AB0}'..='\u{1AFF}').contains(&c) // ...Extended
+    || ('\u{1DC0}'..='\u{1DFF}').contains(&c) // ...Supplement
+    || ('\u{20D0}'..='\u{20FF}').contains(&c) // ...for Symbols
+    || ('\u{FE20}'..='\u{FE2F}').contains(&c) // Combining Half Marks
+    || c == '\u{203F}' || c == '\u{2040}'     // Undertie, Character Tie
+}
 
 #[cfg(test)]
 mod tests {
     use super::detect_identifier as detect;
+    use super::detect_identifier_2015 as detect_2015;
+
+    #[test]
+    fn detect_identifier_off_char_boundary() {
+        assert_eq!(detect("€abc", 1), 1);
+        assert_eq!(detect("€abc", 2), 2);
+        assert_eq!(detect("€abc", 3), 6);
+        assert_eq!(detect_2015("€abc", 1), 1);
+        assert_eq!(detect_2015("€abc", 2), 2);
+        assert_eq!(detect_2015("€abc", 3), 6);
+    }
     
     #[test]
     fn detect_identifier_correct() {
@@ -64,6 +116,33 @@ mod tests {
         assert_eq!(detect(orig, 27), 29); // X2
         assert_eq!(detect(orig, 30), 31); // Y
         assert_eq!(detect(orig, 32), 33); // Z
+        // Raw identifiers.
+        let orig = "r#type r#match,r#fn r#async r#_x r#r";
+        assert_eq!(detect(orig, 0), 6);   // r#type
+        assert_eq!(detect(orig, 1), 1);   // # is invalid in identifiers
+        assert_eq!(detect(orig, 2), 6);   // type
+        assert_eq!(detect(orig, 7), 14);  // r#match
+        assert_eq!(detect(orig, 15), 19); // r#fn
+        assert_eq!(detect(orig, 20), 27); // r#async
+        assert_eq!(detect(orig, 28), 32); // r#_x
+        assert_eq!(detect(orig, 33), 36); // r#r at end of input
+    }
+
+    #[test]
+    fn detect_identifier_2015_correct() {
+        // Raw identifiers are not supported, so only the "r" is detected.
+        let orig = "r#type r#fn";
+        assert_eq!(detect_2015(orig, 0), 1); // r
+        assert_eq!(detect_2015(orig, 2), 6); // type
+        assert_eq!(detect_2015(orig, 7), 8); // r
+        // `async`, `await`, `dyn` and `try` are ordinary identifiers in 2015.
+        assert_eq!(detect_2015("async", 0), 5);
+        assert_eq!(detect_2015("dyn Trait", 0), 3);
+        // Otherwise, the same as `detect_identifier()`.
+        assert_eq!(detect_2015("_abc€", 0), 4); // _abc
+        assert_eq!(detect_2015("café", 0), 5); // non-ascii
+        assert_eq!(detect_2015("_", 0), 0); // _ cannot be the only char
+        assert_eq!(detect_2015("abc", 100), 100); // way out of range
     }
 
     #[test]
@@ -73,6 +152,13 @@ mod tests {
         assert_eq!(detect(orig, 0), 0); // _ cannot be the only char
         assert_eq!(detect(orig, 2), 2); // 2X is not a valid identifier
         assert_eq!(detect(orig, 5), 5); // _ cannot be the only char
+        // Raw identifiers which fall back to detecting just the "r".
+        let orig = "r# r#1 r#_ r#€";
+        assert_eq!(detect(orig, 0), 1);  // r# has no identifier after the #
+        assert_eq!(detect(orig, 3), 4);  // r#1 has a digit after the #
+        assert_eq!(detect(orig, 7), 8);  // r#_ cannot be just an underscore
+        assert_eq!(detect(orig, 11), 12); // r#€ has non-ascii after the #
+        assert_eq!(detect("r#", 0), 1); // r# at end of input
     }
 
     #[test]
@@ -94,4 +180,24 @@ mod tests {
         assert_eq!(detect("abcd€fg", 2), 4); // cd
     }
 
+    #[test]
+    fn detect_identifier_unicode() {
+        // Non-ascii, after an ascii first char.
+        let orig = "über naïve café αβγ;";
+        assert_eq!(detect(orig, 0), 5);   // über
+        assert_eq!(detect(orig, 6), 12);  // naïve
+        assert_eq!(detect(orig, 13), 18); // café
+        assert_eq!(detect(orig, 19), 25); // αβγ followed by punctuation
+        assert_eq!(detect("élan", 0), 5); // non-ascii first char
+        assert_eq!(detect("_ü", 0), 3); // underscore then non-ascii
+        assert_eq!(detect("x1_ß", 0), 5); // ascii then non-ascii at the end
+        // Combining marks.
+        assert_eq!(detect("e\u{301}t\u{301}", 0), 6); // accented with U+0301
+        assert_eq!(detect("\u{301}e", 0), 0); // leading combining mark
+        // Emoji and symbols.
+        assert_eq!(detect("😀", 0), 0); // emoji cannot start an identifier
+        assert_eq!(detect("a😀", 0), 1); // emoji cannot continue one
+        assert_eq!(detect("ab±", 0), 2); // neither can ±
+    }
+
 }
@@ -0,0 +1,145 @@
+//! Detects a keyword, like `fn` or `match`.
+
+use super::identifier::{detect_identifier,detect_identifier_2015};
+
+/// The strict and reserved keywords of Rust 2018, in alphabetical order.
+/// 
+/// doc.rust-lang.org/reference/keywords.html
+pub const RUST_2018_KEYWORDS: [&str; 51] = [
+    "Self", "abstract", "as", "async", "await", "become", "box", "break",
+    "const", "continue", "crate", "do", "dyn", "else", "enum", "extern",
+    "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Detects a keyword, like `fn` or `match`.
+/// 
+/// Raw identifiers, like `r#fn`, are never keywords. Weak keywords, like
+/// `union`, are only keywords in certain contexts, so they are not detected.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins an identifier which is a Rust 2018 keyword,
+/// `detect_keyword()` returns the character position after the keyword ends.
+/// Otherwise, `detect_keyword()` just returns the `pos` argument.
+pub fn detect_keyword(orig: &str, pos: usize) -> usize {
+    // `detect_identifier()` bails out if `pos` is part way through a char.
+    let end = detect_identifier(orig, pos);
+    if end != pos && is_keyword(&orig[pos..end]) { end } else { pos }
+}
+
+/// Detects a keyword in Rust 2015 code, like `fn` or `match`.
+/// 
+/// `async`, `await`, `dyn` and `try` became keywords in the 2018 edition, so
+/// they are not detected.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2015 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins an identifier which is a Rust 2015 keyword,
+/// `detect_keyword_2015()` returns the character position after it ends.
+/// Otherwise, `detect_keyword_2015()` just returns the `pos` argument.
+pub fn detect_keyword_2015(orig: &str, pos: usize) -> usize {
+    // `detect_identifier_2015()` bails out if `pos` is part way through a char.
+    let end = detect_identifier_2015(orig, pos);
+    if end == pos { return pos }
+    match &orig[pos..end] {
+        "async" | "await" | "dyn" | "try" => pos,
+        snippet => if is_keyword(snippet) { end } else { pos },
+    }
+}
+
+/// Checks whether an identifier is a Rust 2018 keyword.
+/// 
+/// ### Arguments
+/// * `snippet` An identifier, like `"fn"` or `"r#fn"`
+/// 
+/// ### Returns
+/// `true` if `snippet` is in [`RUST_2018_KEYWORDS`], or `false` otherwise.
+pub fn is_keyword(snippet: &str) -> bool {
+    RUST_2018_KEYWORDS.binary_search(&snippet).is_ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_keyword as detect;
+    use super::detect_keyword_2015 as detect_2015;
+    use super::{RUST_2018_KEYWORDS,is_keyword};
+
+    #[test]
+    fn detect_keyword_off_char_boundary() {
+        assert_eq!(detect("€fn", 1), 1);
+        assert_eq!(detect("€fn", 2), 2);
+        assert_eq!(detect("€fn", 3), 5);
+        assert_eq!(detect_2015("€fn", 1), 1);
+        assert_eq!(detect_2015("€fn", 2), 2);
+        assert_eq!(detect_2015("€fn", 3), 5);
+    }
+
+    #[test]
+    fn detect_keyword_correct() {
+        let orig = "fn main() { let mut x = Self::new(); }";
+        assert_eq!(detect(orig, 0), 2);   // fn
+        assert_eq!(detect(orig, 3), 3);   // main is not a keyword
+        assert_eq!(detect(orig, 12), 15); // let
+        assert_eq!(detect(orig, 16), 19); // mut
+        assert_eq!(detect(orig, 24), 28); // Self
+        assert_eq!(detect(orig, 30), 30); // new is not a keyword
+        // 2018 only keywords.
+        assert_eq!(detect("async", 0), 5);
+        assert_eq!(detect("dyn", 0), 3);
+        // Every keyword is detected in full.
+        for keyword in RUST_2018_KEYWORDS.iter() {
+            assert_eq!(detect(keyword, 0), keyword.len());
+        }
+    }
+
+    #[test]
+    fn detect_keyword_incorrect() {
+        assert_eq!(detect("function", 0), 0); // starts with "fn"
+        assert_eq!(detect("r#fn", 0), 0); // raw identifier
+        assert_eq!(detect("fn_", 0), 0); // continues with "_"
+        assert_eq!(detect("FN", 0), 0); // keywords are case-sensitive
+        assert_eq!(detect("union", 0), 0); // weak keyword
+        assert_eq!(detect("'static", 0), 0); // lifetime
+        assert_eq!(detect("_", 0), 0); // lone underscore
+    }
+
+    #[test]
+    fn detect_keyword_2015_correct() {
+        assert_eq!(detect_2015("fn", 0), 2);
+        assert_eq!(detect_2015("async", 0), 0); // 2018 only
+        assert_eq!(detect_2015("dyn", 0), 0); // 2018 only
+        assert_eq!(detect_2015("r#fn", 0), 0); // just the "r"
+        assert_eq!(detect_2015("r#fn", 2), 4); // fn
+    }
+
+    #[test]
+    fn detect_keyword_will_not_panic() {
+        assert_eq!(detect("", 0), 0); // empty string
+        assert_eq!(detect("if", 2), 2); // 2 is after "f"
+        assert_eq!(detect("if", 100), 100); // 100 is way out of range
+        assert_eq!(detect("€", 1), 1); // part way through the eurobytes
+        assert_eq!(detect_2015("", 0), 0); // empty string
+        assert_eq!(detect_2015("if", 100), 100); // 100 is way out of range
+    }
+
+    #[test]
+    fn rust_2018_keywords_are_sorted() {
+        // `is_keyword()` uses a binary search, so the array must be sorted.
+        let mut sorted = RUST_2018_KEYWORDS;
+        sorted.sort_unstable();
+        assert_eq!(sorted, RUST_2018_KEYWORDS);
+        assert!(is_keyword("Self"));
+        assert!(is_keyword("yield"));
+        assert!(! is_keyword("main"));
+    }
+}
@@ -0,0 +1,101 @@
+//! Detects a lifetime or label, like `'static` or `'outer`.
+
+use super::identifier::detect_identifier;
+use super::scanner::Scanner;
+
+/// Detects a lifetime or label, like `'static` or `'outer`.
+/// 
+/// A single quote followed by an identifier is a lifetime (or a label), unless
+/// the identifier is followed by another single quote. For example, `'a` is a
+/// lifetime, but `'a'` is a char. The anonymous lifetime, `'_`, is also
+/// detected.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a valid looking lifetime or label, `detect_lifetime()`
+/// returns the character position after it ends.  
+/// Otherwise, `detect_lifetime()` just returns the `pos` argument.
+pub fn detect_lifetime(orig: &str, pos: usize) -> usize {
+    let scanner = Scanner::new(orig, pos);
+    if ! scanner.is_at_boundary() { return pos }
+    // If the current char is not a single quote, it does not begin a lifetime.
+    if scanner.peek() != "'" { return pos }
+    // If the single quote is followed by an underscore and then a char which
+    // cannot continue an identifier, this could be the anonymous lifetime.
+    let end = if scanner.peek_at(1) == "_"
+        && detect_identifier(orig, pos+1) == pos + 1 { pos + 2 }
+        // Otherwise, the single quote must be followed by an identifier.
+        else { detect_identifier(orig, pos+1) };
+    // If no identifier follows the single quote, this is not a lifetime.
+    if end == pos + 1 { return pos }
+    // If the identifier is followed by a single quote, this is a char like 'a'
+    // (or something invalid, like 'ab'), but not a lifetime.
+    if scanner.ascii_or_tilde(end) == "'" { pos } else { end }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_lifetime as detect;
+
+    #[test]
+    fn detect_lifetime_off_char_boundary() {
+        assert_eq!(detect("€'a", 1), 1);
+        assert_eq!(detect("€'a", 2), 2);
+        assert_eq!(detect("€'a", 3), 5);
+    }
+
+    #[test]
+    fn detect_lifetime_correct() {
+        // Lifetimes.
+        let orig = "&'a str, &'static u8, &'_ T";
+        assert_eq!(detect(orig, 0), 0);   // & is not a lifetime
+        assert_eq!(detect(orig, 1), 3);   // 'a
+        assert_eq!(detect(orig, 2), 2);   // a has no single quote
+        assert_eq!(detect(orig, 10), 17); // 'static
+        assert_eq!(detect(orig, 23), 25); // '_
+        // Labels.
+        let orig = "'outer: loop { break 'outer; }";
+        assert_eq!(detect(orig, 0), 6);   // 'outer
+        assert_eq!(detect(orig, 21), 27); // 'outer
+        // Non-ascii.
+        assert_eq!(detect("'café ", 0), 6); // 'café
+        // At the end of input.
+        assert_eq!(detect("<'a", 1), 3); // 'a
+        assert_eq!(detect("<'_", 1), 3); // '_
+    }
+
+    #[test]
+    fn detect_lifetime_incorrect() {
+        // Chars.
+        assert_eq!(detect("'a'", 0), 0); // 'a' is a char
+        assert_eq!(detect("'_'", 0), 0); // '_' is a char
+        assert_eq!(detect("'\\n'", 0), 0); // '\n' is a char
+        assert_eq!(detect("'ab'", 0), 0); // 'ab' is not a valid char
+        // Not followed by an identifier.
+        assert_eq!(detect("'1", 0), 0); // '1 starts with a digit
+        assert_eq!(detect("' a", 0), 0); // ' a has a space
+        assert_eq!(detect("''", 0), 0); // ''
+    }
+
+    #[test]
+    fn detect_lifetime_will_not_panic() {
+        // Near the end of `orig`.
+        assert_eq!(detect("", 0), 0); // empty string
+        assert_eq!(detect("'", 0), 0); // '
+        assert_eq!(detect("'_", 0), 2); // '_
+        // Invalid `pos`.
+        assert_eq!(detect("abc", 2), 2); // 2 is before "c", so in range
+        assert_eq!(detect("abc", 3), 3); // 3 is after "c", so incorrect
+        assert_eq!(detect("abc", 4), 4); // 4 is out of range
+        assert_eq!(detect("abc", 100), 100); // 100 is way out of range
+        // Non-ascii.
+        assert_eq!(detect("€", 1), 1); // part way through the three eurobytes
+        assert_eq!(detect("'€", 0), 0); // non-ascii after '
+        assert_eq!(detect("'a€", 0), 2); // non-ascii after 'a
+    }
+
+}
@@ -1,9 +1,21 @@
 //! Functions for detecting `Lexeme`s in Rust 2018 code.
+//! 
+//! Each `detect_*()` function takes the original code, `orig`, and a position,
+//! `pos`. Callers may pass any `pos`, so if `pos` is part way through a
+//! non-ascii char, like the second byte of `€`, or is out of range, nothing is
+//! detected and `pos` is returned unchanged. Functions which return an `Option`
+//! return `None` instead. Each function checks this with
+//! `Scanner::is_at_boundary()`.
 
+pub mod attribute;
 pub mod character;
 pub mod comment;
 pub mod identifier;
+pub mod keyword;
+pub mod lifetime;
 pub mod number;
 pub mod punctuation;
+pub mod scanner;
+pub mod shebang;
 pub mod string;
 pub mod whitespace;
@@ -1,7 +1,40 @@
 //! Detects a number literal, like `12.34` or `0b100100`.
 
+use super::scanner::{Scanner,ascii_or_tilde as get_aot};
+
+/// The base of a number literal, eg `Hex` for `0xFF`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Radix {
+    /// A binary literal, which starts `0b`.
+    Bin,
+    /// An octal literal, which starts `0o`.
+    Oct,
+    /// A decimal literal, which is an integer or a float.
+    Dec,
+    /// A hexadecimal literal, which starts `0x`.
+    Hex,
+}
+
+/// Describes a number literal found by `detect_number_detailed()`.
+pub struct NumberInfo {
+    /// The character position after the end of the number, and any suffix.
+    pub end_pos: usize,
+    /// True if the number contains an exponent, eg the `e3` in `1.5e3`.
+    pub has_exponent: bool,
+    /// True if the number contains a decimal point, eg `1.5` or `2.`.
+    pub has_fraction: bool,
+    /// The base of the number.
+    pub radix: Radix,
+    /// The number’s type suffix, eg `Some("u8")` for `0xFFu8`, or `None`.
+    pub suffix: Option<&'static str>,
+}
+
 /// Detects a number literal, like `12.34` or `0b100100`.
 /// 
+/// A valid type suffix, like the `u8` in `0xFFu8` or the `f32` in `3.14f32`, is
+/// treated as part of the number. An invalid suffix, like the `u7` in `42u7`,
+/// is not.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// * `pos` The character position in `orig` to look at
@@ -11,14 +44,32 @@
 /// returns the character position after the end of the number.  
 /// Otherwise, `detect_number()` just returns the `pos` argument.
 pub fn detect_number(orig: &str, pos: usize) -> usize {
-    // If the current char is past the last char in `orig`, bail out!
+    match detect_number_detailed(orig, pos) {
+        Some(info) => info.end_pos,
+        None => pos,
+    }
+}
+
+/// Detects a number literal, and describes its radix, fraction and suffix.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a valid looking number literal, `detect_number_detailed()`
+/// returns a [`NumberInfo`] object describing it.  
+/// Otherwise, `detect_number_detailed()` returns `None`.
+pub fn detect_number_detailed(orig: &str, pos: usize) -> Option<NumberInfo> {
+    // If the current char is past the last char in `orig`, or `pos` is part
+    // way through a char, bail out!
     let len = orig.len();
-    if pos >= len { return pos }
+    if pos >= len || ! Scanner::new(orig, pos).is_at_boundary() { return None }
     let c = get_aot(orig, pos);
     // If the current char is not a digit, then it does not begin a char.
-    if c < "0" || c > "9" { return pos }
+    if ! ("0"..="9").contains(&c) { return None }
     // If the digit is the input code’s last character, we’re finished.
-    if len == pos + 1 { return len }
+    if len == pos + 1 { return Some(decimal_info(len, false, false, None)) }
     // If the digit at `pos` is not zero, this is a decimal number:
     if c != "0" { return detect_number_decimal(orig, pos, len) }
     // If the digit is zero, and the next char is "b", "x" or "o":
@@ -27,15 +78,253 @@ pub fn detect_number(orig: &str, pos: usize) -> usize {
         "b" => detect_number_binary(orig, pos, len),
         "x" => detect_number_hex(orig, pos, len),
         "o" => detect_number_octal(orig, pos, len),
+        // Radix prefixes must be lowercase, so "0B11", "0O34" and "0XAB" are
+        // just the decimal number "0", followed by an identifier. The
+        // transpiler can use `uppercase_radix_hint()` to point this out.
+        "B" | "O" | "X" => detect_number_decimal(orig, pos, len),
         // Otherwise, this is a decimal number which starts with a zero.
         _ => detect_number_decimal(orig, pos, len),
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, p: usize) -> &str { orig.get(p..p+1).unwrap_or("~") }
+/// Checks whether a number literal’s underscores are also valid in JavaScript.
+/// 
+/// Rust allows an underscore almost anywhere after the first digit, but
+/// JavaScript’s numeric separators are stricter. An underscore must sit
+/// between two digits, so it cannot be doubled, trail the number, or be next
+/// to a `.`, `e`, `E`, sign or radix prefix. It also cannot appear in a decimal
+/// integer part which starts with a zero, like `0_1`, because that looks like a
+/// legacy octal literal. Despite its name, this function checks integers too.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `true` if `pos` begins a number literal which JavaScript could parse, once
+/// any suffix is removed. For example `4_3.21e+10` returns `true`, but
+/// `43_.21e+10` returns `false`. Anything which is not a number literal also
+/// returns `false`.
+pub fn float_is_js_compatible(orig: &str, pos: usize) -> bool {
+    let info = match detect_number_detailed(orig, pos) {
+        Some(info) => info,
+        None => return false,
+    };
+    // The digits end where the suffix, if any, begins.
+    let end = info.end_pos - info.suffix.map_or(0, str::len);
+    let is_digit = |p: usize| match info.radix {
+        Radix::Hex => get_aot(orig, p).chars().all(|c| c.is_ascii_hexdigit()),
+        _ => get_aot(orig, p).chars().all(|c| c.is_ascii_digit()),
+    };
+    let is_underscore = |p: usize| get_aot(orig, p) == "_";
+    // A decimal integer part which starts with a zero cannot contain any
+    // underscores, so `0_1` and `00_1` are rejected, but `0.0_1` is not.
+    if info.radix == Radix::Dec && get_aot(orig, pos) == "0" {
+        let int_end = (pos..end).find(|&i| ! is_digit(i) && ! is_underscore(i))
+            .unwrap_or(end);
+        if (pos+1..int_end).any(is_underscore) { return false }
+    }
+    // Each underscore must have a digit on both sides. The first char is
+    // always a digit, so `i - 1` cannot underflow.
+    (pos+1..end).all(|i| ! is_underscore(i)
+        || (is_digit(i - 1) && i + 1 < end && is_digit(i + 1)))
+}
+
+/// The largest integer which JavaScript’s `number` type represents exactly.
+pub const JS_MAX_SAFE_INTEGER: u128 = 9_007_199_254_740_991; // 2^53 - 1
+
+/// Detects an integer literal which is too large for a JavaScript `number`.
+/// 
+/// TypeScript would silently lose precision on an integer above
+/// `Number.MAX_SAFE_INTEGER`, so the transpiler should emit a `BigInt` instead.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `true` if `pos` begins a binary, octal, decimal or hex integer literal whose
+/// value is larger than [`JS_MAX_SAFE_INTEGER`]. Floats, and anything which is
+/// not a number literal, return `false`.
+pub fn number_exceeds_js_safe_integer(orig: &str, pos: usize) -> bool {
+    match parse_integer(orig, pos) {
+        // Not an integer literal.
+        None => false,
+        // A number too large for a `u128` certainly exceeds the safe range.
+        Some(None) => true,
+        Some(Some(value)) => value > JS_MAX_SAFE_INTEGER,
+    }
+}
+
+/// Detects an integer literal which is too large for a Rust `u128`.
+/// 
+/// `detect_number()` is permissive, and accepts integers of any length. But
+/// Rust itself cannot parse an integer larger than `u128::MAX`, so neither
+/// should the transpiler.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `true` if `pos` begins a binary, octal, decimal or hex integer literal whose
+/// value is larger than `u128::MAX`. Floats, and anything which is not a
+/// number literal, return `false`.
+pub fn number_exceeds_u128(orig: &str, pos: usize) -> bool {
+    parse_integer(orig, pos) == Some(None)
+}
+
+// Parses the integer literal at `pos`, ignoring underscores and any suffix.
+// Returns `None` if `pos` does not begin an integer literal, or `Some(None)` if
+// its value is larger than `u128::MAX`.
+fn parse_integer(orig: &str, pos: usize) -> Option<Option<u128>> {
+    // If `pos` does not begin a number, or the number is a float, bail out!
+    let info = detect_number_detailed(orig, pos)?;
+    if info.has_fraction || info.has_exponent { return None }
+    if FLOAT_SUFFIXES.iter().any(|s| info.suffix == Some(s)) { return None }
+    // Get the digits, without the "0b", "0o" or "0x" prefix, or the suffix.
+    let start = if info.radix == Radix::Dec { pos } else { pos + 2 };
+    let end = info.end_pos - info.suffix.map_or(0, str::len);
+    let digits = orig[start..end].replace("_", "");
+    let radix = match info.radix {
+        Radix::Bin => 2,
+        Radix::Oct => 8,
+        Radix::Dec => 10,
+        Radix::Hex => 16,
+    };
+    Some(u128::from_str_radix(&digits, radix).ok())
+}
+
+/// Suggests a fix for a number literal with an uppercase radix prefix.
+/// 
+/// Rust’s radix prefixes are lowercase, so `detect_number()` only finds the
+/// leading `0` of `0B11`, `0O34` or `0XAB`. That is the correct behaviour, but
+/// the rest of the literal then becomes a confusing identifier, like `XAB`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a `0` followed by `B`, `O` or `X`, which would be a valid
+/// number literal if that letter were lowercase, a short message suggesting
+/// the lowercase prefix. Otherwise, `None`.
+pub fn uppercase_radix_hint(orig: &str, pos: usize) -> Option<&'static str> {
+    if get_aot(orig, pos) != "0" { return None }
+    let (lowercase, hint) = match get_aot(orig, pos + 1) {
+        "B" => ("0b", "Radix prefixes are lowercase, did you mean 0b?"),
+        "O" => ("0o", "Radix prefixes are lowercase, did you mean 0o?"),
+        "X" => ("0x", "Radix prefixes are lowercase, did you mean 0x?"),
+        _ => return None,
+    };
+    // Check whether the lowercase version would be a valid number.
+    let fixed = format!("{}{}", lowercase, &orig[pos+2..]);
+    if detect_number(&fixed, 0) == 0 { None } else { Some(hint) }
+}
+
+/// Quickly checks whether a number literal could begin at `pos`.
+/// 
+/// This is cheaper than `detect_number()`, because it only looks at the first
+/// few chars, rather than finding the end of the number.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `false` if `detect_number()` would definitely not find a number at `pos`.  
+/// Otherwise, `starts_number()` returns `true`.
+pub fn starts_number(orig: &str, pos: usize) -> bool {
+    // If the current char is not a digit, then it does not begin a number.
+    let c = get_aot(orig, pos);
+    if ! ("0"..="9").contains(&c) { return false }
+    // If the digit is not zero, or is the input code’s last character, then
+    // it begins a decimal number.
+    if c != "0" || orig.len() == pos + 1 { return true }
+    // If the zero is followed by "b", "o" or "x", the char after that must be
+    // an underscore or a digit in the appropriate radix.
+    let d = get_aot(orig, pos + 2);
+    match get_aot(orig, pos + 1) {
+        "b" => d == "_" || d == "0" || d == "1",
+        "o" => d == "_" || ("0"..="7").contains(&d),
+        "x" => d == "_" || d.chars().all(|c| c.is_ascii_hexdigit()),
+        // Otherwise, this is a decimal number which starts with a zero.
+        _ => true,
+    }
+}
+
+// doc.rust-lang.org/reference/tokens.html#integer-literals
+const INTEGER_SUFFIXES: [&str; 12] = [
+    "i8", "i16", "i32", "i64", "i128", "isize",
+    "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+// doc.rust-lang.org/reference/tokens.html#floating-point-literals
+const FLOAT_SUFFIXES: [&str; 2] = ["f32", "f64"];
+
+// Returns the one of `suffixes` which is found at `i`, eg "u8" or "f32".
+fn detect_suffix(
+    orig: &str,
+    i: usize,
+    suffixes: &[&'static str],
+) -> Option<&'static str> {
+    for suffix in suffixes.iter() {
+        let end = i + suffix.len();
+        // If `orig` does not contain this suffix at `i`, try the next one.
+        if orig.get(i..end) != Some(*suffix) { continue }
+        // Reject a suffix which runs on into other identifier chars, eg "u8x".
+        let c = get_aot(orig, end);
+        if c == "_" || c.chars().all(char::is_alphanumeric) { return None }
+        // Found a valid suffix.
+        return Some(suffix)
+    }
+    // No valid suffix was found.
+    None
+}
+
+// Describes a decimal number which ends at `end_pos`, excluding any suffix.
+fn decimal_info(
+    end_pos: usize,
+    has_fraction: bool,
+    has_exponent: bool,
+    suffix: Option<&'static str>,
+) -> NumberInfo {
+    NumberInfo {
+        end_pos: end_pos + suffix.map_or(0, str::len),
+        has_exponent,
+        has_fraction,
+        radix: Radix::Dec,
+        suffix,
+    }
+}
+
+// Describes a bin, oct or hex number whose digits end at `i`, consuming any
+// integer suffix, eg "u8". Returns `None` if an invalid suffix starts at `i`.
+fn consume_int_suffix(
+    orig: &str,
+    i: usize,
+    radix: Radix,
+) -> Option<NumberInfo> {
+    let suffix = detect_suffix(orig, i, &INTEGER_SUFFIXES);
+    // Reject the whole of 0b1u7, don’t just accept the 0b1 part. And reject
+    // the whole of 0o7f32, because floats can’t be binary, octal or hex.
+    if suffix.is_none() {
+        match get_aot(orig, i) { "i" | "u" | "f" => return None, _ => {} }
+    }
+    Some(NumberInfo {
+        end_pos: i + suffix.map_or(0, str::len),
+        has_exponent: false,
+        has_fraction: false,
+        radix,
+        suffix,
+    })
+}
 
-fn detect_number_binary(orig: &str, pos: usize, len: usize) -> usize {
+fn detect_number_binary(
+    orig: &str,
+    pos: usize,
+    len: usize,
+) -> Option<NumberInfo> {
     let mut has_digit = false; // binary literals must have at least one digit
     for i in pos+2..len { // +2, because we already found "0b"
         let c = get_aot(orig, i);
@@ -48,17 +337,22 @@ fn detect_number_binary(orig: &str, pos: usize, len: usize) -> usize {
         } else if (c >= "0" && c <= "9") || c == "." {
             // Reject the whole of 0b101021, don’t just accept the 0b1010 part.
             // And reject the whole of 0b11.1, don’t just accept the 0b11 part.
-            return pos
+            return None
         } else {
-            // Advance to the character after the binary number.
-            return if has_digit { i } else { pos }
+            // Advance to the character after the binary number and any suffix.
+            return if has_digit { consume_int_suffix(orig, i, Radix::Bin) }
+                else { None }
         }
     }
     // We’ve reached the end of the input string.
-    if has_digit { len } else { pos }
+    if has_digit { consume_int_suffix(orig, len, Radix::Bin) } else { None }
 }
 
-fn detect_number_decimal(orig: &str, pos: usize, len: usize) -> usize {
+fn detect_number_decimal(
+    orig: &str,
+    pos: usize,
+    len: usize,
+) -> Option<NumberInfo> {
     let mut has_dot = false; // decimal literals may have one "."
     let mut has_e = false; // decimal literals may have one "e" or "E"
     let mut pos_dot = 0; // helps detect invalid numbers like "1._2"
@@ -72,7 +366,7 @@ fn detect_number_decimal(orig: &str, pos: usize, len: usize) -> usize {
         // If the character is an underscore:
         if c == "_" {
             // Reject a number like "1._2", where the "." is followed by "_".
-            if has_dot && pos_dot == i { return pos }
+            if has_dot && pos_dot == i { return None }
             // Guard against a dangling underscore, eg "7.5e_".
             if has_e && pos_e == i { pos_eu = i + 1 }
 
@@ -81,10 +375,11 @@ fn detect_number_decimal(orig: &str, pos: usize, len: usize) -> usize {
             // Guard against a dangling plus or minus sign, eg "7.5e-".
             pos_s = i + 1
 
-        // If we haven’t found a decimal point yet, and this char is a dot:
-        } else if ! has_dot && c == "." {
+        // If we haven’t found a decimal point yet, and this char is a dot
+        // which does not begin a range, like the ".." in "0..=3":
+        } else if ! has_dot && c == "." && get_aot(orig, i+1) != "." {
             // Reject a number like "1e2.3", where the exponent contains a dot.
-            if has_e { return pos }
+            if has_e { return None }
             // Else, record that a dot was found, and the position after it.
             // We are being verbose by setting two variables here, but hopefully
             // it makes the code clearer, and perhaps run a little faster.
@@ -101,16 +396,32 @@ fn detect_number_decimal(orig: &str, pos: usize, len: usize) -> usize {
         } else if c < "0" || c > "9" {
             // We’ve reached a char which can’t be part of a valid number.
             // Numbers can’t end "e", "E", "+", "-", "e_" or "E_".
-            return if i == pos_e || i == pos_s || i == pos_eu { pos } else { i }
+            if i == pos_e || i == pos_s || i == pos_eu { return None }
+            // A suffix can’t directly follow a dot, eg "1.f32" is a field access.
+            if has_dot && pos_dot == i {
+                return Some(decimal_info(i, has_dot, has_e, None))
+            }
+            // Any decimal number may have a float suffix, like "f32"...
+            let mut suffix = detect_suffix(orig, i, &FLOAT_SUFFIXES);
+            // ...but only integers may have an integer suffix, like "u8".
+            if suffix.is_none() && ! has_dot && ! has_e {
+                suffix = detect_suffix(orig, i, &INTEGER_SUFFIXES)
+            }
+            return Some(decimal_info(i, has_dot, has_e, suffix))
         }
     }
 
     // We’ve reached the end of the input string.
     // Numbers can’t end "e", "E", "+", "-", "e_" or "E_".
-    if len == pos_e || len == pos_s || len == pos_eu { pos } else { len }
+    if len == pos_e || len == pos_s || len == pos_eu { None }
+        else { Some(decimal_info(len, has_dot, has_e, None)) }
 }
 
-fn detect_number_hex(orig: &str, pos: usize, len: usize) -> usize {
+fn detect_number_hex(
+    orig: &str,
+    pos: usize,
+    len: usize,
+) -> Option<NumberInfo> {
     let mut has_digit = false; // hex literals must have at least one digit
     for i in pos+2..len { // +2, because we already found "0x"
         let c = get_aot(orig, i);
@@ -122,17 +433,22 @@ fn detect_number_hex(orig: &str, pos: usize, len: usize) -> usize {
         // Otherwise, if this char is a point:
         } else if c == "." {
             // Reject the whole of 0xAB.C, don’t just accept the 0xAB part.
-            return pos
+            return None
         } else {
-            // Advance to the character after the hex number.
-            return if has_digit { i } else { pos }
+            // Advance to the character after the hex number and any suffix.
+            return if has_digit { consume_int_suffix(orig, i, Radix::Hex) }
+                else { None }
         }
     }
     // We’ve reached the end of the input string.
-    if has_digit { len } else { pos }
+    if has_digit { consume_int_suffix(orig, len, Radix::Hex) } else { None }
 }
 
-fn detect_number_octal(orig: &str, pos: usize, len: usize) -> usize {
+fn detect_number_octal(
+    orig: &str,
+    pos: usize,
+    len: usize,
+) -> Option<NumberInfo> {
     let mut has_digit = false; // octal literals must have at least one digit
     for i in pos+2..len { // +2, because we already found "0o"
         let c = get_aot(orig, i);
@@ -144,20 +460,34 @@ fn detect_number_octal(orig: &str, pos: usize, len: usize) -> usize {
         // Otherwise, if this char is a point:
         } else if c == "." {
             // Reject the whole of 0o56.7, don’t just accept the 0o56 part.
-            return pos
+            return None
         } else {
-            // Advance to the character after the octal number.
-            return if has_digit { i } else { pos }
+            // Advance to the character after the octal number and any suffix.
+            return if has_digit { consume_int_suffix(orig, i, Radix::Oct) }
+                else { None }
         }
     }
     // We’ve reached the end of the input string.
-    if has_digit { len } else { pos }
+    if has_digit { consume_int_suffix(orig, len, Radix::Oct) } else { None }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::detect_number as detect;
+    use super::{Radix,detect_number_detailed,float_is_js_compatible};
+    use super::{starts_number,uppercase_radix_hint};
+
+    #[test]
+    fn detect_number_off_char_boundary() {
+        assert_eq!(detect("€12", 1), 1);
+        assert_eq!(detect("€12", 2), 2);
+        assert_eq!(detect("€12", 3), 5);
+        // `detect_number_detailed()` returns `None`.
+        assert!(detect_number_detailed("€12", 1).is_none());
+        assert!(detect_number_detailed("€12", 2).is_none());
+        assert!(detect_number_detailed("€12", 3).is_some());
+    }
 
     #[test]
     fn detect_number_correct() {
@@ -206,6 +536,13 @@ mod tests {
         let orig = "123. 123.";
         assert_eq!(detect(orig, 0), 4); // 123. part way through input
         assert_eq!(detect(orig, 5), 9); // 123. reaches end of input
+        // Ranges. The ".." is punctuation, not a decimal point.
+        let orig = "0..=3 1..2 4.5..6 7e1..";
+        assert_eq!(detect(orig, 0), 1);   // 0
+        assert_eq!(detect(orig, 4), 5);   // 3
+        assert_eq!(detect(orig, 6), 7);   // 1
+        assert_eq!(detect(orig, 11), 14); // 4.5
+        assert_eq!(detect(orig, 18), 21); // 7e1
         // Float with exponent.
         let orig = "0e0 9E9 1e+2 4E-3 8E1+2 54.32E+10";
         assert_eq!(detect(orig, 0), 3);   // 0e0 is 0
@@ -223,6 +560,15 @@ mod tests {
         assert_eq!(detect(orig, 55), 65); // 43.21e+1_0 is ok .js
         assert_eq!(detect(orig, 66), 76); // 43.21e+10_ is invalid in .js
         assert_eq!(detect("43.21e_10", 0), 9); // 43.21e_10 is invalid in .js
+        // `float_is_js_compatible()` agrees with each ".js" comment above.
+        assert!(float_is_js_compatible(orig, 0));    // 4_3.21e+10
+        assert!(! float_is_js_compatible(orig, 11)); // 43_.21e+10
+        assert!(float_is_js_compatible(orig, 22));   // 43.2_1e+10
+        assert!(! float_is_js_compatible(orig, 33)); // 43.21_e+10
+        assert!(! float_is_js_compatible(orig, 44)); // 43.21e+_10
+        assert!(float_is_js_compatible(orig, 55));   // 43.21e+1_0
+        assert!(! float_is_js_compatible(orig, 66)); // 43.21e+10_
+        assert!(! float_is_js_compatible("43.21e_10", 0));
         // Hex.
         let orig = "0x09 0xA_b_ 0xAG 0x__C_";
         assert_eq!(detect(orig, 0), 4);   // 0x09
@@ -239,6 +585,28 @@ mod tests {
         assert_eq!(detect(orig, 5), 11);  // 0o7_3_
         assert_eq!(detect(orig, 12), 15); // the 0o7 part is accepted
         assert_eq!(detect(orig, 17), 23); // 0o__5_
+        // Integer suffixes.
+        let orig = "42u8 100usize 1_000i64 0xFFu8 1_0i32 0b1_u16 0o7i128";
+        assert_eq!(detect(orig, 0), 4);   // 42u8
+        assert_eq!(detect(orig, 5), 13);  // 100usize
+        assert_eq!(detect(orig, 14), 22); // 1_000i64
+        assert_eq!(detect(orig, 23), 29); // 0xFFu8
+        assert_eq!(detect(orig, 30), 36); // 1_0i32
+        assert_eq!(detect(orig, 37), 44); // 0b1_u16
+        assert_eq!(detect(orig, 45), 52); // 0o7i128
+        assert_eq!(detect("0o7usize", 0), 8); // 0o7usize
+        assert_eq!(detect("0xFfi128", 0), 8); // 0xFfi128, the "f" is a digit
+        assert_eq!(detect("0b1i64;", 0), 6); // 0b1i64 followed by punctuation
+        assert_eq!(detect("9isize", 0), 6); // 9isize at end of input
+        assert_eq!(detect("9u64;", 0), 4); // 9u64 followed by punctuation
+        // Float suffixes.
+        let orig = "3.14f32 2.0f64 1e10f32 3f32 1_000.5_f64 2.5E-3f32";
+        assert_eq!(detect(orig, 0), 7);   // 3.14f32
+        assert_eq!(detect(orig, 8), 14);  // 2.0f64
+        assert_eq!(detect(orig, 15), 22); // 1e10f32
+        assert_eq!(detect(orig, 23), 27); // 3f32 is a float
+        assert_eq!(detect(orig, 28), 39); // 1_000.5_f64
+        assert_eq!(detect(orig, 40), 49); // 2.5E-3f32
     }
 
     #[test]
@@ -254,6 +622,28 @@ mod tests {
         assert_eq!(detect(orig, 20), 20); // 0b___ is not a valid number
         // Decimal integer.
         // @TODO
+        // Incorrect integer suffixes.
+        let orig = "42u7 42q 1.5u8 1e2i32 42u8x 0xFFu 0b_u8";
+        assert_eq!(detect(orig, 0), 2);   // the 42 part is accepted
+        assert_eq!(detect(orig, 5), 7);   // the 42 part is accepted
+        assert_eq!(detect(orig, 9), 12);  // floats can’t have integer suffixes
+        assert_eq!(detect(orig, 15), 18); // floats can’t have integer suffixes
+        assert_eq!(detect(orig, 22), 24); // u8x is not a valid suffix
+        assert_eq!(detect(orig, 28), 28); // 0xFFu has a dangling "u"
+        assert_eq!(detect(orig, 34), 34); // 0b_u8 has no binary value
+        let orig = "0b1u7 0b10f32 0o7u 0xFi9 0o_i8 0x__u16";
+        assert_eq!(detect(orig, 0), 0);   // 0b1u7 has an invalid suffix
+        assert_eq!(detect(orig, 6), 6);   // floats can’t be binary
+        assert_eq!(detect(orig, 14), 14); // 0o7u has a dangling "u"
+        assert_eq!(detect(orig, 19), 19); // 0xFi9 has an invalid suffix
+        assert_eq!(detect(orig, 25), 25); // 0o_i8 has no octal value
+        assert_eq!(detect(orig, 31), 31); // 0x__u16 has no hex value
+        // Incorrect float suffixes.
+        let orig = "3.14f16 2.0f32x 1.f32 1ef32";
+        assert_eq!(detect(orig, 0), 4);   // f16 is not a suffix, 3.14 is ok
+        assert_eq!(detect(orig, 8), 11);  // f32x is not a suffix, 2.0 is ok
+        assert_eq!(detect(orig, 16), 18); // 1. then a field access
+        assert_eq!(detect(orig, 22), 22); // 1e has no exponent value
         // Incorrect float no exponent.
         let orig = "1.2.3 .12 0..1";
         assert_eq!(detect(orig, 0), 3); // 1.2
@@ -262,7 +652,7 @@ mod tests {
         assert_eq!(detect(orig, 5), 5); // space
         assert_eq!(detect(orig, 6), 6); // .12 is not a valid number
         assert_eq!(detect(orig, 7), 9); // 12
-        assert_eq!(detect(orig, 10), 12); // 0.
+        assert_eq!(detect(orig, 10), 11); // 0 followed by the range ..
         assert_eq!(detect(orig, 11), 11); // ..
         assert_eq!(detect(orig, 12), 12); // .1
         assert_eq!(detect(orig, 13), 14); // 1
@@ -344,6 +734,9 @@ mod tests {
         assert_eq!(detect("_1", 0), 0); // leading underscore is not allowed
         assert_eq!(detect("1_1", 0), 3); // 1_1
         assert_eq!(detect("1__1", 0), 4); // 1__1
+        assert_eq!(detect("5u", 0), 1); // dangling "u" is not a suffix
+        assert_eq!(detect("5u8", 0), 3); // 5u8
+        assert_eq!(detect("5i12", 0), 1); // i12 is not a suffix
         // Float, near the end of `orig`.
         assert_eq!(detect("1.", 0), 2); // 1.
         assert_eq!(detect("1.1", 0), 3); // 1.1
@@ -363,6 +756,11 @@ mod tests {
         assert_eq!(detect("1E+", 0), 0); // rejected, trailing sign after +
         assert_eq!(detect("1e-", 0), 0); // rejected, trailing sign after -
         assert_eq!(detect("1E-", 0), 0); // rejected, trailing sign after -
+        assert_eq!(detect("1f", 0), 1); // dangling "f" is not a suffix
+        assert_eq!(detect("1f32", 0), 4); // 1f32
+        assert_eq!(detect("1.5f6", 0), 3); // 1.5
+        assert_eq!(detect("1.5f64", 0), 6); // 1.5f64
+        assert_eq!(detect("1e1f64", 0), 6); // 1e1f64
         // Hex, near the end of `orig`.
         assert_eq!(detect("0x", 0), 0); // rejected, no hex value
         assert_eq!(detect("0X", 0), 1); // 0, "X" is not like "x"
@@ -416,4 +814,169 @@ mod tests {
         assert_eq!(detect("0o0€", 0), 3); // non-ascii after 0o0
     }
 
+    #[test]
+    fn detect_number_detailed_as_expected() {
+        // Hex, with a suffix.
+        let info = detect_number_detailed("0x1Fu8", 0).unwrap();
+        assert_eq!(info.end_pos, 6);
+        assert_eq!(info.radix, Radix::Hex);
+        assert_eq!(info.suffix, Some("u8"));
+        assert!(! info.has_fraction);
+        assert!(! info.has_exponent);
+        // Binary and octal, without suffixes.
+        let info = detect_number_detailed("0b1_0 ", 0).unwrap();
+        assert_eq!((info.end_pos, info.radix, info.suffix), (5, Radix::Bin, None));
+        let info = detect_number_detailed("0o17", 0).unwrap();
+        assert_eq!((info.end_pos, info.radix, info.suffix), (4, Radix::Oct, None));
+        // Decimal.
+        let info = detect_number_detailed("7", 0).unwrap();
+        assert_eq!((info.end_pos, info.radix, info.suffix), (1, Radix::Dec, None));
+        let info = detect_number_detailed("12usize", 0).unwrap();
+        assert_eq!((info.end_pos, info.suffix), (7, Some("usize")));
+        assert_eq!((info.has_fraction, info.has_exponent), (false, false));
+        let info = detect_number_detailed("1.5e3f64;", 0).unwrap();
+        assert_eq!((info.end_pos, info.suffix), (8, Some("f64")));
+        assert_eq!((info.has_fraction, info.has_exponent), (true, true));
+        let info = detect_number_detailed("3f32", 0).unwrap();
+        assert_eq!((info.end_pos, info.suffix), (4, Some("f32")));
+        assert_eq!((info.has_fraction, info.has_exponent), (false, false));
+        let info = detect_number_detailed("2.", 0).unwrap();
+        assert_eq!((info.end_pos, info.suffix), (2, None));
+        assert_eq!((info.has_fraction, info.has_exponent), (true, false));
+        // Not a number.
+        assert!(detect_number_detailed("x", 0).is_none());
+        assert!(detect_number_detailed("0b", 0).is_none());
+        assert!(detect_number_detailed("1e", 0).is_none());
+        assert!(detect_number_detailed("123", 3).is_none());
+    }
+
+    #[test]
+    fn float_is_js_compatible_as_expected() {
+        use super::float_is_js_compatible as compatible;
+        // Without underscores, and with a suffix.
+        assert!(compatible("1.5e3", 0));
+        assert!(compatible("1_000f32", 0)); // the suffix is not checked
+        assert!(! compatible("1_f32", 0)); // but the underscore before it is
+        // Doubled and trailing underscores.
+        assert!(! compatible("1__0", 0));
+        assert!(! compatible("10_", 0));
+        assert!(! compatible("1.5E-_3", 0));
+        // Other radixes.
+        assert!(compatible("0xA_b", 0));
+        assert!(! compatible("0x_Ab", 0)); // next to the "0x" prefix
+        assert!(compatible("0b1_0u8", 0));
+        assert!(! compatible("0o7_", 0));
+        // A decimal integer part which starts with a zero.
+        assert!(! compatible("0_1", 0));
+        assert!(! compatible("00_1", 0));
+        assert!(! compatible("0_1.5", 0));
+        assert!(! compatible("01_0e3", 0));
+        assert!(compatible("0.0_1", 0));
+        assert!(compatible("0e1_0", 0));
+        assert!(compatible("10_0", 0));
+        // Not numbers.
+        assert!(! compatible("_1", 0));
+        assert!(! compatible("", 0));
+        assert!(! compatible("1", 100));
+    }
+
+    #[test]
+    fn number_exceeds_js_safe_integer_as_expected() {
+        use super::number_exceeds_js_safe_integer as exceeds;
+        // Number.MAX_SAFE_INTEGER and one more than it, in each radix.
+        assert!(! exceeds("9007199254740991", 0));
+        assert!(exceeds("9_007_199_254_740_992u64", 0));
+        assert!(! exceeds("0x1F_FFFF_FFFF_FFFF", 0));
+        assert!(exceeds("0x20_0000_0000_0000", 0));
+        assert!(! exceeds("0o377777777777777777", 0));
+        assert!(exceeds("0o400000000000000000", 0));
+        assert!(! exceeds(&format!("0b{}", "1".repeat(53)), 0));
+        assert!(exceeds(&format!("0b1{}", "0".repeat(53)), 0));
+        // The same huge inputs as `detect_number_incorrect()`, which are too
+        // large even for a `u128`.
+        assert!(exceeds("1234567890123456789012345678901234567890", 0));
+        assert!(exceeds("0b1_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000", 0));
+        assert!(exceeds("0o12345671234567123456712345671234567123456712", 0));
+        assert!(exceeds("0x1234567890abcdefABCDEF1234567890a", 0));
+        // Floats, and anything which is not a number.
+        assert!(! exceeds("12345678901234567890.0", 0));
+        assert!(! exceeds("1e100", 0));
+        assert!(! exceeds("12345678901234567890f64", 0));
+        assert!(! exceeds("nope", 0));
+        assert!(! exceeds(" 12345678901234567890", 0));
+        assert!(exceeds(" 12345678901234567890", 1));
+    }
+
+    #[test]
+    fn number_exceeds_u128_as_expected() {
+        use super::number_exceeds_u128 as exceeds;
+        // u128::MAX and one more than it.
+        assert!(! exceeds("340282366920938463463374607431768211455", 0));
+        assert!(exceeds("340282366920938463463374607431768211456", 0));
+        assert!(! exceeds("340_282_366_920_938_463_463_374_607_431_768_211_455u128", 0));
+        assert!(exceeds("0x1_0000_0000_0000_0000_0000_0000_0000_0000", 0));
+        // The 40-digit number from `detect_number_incorrect()`.
+        assert!(exceeds("1234567890123456789012345678901234567890", 0));
+        // Floats, and things which are not numbers.
+        assert!(! exceeds("1234567890123456789012345678901234567890.0", 0));
+        assert!(! exceeds("1234567890123456789012345678901234567890f64", 0));
+        assert!(! exceeds("abc", 0));
+        assert!(! exceeds("", 0));
+    }
+
+    #[test]
+    fn starts_number_as_expected() {
+        // Numbers.
+        assert!(starts_number("0", 0)); // 0
+        assert!(starts_number("7.5", 0)); // 7.5
+        assert!(starts_number("0b1", 0)); // 0b1
+        assert!(starts_number("0o_7", 0)); // 0o_7
+        assert!(starts_number("0xF", 0)); // 0xF
+        assert!(starts_number("0B1", 0)); // the 0 part is accepted
+        assert!(starts_number("a12", 1)); // 12
+        // Not numbers.
+        assert!(! starts_number("", 0)); // empty string
+        assert!(! starts_number("a12", 0)); // a12 is an identifier
+        assert!(! starts_number(".5", 0)); // .5 is not a valid number
+        assert!(! starts_number("0b", 0)); // 0b has no binary value
+        assert!(! starts_number("0b2", 0)); // 0b2 is out of range
+        assert!(! starts_number("0o8", 0)); // 0o8 is out of range
+        assert!(! starts_number("0xG", 0)); // 0xG is out of range
+        assert!(! starts_number("123", 3)); // 3 is after "3"
+        assert!(! starts_number("€", 1)); // part way through the eurobytes
+        // Whenever `detect_number()` finds a number, so does `starts_number()`.
+        let orig = "0b1 0o7 0x1 0b2 0o8 0xg 1.5e3 0 42u8 .5 a1 _1 €1";
+        for pos in 0..orig.len() {
+            if detect(orig, pos) != pos { assert!(starts_number(orig, pos)) }
+        }
+    }
+
+    #[test]
+    fn uppercase_radix_hint_as_expected() {
+        // The lexer behaviour is unchanged, only the "0" is a number.
+        assert_eq!(detect("0XAB", 0), 1);
+        assert_eq!(detect("0B11", 0), 1);
+        assert_eq!(detect("0O34", 0), 1);
+        // The hint fires for an uppercase prefix.
+        assert_eq!(uppercase_radix_hint("0XAB", 0),
+            Some("Radix prefixes are lowercase, did you mean 0x?"));
+        assert_eq!(uppercase_radix_hint("= 0B11;", 2),
+            Some("Radix prefixes are lowercase, did you mean 0b?"));
+        assert_eq!(uppercase_radix_hint("0O3_4u8", 0),
+            Some("Radix prefixes are lowercase, did you mean 0o?"));
+        // But not for a lowercase prefix, which is already valid.
+        assert_eq!(uppercase_radix_hint("0xAB", 0), None);
+        assert_eq!(uppercase_radix_hint("0b11", 0), None);
+        assert_eq!(uppercase_radix_hint("0o34", 0), None);
+        // Nor if the lowercase prefix would still be invalid.
+        assert_eq!(uppercase_radix_hint("0XYZ", 0), None);
+        assert_eq!(uppercase_radix_hint("0B12", 0), None);
+        assert_eq!(uppercase_radix_hint("0X", 0), None);
+        // Nor for other numbers, or anything else.
+        assert_eq!(uppercase_radix_hint("0", 0), None);
+        assert_eq!(uppercase_radix_hint("10XAB", 0), None);
+        assert_eq!(uppercase_radix_hint("0E1", 0), None);
+        assert_eq!(uppercase_radix_hint("", 0), None);
+        assert_eq!(uppercase_radix_hint("€", 1), None);
+    }
 }
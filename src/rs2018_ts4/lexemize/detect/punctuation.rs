@@ -1,7 +1,18 @@
 //! Detects sequences of punctuation characters, like `;` or `>>=`.
 
+use super::identifier::detect_identifier;
+
 /// Detects sequences of punctuation characters, like `;` or `>>=`.
 /// 
+/// The longest known punctuation at `pos` is detected, so `..=` is detected as
+/// a single 3-char punctuation, rather than `..` followed by `=`.
+/// 
+/// Without any context, `>>` could be a shift operator, or two angle brackets
+/// closing nested generics, like in `Vec<Vec<u8>>`. So `>>` and `>>=` are
+/// always detected as operators, and the transpiler splits them when they end
+/// a type. The `::<` turbofish, like in `x.parse::<i32>()`, is detected as
+/// `::` followed by `<`.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// * `pos` The character position in `orig` to look at
@@ -11,101 +22,159 @@
 /// `detect_punctuation()` returns the character position after it ends.  
 /// Otherwise, `detect_punctuation()` just returns the `pos` argument.
 pub fn detect_punctuation(orig: &str, pos: usize) -> usize {
-    // If the current char is past the last char in `orig`, bail out!
-    let len = orig.len();
-    if pos >= len { return pos }
-    // If the current char is not present in PUNCTUATION_1, it is not, and does
-    // not begin, punctuation. That’s because PUNCTUATION_2 and PUNCTUATION_3
-    // all start with a PUNCTUATION_1 character.
-    let c1 = orig.get(pos..pos+1).unwrap_or("~");
-    if ! PUNCTUATION_1.contains(&c1) { return pos };
-
-    // If the current char is the last in the code, then it must be punctuation.
-    if len == pos + 1 { return len }
-
-    // Get two chars. If they are not a 2-char punctuation, then detect just
-    // the single-character punctuation.
-    let c2 = orig.get(pos..pos+2).unwrap_or("~");
-    if ! PUNCTUATION_2.contains(&c2) { return pos + 1 };
-
-    // If c2 reaches the end of the code, then c1 starts a 2-char punctuation.
-    if len == pos + 2 { return len }
-
-    // Get three chars. If they are not a 3-char punctuation, then detect just
-    // the two-character punctuation.
-    let c3 = orig.get(pos..pos+3).unwrap_or("~");
-    if ! PUNCTUATION_3.contains(&c3) { return pos + 2 };
-
-    // `detect_punctuation()` accepts any character at all after finding
-    // 3-char punctuation. It could also be the end-of-input.
-    pos + 3
+    // Punctuation is always ascii, so compare bytes. That way, a `pos` which is
+    // part way through a non-ascii char can never match.
+    let rest = match orig.as_bytes().get(pos..) {
+        Some(rest) => rest,
+        None => return pos,
+    };
+    // A lone underscore is a placeholder, like in `let _ = x;`. But if it begins
+    // an identifier, like `_x` or `__`, it is not punctuation.
+    if rest.first() == Some(&b'_') && detect_identifier(orig, pos) != pos {
+        return pos
+    }
+    // `RUST_PUNCTUATION` is sorted longest-first, so the first match is also
+    // the longest.
+    match RUST_PUNCTUATION.iter().find(|p| rest.starts_with(p.as_bytes())) {
+        Some(punctuation) => pos + punctuation.len(),
+        None => pos,
+    }
 }
 
-const PUNCTUATION_1: [&str; 28] = [
-    "'", // SingleQuote        Labels, Lifetimes
-    "_", // Underscore         Wildcard patterns, Inferred types, Unnamed...
-    "-", // Minus              Subtraction, Negation
-    ",", // Comma              Various separators
-    ";", // Semi               Terminator for situations, Array types
-    ":", // Colon              Various separators
-    "!", // Not                Bitwise and Logical NOT, Macro Calls, ...
-    "?", // Question           Question mark operator, Questionably sized, ...
-    ".", // Dot                Field access, Tuple index
-    "(", // OpenParentheses    Logic
-    ")", // CloseParentheses   Logic
-    "[", // OpenSquareBraces   Arrays
-    "]", // CloseSquareBraces  Arrays
-    "{", // OpenCurlyBraces    Blocks
-    "}", // CloseCurlyBraces   Blocks
-    "@", // At                 Subpattern binding
-    "*", // Star               Multiplication, Dereference, Raw Pointers, ...
-    "/", // Slash              Division
-    "&", // And                Bitwise / Logical AND, Borrow, References, ...
-    "#", // Pound              Attributes
-    "%", // Percent            Remainder
-    "^", // Caret              Bitwise and Logical XOR
-    "+", // Plus               Addition, Trait Bounds, Macro Kleene Matcher
-    "<", // Lt                 Less than, Generics, Paths
-    "=", // Eq                 Assignment, Attributes, Various type definitions
-    ">", // Gt                 Greater than, Generics, Paths
-    "|", // Or                 Bitwise / Logical OR, Closures, if let, ...
-    "$", // Dollar             Macros
-];
+/// Every punctuation in Rust 2018, sorted longest-first.
+/// 
+/// Some punctuation begins with shorter punctuation, like `<<=`, which begins
+/// with `<<` and `<`. Sorting the longest first means that the first match is
+/// always the longest. The unused `~` is not included, so it is detected as
+/// ‘Xtraneous’.
+/// 
+/// doc.rust-lang.org/reference/tokens.html#punctuation
+pub const RUST_PUNCTUATION: [&str; 52] = [
+    "...", // DotDotDot         Variadic functions, Range patterns
+    "..=", // DotDotEq          Inclusive Range, Range patterns
+    "<<=", // ShlEq             Shift Left assignment
+    ">>=", // ShrEq             Shift Right assignment, Nested Generics
 
-const PUNCTUATION_2: [&str; 20] = [
-    "-=", // MinusEq        Subtraction assignment
-    "->", // RArrow         Function return type, Closure return type, ...
-    "::", // PathSep        Path separator
-    "!=", // Ne             Not Equal
-    "..", // DotDot         Range, Struct expressions, Patterns
-    "*=", // StarEq         Multiplication assignment
-    "/=", // SlashEq        Division assignment
-    "&&", // AndAnd         Lazy AND, Borrow, References, Reference patterns
-    "&=", // AndEq          Bitwise And assignment
-    "%=", // PercentEq      Remainder assignment
-    "^=", // CaretEq        Bitwise XOR assignment
-    "+=", // PlusEq         Addition assignment
-    "<<", // Shl            Shift Left, Nested Generics
-    "<=", // Le             Less than or equal to
-    "==", // EqEq           Equal
-    "=>", // FatArrow       Match arms, Macros
-    ">=", // Ge             Greater than or equal to, Generics
-    ">>", // Shr            Shift Right, Nested Generics
-    "|=", // OrEq           Bitwise Or assignment
-    "||", // OrOr           Lazy OR, Closures
-];
+    "-=",  // MinusEq           Subtraction assignment
+    "->",  // RArrow            Function return type, Closure return type, ...
+    "::",  // PathSep           Path separator
+    "!=",  // Ne                Not Equal
+    "..",  // DotDot            Range, Struct expressions, Patterns
+    "*=",  // StarEq            Multiplication assignment
+    "/=",  // SlashEq           Division assignment
+    "&&",  // AndAnd            Lazy AND, Borrow, References, Reference patterns
+    "&=",  // AndEq             Bitwise And assignment
+    "%=",  // PercentEq         Remainder assignment
+    "^=",  // CaretEq           Bitwise XOR assignment
+    "+=",  // PlusEq            Addition assignment
+    "<<",  // Shl               Shift Left, Nested Generics
+    "<=",  // Le                Less than or equal to
+    "==",  // EqEq              Equal
+    "=>",  // FatArrow          Match arms, Macros
+    ">=",  // Ge                Greater than or equal to, Generics
+    ">>",  // Shr               Shift Right, Nested Generics
+    "|=",  // OrEq              Bitwise Or assignment
+    "||",  // OrOr              Lazy OR, Closures
 
-const PUNCTUATION_3: [&str; 4] = [
-    "...", // DotDotDot  Variadic functions, Range patterns
-    "..=", // DotDotEq   Inclusive Range, Range patterns
-    "<<=", // ShlEq      Shift Left assignment
-    ">>=", // ShrEq      Shift Right assignment, Nested Generics
+    "'",   // SingleQuote       Labels, Lifetimes
+    "_",   // Underscore        Wildcard patterns, Inferred types, Unnamed...
+    "-",   // Minus             Subtraction, Negation
+    ",",   // Comma             Various separators
+    ";",   // Semi              Terminator for situations, Array types
+    ":",   // Colon             Various separators
+    "!",   // Not               Bitwise and Logical NOT, Macro Calls, ...
+    "?",   // Question          Question mark operator, Questionably sized, ...
+    ".",   // Dot               Field access, Tuple index
+    "(",   // OpenParentheses   Logic
+    ")",   // CloseParentheses  Logic
+    "[",   // OpenSquareBraces  Arrays
+    "]",   // CloseSquareBraces Arrays
+    "{",   // OpenCurlyBraces   Blocks
+    "}",   // CloseCurlyBraces  Blocks
+    "@",   // At                Subpattern binding
+    "*",   // Star              Multiplication, Dereference, Raw Pointers, ...
+    "/",   // Slash             Division
+    "&",   // And               Bitwise / Logical AND, Borrow, References, ...
+    "#",   // Pound             Attributes
+    "%",   // Percent           Remainder
+    "^",   // Caret             Bitwise and Logical XOR
+    "+",   // Plus              Addition, Trait Bounds, Macro Kleene Matcher
+    "<",   // Lt                Less than, Generics, Paths
+    "=",   // Eq                Assignment, Attributes, Various type definitions
+    ">",   // Gt                Greater than, Generics, Paths
+    "|",   // Or                Bitwise / Logical OR, Closures, if let, ...
+    "$",   // Dollar            Macros
 ];
 
 
 #[cfg(test)]
 mod tests {
     use super::detect_punctuation as detect;
+    use super::RUST_PUNCTUATION;
+
+    #[test]
+    fn detect_punctuation_off_char_boundary() {
+        assert_eq!(detect("€+=", 1), 1);
+        assert_eq!(detect("€+=", 2), 2);
+        assert_eq!(detect("€+=", 3), 5);
+    }
+
+    #[test]
+    fn rust_punctuation_is_complete() {
+        // Each punctuation is detected to its full length, on its own and when
+        // followed by more code.
+        for punctuation in RUST_PUNCTUATION.iter() {
+            let len = punctuation.len();
+            assert_eq!(detect(punctuation, 0), len, "{}", punctuation);
+            let orig = format!(" {} x", punctuation);
+            assert_eq!(detect(&orig, 1), len + 1, "{}", punctuation);
+        }
+        // Sorted longest-first, with no duplicates.
+        assert!(RUST_PUNCTUATION.windows(2).all(|w| w[0].len() >= w[1].len()));
+        for (i, punctuation) in RUST_PUNCTUATION.iter().enumerate() {
+            assert!(! RUST_PUNCTUATION[i+1..].contains(punctuation));
+        }
+        // The complete set, apart from `~`.
+        assert_eq!(RUST_PUNCTUATION.len(), 52);
+        assert_eq!(detect("~", 0), 0);
+    }
+
+    #[test]
+    fn detect_punctuation_longest_match() {
+        assert_eq!(detect("<<=", 0), 3); // <<= not <<
+        assert_eq!(detect("<< ", 0), 2); // << not <
+        assert_eq!(detect("< <", 0), 1); // <
+        assert_eq!(detect(">>=", 0), 3); // >>= not >>
+        assert_eq!(detect("...", 0), 3); // ... not ..
+        assert_eq!(detect("..=", 0), 3); // ..= not ..
+        // A range, a struct update, a rest pattern and the removed `...`.
+        let orig = "0..=9 Foo { a, ..base } [x, ..] 0...9";
+        assert_eq!(detect(orig, 1), 4); // ..=
+        assert_eq!(detect(orig, 15), 17); // ..
+        assert_eq!(detect(orig, 28), 30); // ..
+        assert_eq!(detect(orig, 33), 36); // ...
+    }
+
+    #[test]
+    fn detect_punctuation_generics() {
+        // The turbofish is a path separator, then an angle bracket.
+        let orig = "Vec::<u8>::new()";
+        assert_eq!(detect(orig, 3), 5); // ::
+        assert_eq!(detect(orig, 5), 6); // <
+        assert_eq!(detect(orig, 8), 9); // >, not >::
+        assert_eq!(detect(orig, 9), 11); // ::
+        assert_eq!(detect("x.parse::<i32>()", 7), 9); // :: not ::<
+        // Nested generics end with a shift operator, which is left for the
+        // transpiler to split.
+        let orig = "Vec<Vec<u8>>";
+        assert_eq!(detect(orig, 3), 4); // <
+        assert_eq!(detect(orig, 10), 12); // >>
+        assert_eq!(detect("a >> b", 2), 4); // >>
+        assert_eq!(detect("a >>= b", 2), 5); // >>=
+        assert_eq!(detect("Vec<Vec<u8>>= v", 10), 13); // >>= here too
+        assert_eq!(detect("a::b", 1), 3); // ::
+        assert_eq!(detect(":::", 0), 2); // :: then :
+    }
 
     #[test]
     fn detect_punctuation_correct() {
@@ -117,6 +186,14 @@ mod tests {
         assert_eq!(detect(orig, 6), 7); // ' not considered part of the label
         assert_eq!(detect(orig, 13), 16); // ...
 
+        // Longest match wins.
+        let orig = "a..=3 b::c->d";
+        assert_eq!(detect(orig, 1), 4);   // ..= not .. followed by =
+        assert_eq!(detect(orig, 2), 3);   // .= is not punctuation, so just .
+        assert_eq!(detect(orig, 4), 4);   // 3 is a number, not punctuation
+        assert_eq!(detect(orig, 7), 9);   // :: not :
+        assert_eq!(detect(orig, 10), 12); // -> not -
+
         // Single at end.
         assert_eq!(detect(" '", 1), 2);
         assert_eq!(detect(" _", 1), 2);
@@ -280,6 +357,13 @@ mod tests {
         assert_eq!(detect(orig, 0), 0); // backtick is not Rust punctuation
         assert_eq!(detect(orig, 2), 3); // the = of =* is accepted
         assert_eq!(detect(orig, 5), 6); // the . of .:. is accepted
+        // Underscores which begin identifiers.
+        let orig = "_x __ _1 _é (_)";
+        assert_eq!(detect(orig, 0), 0);   // _x is an identifier
+        assert_eq!(detect(orig, 3), 3);   // __ is an identifier
+        assert_eq!(detect(orig, 6), 6);   // _1 is an identifier
+        assert_eq!(detect(orig, 9), 9);   // _é is an identifier
+        assert_eq!(detect(orig, 14), 15); // _ in (_) is a placeholder
     }
 
     #[test]
@@ -0,0 +1,136 @@
+//! A lightweight cursor over Rust code, shared by the `detect_*()` functions.
+
+/// A position in the original Rust code, with helpers for looking at the chars
+/// around it.
+/// 
+/// Each `detect_*()` function takes `orig` and `pos` arguments. Wrapping them
+/// in a `Scanner` means the functions share one implementation of the checks
+/// they all need, like reading an ascii char without panicking.
+/// 
+/// ### Fields
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::detect::scanner::Scanner;
+/// let scanner = Scanner::new("'a €", 1);
+/// assert_eq!(scanner.peek(), "a");
+/// assert_eq!(scanner.peek_at(1), " ");
+/// assert_eq!(scanner.peek_at(2), "~"); // non-ascii
+/// assert_eq!(scanner.peek_at(9), "~"); // out of range
+/// assert_eq!(scanner.ascii_or_tilde(0), "'");
+/// assert!(scanner.is_at_boundary());
+/// ```
+#[derive(Clone,Copy)]
+pub struct Scanner<'a> {
+    /// The original Rust code, assumed to conform to the 2018 edition.
+    pub orig: &'a str,
+    /// The character position in `orig` to look at.
+    pub pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    /// Creates a Scanner which looks at position `pos` in `orig`.
+    pub fn new(orig: &'a str, pos: usize) -> Self {
+        Scanner { orig, pos }
+    }
+    /// Returns the ascii char at the current position, or a tilde if that is
+    /// out of range or non-ascii.
+    pub fn peek(&self) -> &'a str {
+        self.ascii_or_tilde(self.pos)
+    }
+    /// Returns the ascii char `n` bytes after the current position, or a tilde
+    /// if that is out of range or non-ascii.
+    pub fn peek_at(&self, n: usize) -> &'a str {
+        self.ascii_or_tilde(self.pos + n)
+    }
+    /// Whether the current position is at the start of a char, or at the end
+    /// of `orig`. This is `false` part way through a non-ascii char, like `€`,
+    /// or past the end of `orig`.
+    pub fn is_at_boundary(&self) -> bool {
+        self.orig.is_char_boundary(self.pos)
+    }
+    /// Returns the ascii char at position `p` in `orig`, or a tilde if that is
+    /// out of range or non-ascii. Unlike `peek_at()`, `p` is not relative to
+    /// the current position.
+    pub fn ascii_or_tilde(&self, p: usize) -> &'a str {
+        ascii_or_tilde(self.orig, p)
+    }
+}
+
+/// Returns the ascii char at a position, or a tilde if invalid or non-ascii.
+/// 
+/// This is `Scanner::ascii_or_tilde()`, for code which does not need a
+/// `Scanner`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `p` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// A string slice containing one ascii char, or `"~"`.
+pub fn ascii_or_tilde(orig: &str, p: usize) -> &str {
+    // A non-ascii char is several bytes long, so slicing one byte of it fails.
+    orig.get(p..p+1).unwrap_or("~")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scanner_peek_as_expected() {
+        let scanner = Scanner::new("ab", 0);
+        assert_eq!(scanner.peek(), "a");
+        assert_eq!(scanner.peek_at(0), "a");
+        assert_eq!(scanner.peek_at(1), "b");
+        assert_eq!(scanner.peek_at(2), "~"); // at the end of `orig`
+        assert_eq!(Scanner::new("ab", 2).peek(), "~");
+        assert_eq!(Scanner::new("", 0).peek(), "~");
+        // Non-ascii, and part way through non-ascii.
+        assert_eq!(Scanner::new("€a", 0).peek(), "~");
+        assert_eq!(Scanner::new("€a", 1).peek(), "~");
+        assert_eq!(Scanner::new("€a", 0).peek_at(3), "a");
+        // Way out of range.
+        assert_eq!(Scanner::new("ab", 100).peek_at(100), "~");
+    }
+
+    #[test]
+    fn scanner_is_at_boundary_as_expected() {
+        assert!(Scanner::new("a€", 0).is_at_boundary());
+        assert!(Scanner::new("a€", 1).is_at_boundary());
+        assert!(! Scanner::new("a€", 2).is_at_boundary()); // inside "€"
+        assert!(! Scanner::new("a€", 3).is_at_boundary());
+        assert!(Scanner::new("a€", 4).is_at_boundary()); // the end of `orig`
+        assert!(! Scanner::new("a€", 5).is_at_boundary()); // out of range
+    }
+
+    #[test]
+    fn ascii_or_tilde_as_expected() {
+        let scanner = Scanner::new("x€y", 4);
+        assert_eq!(scanner.ascii_or_tilde(0), "x");
+        assert_eq!(scanner.ascii_or_tilde(2), "~");
+        assert_eq!(scanner.ascii_or_tilde(4), "y");
+        assert_eq!(ascii_or_tilde("x€y", 4), "y");
+        assert_eq!(ascii_or_tilde("x€y", 5), "~");
+    }
+
+    #[test]
+    fn detectors_which_use_scanner_are_unchanged() {
+        use super::super::comment::detect_comment;
+        use super::super::lifetime::detect_lifetime;
+        // Try every position, including part way through non-ascii chars, and
+        // past the end. These results are from before `Scanner` was added.
+        let orig = "€//é\n/*€*/ 'a€ '_ 'b' /";
+        let mut comments = vec![];
+        let mut lifetimes = vec![];
+        for pos in 0..orig.len() + 2 {
+            let end = detect_comment(orig, pos);
+            if end != pos { comments.push((pos, end)) }
+            let end = detect_lifetime(orig, pos);
+            if end != pos { lifetimes.push((pos, end)) }
+        }
+        assert_eq!(comments, vec![(3, 7), (8, 15)]);
+        assert_eq!(lifetimes, vec![(16, 18), (22, 24)]);
+    }
+}
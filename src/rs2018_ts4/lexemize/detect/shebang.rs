@@ -0,0 +1,99 @@
+//! Detects a shebang line, like `#!/usr/bin/env run-cargo-script`.
+
+/// Detects a shebang line, like `#!/usr/bin/env run-cargo-script`.
+/// 
+/// A shebang can only appear at the very start of the input code, or directly
+/// after a byte order mark (U+FEFF), which `lexemize()` skips. A `#!`
+/// followed by `[` begins an inner attribute, like `#![allow(unused)]`, so it
+/// is not a shebang.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` is the start of the input and begins a shebang, `detect_shebang()`
+/// returns the character position of the newline which ends it, or the end of
+/// the input.  
+/// Otherwise, `detect_shebang()` just returns the `pos` argument.
+pub fn detect_shebang(orig: &str, pos: usize) -> usize {
+    // The input starts after the byte order mark, if there is one.
+    let start = if orig.starts_with('\u{FEFF}') { 3 } else { 0 };
+    // If `pos` is not the start of the input, or it does not begin "#!", it
+    // does not begin a shebang.
+    if pos != start || ! orig[start..].starts_with("#!") { return pos }
+    // If the first non-whitespace char after the "#!" is "[", this is an inner
+    // attribute, like "#![feature(test)]" or "#! [allow(unused)]".
+    if orig[start+2..].trim_start().starts_with('[') { return pos }
+    // Advance to the newline, or the start of a Windows style "\r\n".
+    match orig.find('\n') {
+        Some(i) if i > 0 && &orig[i-1..i] == "\r" => i - 1,
+        Some(i) => i,
+        // No newline was found, so advance to the end of the input code.
+        None => orig.len(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_shebang as detect;
+
+    #[test]
+    fn detect_shebang_off_char_boundary() {
+        // Part way through a byte order mark, nothing is detected. After it,
+        // detection works as usual.
+        let orig = "\u{FEFF}#!x";
+        assert_eq!(detect(orig, 1), 1);
+        assert_eq!(detect(orig, 2), 2);
+        assert_eq!(detect(orig, 3), 6);
+    }
+
+    #[test]
+    fn detect_shebang_correct() {
+        // With newline.
+        let orig = "#!/usr/bin/env run-cargo-script\nfn main() {}";
+        assert_eq!(detect(orig, 0), 31); // advance to the newline
+        assert_eq!(detect(orig, 1), 1);  // ! is not the start of the input
+        // With Windows line ending. The "\r\n" is not part of the shebang.
+        assert_eq!(detect("#!/bin/x\r\nfn", 0), 8);
+        // Without newline.
+        assert_eq!(detect("#!/bin/x", 0), 8); // advance to the end
+        assert_eq!(detect("#!", 0), 2); // just #!
+        // After a byte order mark, which is three bytes long.
+        assert_eq!(detect("\u{FEFF}#!/bin/x\nfn", 3), 11);
+    }
+
+    #[test]
+    fn detect_shebang_incorrect() {
+        // Inner attributes.
+        assert_eq!(detect("#![feature(test)]", 0), 0); // #![
+        assert_eq!(detect("#! [allow(unused)]", 0), 0); // #! [
+        assert_eq!(detect("#!\n[x]", 0), 0); // the "[" is on the next line
+        // Not at the start of the input.
+        assert_eq!(detect("\n#!/bin/x", 1), 1); // on the second line
+        assert_eq!(detect(" #!/bin/x", 1), 1); // after a space
+        assert_eq!(detect("\u{FEFF}#!/bin/x", 0), 0); // the byte order mark
+        assert_eq!(detect("\u{FEFF}\u{FEFF}#!/bin/x", 6), 6); // after two
+        // Not a shebang.
+        assert_eq!(detect("#/bin/x", 0), 0); // no !
+        assert_eq!(detect("!#/bin/x", 0), 0); // wrong way round
+    }
+
+    #[test]
+    fn detect_shebang_will_not_panic() {
+        // Near the end of `orig`.
+        assert_eq!(detect("", 0), 0); // empty string
+        assert_eq!(detect("#", 0), 0); // #
+        // Invalid `pos`.
+        assert_eq!(detect("abc", 2), 2); // 2 is before "c", so in range
+        assert_eq!(detect("abc", 3), 3); // 3 is after "c", so incorrect
+        assert_eq!(detect("abc", 4), 4); // 4 is out of range
+        assert_eq!(detect("abc", 100), 100); // 100 is way out of range
+        // Non-ascii.
+        assert_eq!(detect("€", 1), 1); // part way through the three eurobytes
+        assert_eq!(detect("#!€", 0), 5); // non-ascii after #!
+        assert_eq!(detect("#!€\r\n", 0), 5); // non-ascii before "\r\n"
+    }
+
+}
@@ -1,9 +1,16 @@
 //! Detects a string literal, like `"Hello \"Rust\""` or `r#"Hello "Rust""#`.
 
+use super::scanner::{Scanner,ascii_or_tilde as get_aot};
+
 /// Detects a string literal, like `"Hello \"Rust\""` or `r#"Hello "Rust""#`.
 /// 
-/// @TODO `b` prefix, eg `b"Just the bytes"`
-/// @TODO `br` prefix, eg `br#"Just "the" bytes"#`
+/// Byte strings, like `b"Just the bytes"`, and raw byte strings, like
+/// `br#"Just "the" bytes"#`, are also detected. They may only contain ascii.
+/// 
+/// A raw string ends at the first double quote which is followed by as many
+/// hashes as it began with. So `r##"a"#b"##` is one string, because `"#` is too
+/// short to end it. Extra hashes are not part of the string, so `r#"a"##` ends
+/// before the last hash. Like rustc, at most 255 leading hashes are allowed.
 /// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
@@ -14,6 +21,7 @@
 /// the character position after the closing single quote (or hash).  
 /// Otherwise, `detect_string()` just returns the `pos` argument.
 pub fn detect_string(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
     // If the current char is the last in `orig`, it does not begin a string.
     let len = orig.len();
     if len < pos + 1 { return pos }
@@ -21,18 +29,221 @@ pub fn detect_string(orig: &str, pos: usize) -> usize {
     // If the current char is:
     match get_aot(orig, pos) {
         // A double quote, `pos` could begin a regular string.
-        "\"" => detect_regular_string(orig, pos, len),
+        "\"" => detect_regular_string(orig, pos, len, false),
         // A lowercase "r", `pos` could begin a raw string.
-        "r" => detect_raw_string(orig, pos, len),
+        "r" => detect_raw_string(orig, pos, len, false),
+        // A lowercase "b", `pos` could begin a byte string or raw byte string.
+        "b" => detect_byte_string(orig, pos, len),
         // Anything else, `pos` does not begin a string.
         _ => pos,
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, p: usize) -> &str { orig.get(p..p+1).unwrap_or("~") }
+/// Detects a C string literal, like `c"Hello"` or `cr#"Hello "Rust""#`.
+/// 
+/// C strings were added in the 2021 edition of Rust, so `detect_string()` does
+/// not detect them, and `c"Hello"` is lexemized as the identifier `c` followed
+/// by the string `"Hello"`. But C strings are often pasted into older code, so
+/// `Lexemizer::c_strings()` can opt in to this detector.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a valid looking C string literal, `detect_c_string()`
+/// returns the character position after the closing double quote (or hash).  
+/// Otherwise, `detect_c_string()` just returns the `pos` argument.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::detect::string::*;
+/// assert_eq!(detect_c_string(r#"c"Hi";"#, 0), 5);
+/// assert_eq!(detect_c_string(r#"c"Hi";"#, 1), 1); // use `detect_string()`
+/// ```
+pub fn detect_c_string(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
+    if get_aot(orig, pos) != "c" { return pos }
+    // If the "c" is followed by a double quote or an "r", use the regular or
+    // raw string detector function. Unlike a byte string, a C string can
+    // contain non-ascii chars.
+    let len = orig.len();
+    let end = match get_aot(orig, pos+1) {
+        "\"" => detect_regular_string(orig, pos+1, len, false),
+        "r" => detect_raw_string(orig, pos+1, len, false),
+        _ => return pos,
+    };
+    // If the char after the "c" does not begin a string, neither does `pos`.
+    if end == pos + 1 { pos } else { end }
+}
+
+/// Quickly checks whether a string literal could begin at `pos`.
+/// 
+/// This is cheaper than `detect_string()`, because it only looks at the first
+/// few chars, rather than finding the closing double quote.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// `false` if `detect_string()` would definitely not find a string at `pos`.  
+/// Otherwise, `starts_string()` returns `true`.
+pub fn starts_string(orig: &str, pos: usize) -> bool {
+    // Skip past the "b" prefix of a byte string, if there is one.
+    let i = if get_aot(orig, pos) == "b" { pos + 1 } else { pos };
+    match get_aot(orig, i) {
+        // A double quote could begin a regular string or byte string.
+        "\"" => true,
+        // An "r" followed by a double quote or hash could begin a raw string.
+        "r" => { let c = get_aot(orig, i + 1); c == "\"" || c == "#" },
+        // Anything else does not begin a string.
+        _ => false,
+    }
+}
+
+/// Detects a string literal which is never closed, like `"oops` or `r#"oops"`.
+/// 
+/// An unterminated string is not detected by `detect_string()`, so its opening
+/// double quote would otherwise become an ‘Xtraneous’ Lexeme. Everything after
+/// the opening double quote is part of the string, so it always runs to the
+/// end of the input. A byte string which is unterminated and also contains
+/// non-ascii chars is detected too.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `pos` begins a string literal with no closing double quote (or hashes),
+/// `detect_unterminated_string()` returns the length of `orig`.  
+/// Otherwise, `detect_unterminated_string()` just returns the `pos` argument.
+pub fn detect_unterminated_string(orig: &str, pos: usize) -> usize {
+    if ! Scanner::new(orig, pos).is_at_boundary() { return pos }
+    // A properly terminated string is not unterminated.
+    if detect_string(orig, pos) != pos { return pos }
+    let len = orig.len();
+    // Skip past the "b" prefix of a byte string, if there is one.
+    let i = if get_aot(orig, pos) == "b" { pos + 1 } else { pos };
+    // Allow non-ascii chars, so that a byte string is only detected here if it
+    // is actually unterminated.
+    let end = match get_aot(orig, i) {
+        "\"" => detect_regular_string(orig, i, len, false),
+        "r" => {
+            // A raw string must have zero or more hashes, then a double quote.
+            // So "r#type" is a raw identifier, not an unterminated string.
+            let mut j = i + 1;
+            while get_aot(orig, j) == "#" { j += 1 }
+            if get_aot(orig, j) != "\"" { return pos }
+            detect_raw_string(orig, i, len, false)
+        },
+        _ => return pos,
+    };
+    if end == i { len } else { pos }
+}
+
+/// Finds any invalid escapes in a regular string or byte string literal.
+/// 
+/// `detect_string()` is permissive, and skips any char after a backslash. This
+/// function is stricter. A valid escape is one of `\n`, `\r`, `\t`, `\\`,
+/// `\0`, `\'` and `\"`, or a backslash at the end of a line. Strings also
+/// allow `\x00` to `\x7F`, and `\u{0}` to `\u{10FFFF}` except for surrogates.
+/// Byte strings allow `\x00` to `\xFF`, but not `\u{...}`. Raw strings have
+/// no escapes, so they never contain an invalid one.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` where the string literal starts
+/// 
+/// ### Returns
+/// The position of the backslash which begins each invalid escape, in order.
+/// If `pos` does not begin a string literal, the vector is empty.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::detect::string::*;
+/// assert_eq!(find_invalid_string_escapes(r#"x = "\q\n\x80""#, 4), vec![5, 9]);
+/// ```
+pub fn find_invalid_string_escapes(orig: &str, pos: usize) -> Vec<usize> {
+    let mut invalid = vec![];
+    // If `pos` does not begin a string, there are no escapes to check.
+    let end = detect_string(orig, pos);
+    if end == pos { return invalid }
+    // Skip past the "b" prefix and the opening double quote. Raw strings, like
+    // r"\q", and raw byte strings, like br"\q", have no escapes.
+    let is_byte = get_aot(orig, pos) == "b";
+    let mut i = if is_byte { pos + 1 } else { pos };
+    if get_aot(orig, i) == "r" { return invalid }
+    i += 1;
+    // Step through each char, up to the closing double quote.
+    while i < end - 1 {
+        // Get this character, even if it’s non-ascii.
+        let mut j = i + 1;
+        while ! orig.is_char_boundary(j) { j += 1 }
+        // If this char is not a backslash, step forward to the next one.
+        if &orig[i..j] != "\\" { i = j; continue }
+        // Otherwise, check the escape, and step forward past it.
+        let (valid, escape_end) = match get_aot(orig, j) {
+            // One of Rust’s simple backslashable chars, or the end of a line.
+            "n" | "r" | "t" | "\\" | "0" | "\"" | "'" | "\n" => (true, j + 1),
+            "\r" if get_aot(orig, j+1) == "\n" => (true, j + 2),
+            // A 7-bit char code in a string, or an 8-bit byte in a byte string.
+            "x" => match orig.get(j+1..j+3) {
+                Some(digits) if digits.chars().all(|c| c.is_ascii_hexdigit())
+                    => (is_byte || digits.as_bytes()[0] <= b'7', j + 3),
+                _ => (false, j + 1),
+            },
+            // A unicode char code, which is not allowed in byte strings.
+            "u" => match parse_unicode_escape(orig, j + 1) {
+                Some(escape_end) => (! is_byte, escape_end),
+                None => (false, j + 1),
+            },
+            // Anything else, which may be non-ascii.
+            _ => (false, j),
+        };
+        if ! valid { invalid.push(i) }
+        i = escape_end;
+    }
+    invalid
+}
+
+// Parses the "{10abCD}" part of a "\u{10abCD}" escape, which starts at `p`.
+// Underscores may follow any digit, like in "\u{1_F600}". Returns the position
+// after the closing curly bracket, or `None` if there are not 1 to 6 hex
+// digits, or if the codepoint is above 10FFFF or a surrogate.
+fn parse_unicode_escape(orig: &str, p: usize) -> Option<usize> {
+    if get_aot(orig, p) != "{" { return None }
+    // Count the hex digits and underscores, which must start with a digit.
+    if ! orig.get(p+1..)?.starts_with(|c: char| c.is_ascii_hexdigit()) {
+        return None
+    }
+    let count = orig.get(p+1..)?.bytes()
+        .take_while(|b| b.is_ascii_hexdigit() || *b == b'_').count();
+    // Check that a closing curly bracket follows.
+    if get_aot(orig, p+1+count) != "}" { return None }
+    let digits: String = orig[p+1..p+1+count].chars()
+        .filter(|c| *c != '_').collect();
+    if digits.len() > 6 { return None }
+    // `char::from_u32()` rejects surrogates, and anything above 10FFFF.
+    std::char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?;
+    Some(p + 2 + count)
+}
 
-fn detect_regular_string(orig: &str, pos: usize, len: usize) -> usize {
+// doc.rust-lang.org/reference/tokens.html#byte-string-literals
+fn detect_byte_string(orig: &str, pos: usize, len: usize) -> usize {
+    // If the "b" is followed by a double quote or an "r", use the regular or
+    // raw string detector function, but only allow ascii chars.
+    let end = match get_aot(orig, pos+1) {
+        "\"" => detect_regular_string(orig, pos+1, len, true),
+        "r" => detect_raw_string(orig, pos+1, len, true),
+        _ => return pos,
+    };
+    // If the char after the "b" does not begin a string, neither does `pos`.
+    if end == pos + 1 { pos } else { end }
+}
+
+fn detect_regular_string(
+    orig: &str,
+    pos: usize,
+    len: usize,
+    ascii_only: bool,
+) -> usize {
     // Slightly hacky way to to skip forward while looping.
     let mut i = pos + 1;
     // Step through each char, from `pos` to the end of the original input code.
@@ -54,6 +265,8 @@ fn detect_regular_string(orig: &str, pos: usize, len: usize) -> usize {
             // Advance to the end of the double quote.
             return j
         }
+        // Byte strings may not contain non-ascii chars, even after a "\\".
+        if ascii_only && ! orig[i..j].is_ascii() { return pos }
         // Step forward, ready for the next iteration.
         i = j;
     }
@@ -61,14 +274,23 @@ fn detect_regular_string(orig: &str, pos: usize, len: usize) -> usize {
     pos
 }
 
+// rustc rejects a raw string which begins with more than 255 hashes.
+const MAX_RAW_STRING_HASHES: usize = 255;
+
 // doc.rust-lang.org/reference/tokens.html#raw-string-literals
-fn detect_raw_string(orig: &str, pos: usize, len: usize) -> usize {
+fn detect_raw_string(
+    orig: &str,
+    pos: usize,
+    len: usize,
+    ascii_only: bool,
+) -> usize {
     // If there are less than two chars after the "r", it cannot begin a string.
     if len < pos + 3 { return pos }
     // Slightly hacky way to to skip forward while looping.
     let mut i = pos + 1;
-    // Keep track of the number of leading hashes.
+    // Keep track of the number of leading hashes, and trailing hashes.
     let mut hashes = 0;
+    let mut trailing_hashes = 0;
     // Keep track of finding the opening and closing double quotes.
     let mut found_opening_dq = false;
     let mut found_closing_dq = false;
@@ -82,10 +304,29 @@ fn detect_raw_string(orig: &str, pos: usize, len: usize) -> usize {
         while !orig.is_char_boundary(j) { j += 1 }
         let c = &orig[i..j];
 
+        // If we have found what could be the closing double quote, and this is
+        // a trailing hash, increment the tally.
+        if found_closing_dq && c == "#" {
+            trailing_hashes += 1;
+            // If the trailing hashes balance the leading hashes:
+            if trailing_hashes == hashes {
+                // Valid raw string, advance to the end of the last hash.
+                return j
+            }
+            // Step forward, ready for the next iteration.
+            i = j;
+            continue
+        }
+        // Anything else means that the double quote was part of the string,
+        // eg the first double quote in r#"a"b"#.
+        found_closing_dq = false;
+
         // If we have not found the opening double quote yet:
         if ! found_opening_dq {
             // If this is the opening double quote, note that it’s been found.
             if c == "\"" {
+                // Too many leading hashes is not valid.
+                if hashes > MAX_RAW_STRING_HASHES { return pos }
                 found_opening_dq = true
             // Otherwise, if this is a leading hash, increment the tally.
             } else if c == "#" {
@@ -95,25 +336,6 @@ fn detect_raw_string(orig: &str, pos: usize, len: usize) -> usize {
                 return pos
             }
 
-        // Otherwise, if we have already found the closing double quote:
-        } else if found_closing_dq {
-            // If we are not expecting any more hashes:
-            if hashes == 0 {
-                // Valid raw string, advance to the end of the double quote.
-                return j
-            // Otherwise, if this is a trailing hash, decrement the tally.
-            } else if c == "#" {
-                hashes -= 1;
-                // If we are not expecting any more hashes:
-                if hashes == 0 {
-                    // Valid raw string, advance to the end of the double quote.
-                    return j
-                }
-            // Anything else is not valid for the end of a raw string.
-            } else {
-                return pos
-            }
-
         // Otherwise we are inside the main part of the string:
         } else {
             // If this char is a backslash:
@@ -126,9 +348,10 @@ fn detect_raw_string(orig: &str, pos: usize, len: usize) -> usize {
                 while !orig.is_char_boundary(j) { j += 1 }
             // If this char is a double quote:
             } else if c == "\"" {
-                // Note that the closing double quote has been found.
+                // Note that the closing double quote may have been found.
                 found_closing_dq = true;
-                // If we are not expecting any more hashes:
+                trailing_hashes = 0;
+                // If we are not expecting any hashes:
                 if hashes == 0 {
                     // Valid raw string, advance to the end of the double quote.
                     return j
@@ -136,20 +359,38 @@ fn detect_raw_string(orig: &str, pos: usize, len: usize) -> usize {
             }
         }
 
+        // Byte strings may not contain non-ascii chars, even after a "\\".
+        if ascii_only && ! orig[i..j].is_ascii() { return pos }
         // Step forward, ready for the next iteration.
         i = j;
     }
 
-    // Reached the end of the `orig` input string. Any leading hashes should have
-    // been balanced by trailing hashes.
-    if found_closing_dq && hashes == 0 { i } else { pos }
+    // Reached the end of the `orig` input string, without the leading hashes
+    // being balanced by trailing hashes, so this is not a raw string.
+    pos
 }
 
 
 #[cfg(test)]
 mod tests {
+    use super::detect_c_string;
     use super::detect_string as detect;
-    
+    use super::detect_unterminated_string as detect_unterminated;
+    use super::find_invalid_string_escapes as find_invalid;
+    use super::starts_string;
+
+    #[test]
+    fn detect_string_off_char_boundary() {
+        assert_eq!(detect("€\"a\"", 1), 1);
+        assert_eq!(detect("€\"a\"", 2), 2);
+        assert_eq!(detect("€\"a\"", 3), 6);
+        assert_eq!(detect_c_string("€c\"a\"", 1), 1);
+        assert_eq!(detect_c_string("€c\"a\"", 2), 2);
+        assert_eq!(detect_c_string("€c\"a\"", 3), 7);
+        assert_eq!(detect_unterminated("€\"a", 1), 1);
+        assert_eq!(detect_unterminated("€\"a", 2), 2);
+        assert_eq!(detect_unterminated("€\"a", 3), 5);
+    }
 
     #[test]
     fn detect_string_correct() {
@@ -163,10 +404,29 @@ mod tests {
         assert_eq!(detect("r#\"ok\"#", 0), 7);
         assert_eq!(detect("abcr###\"ok\"###xyz", 3), 14);
         assert_eq!(detect("abcr###\"ok\"####xyz", 3), 14);
+        assert_eq!(detect("r#\"a\"b\"#", 0), 8); // r#"a"b"#
+        assert_eq!(detect("r##\"a\"#b\"\"##", 0), 12); // r##"a"#b""##
+        assert_eq!(detect("r####\"x\"####", 0), 12); // r####"x"####
+        // An interior quote followed by fewer hashes does not end the string.
+        assert_eq!(detect("r####\"a\"##b\"###\"####", 0), 20);
+        assert_eq!(detect("r##\"a\"#\"##", 0), 10); // r##"a"#"##
+        // Extra trailing hashes are not part of the string.
+        assert_eq!(detect("r#\"a\"##", 0), 6); // r#"a"#
+        assert_eq!(detect("r\"a\"#", 0), 4); // r"a"
+        // The maximum of 255 leading hashes.
+        let hashes = "#".repeat(255);
+        let orig = format!("r{}\"a\"{}", hashes, hashes);
+        assert_eq!(detect(&orig, 0), orig.len());
         // Byte.
-        // @TODO
+        let orig = "-b\"hi\"-";
+        assert_eq!(detect(orig, 1), 6); // b"hi" advance five places
+        assert_eq!(detect(orig, 2), 6); // "hi"
+        assert_eq!(detect("b\"\\x80\\\"\"", 0), 9); // b"\x80\""
+        assert_eq!(detect("b\"\"", 0), 3); // b""
         // Byte raw.
-        // @TODO
+        assert_eq!(detect("br#\"a\"b\"#", 0), 9); // br#"a"b"#
+        assert_eq!(detect("-br\"ok\"-", 1), 7); // br"ok"
+        assert_eq!(detect("br##\"#\"#\"##", 0), 11); // br##"#"#"##
 
         // Escapes.
         // Escaped double quote.
@@ -187,6 +447,26 @@ mod tests {
         assert_eq!(detect("r\"\\0\\n\\t\"", 0), 9); // r"\0\n\t"
     }
 
+    #[test]
+    fn detect_string_at_end_of_input() {
+        // The closing double quote is the last byte of `orig`.
+        assert_eq!(detect("\"\"", 0), 2); // "" is the whole input
+        assert_eq!(detect("x = \"\"", 4), 6); // "" at the end
+        assert_eq!(detect("x = \"a\"", 4), 7); // "a" at the end
+        assert_eq!(detect("x = \"€\"", 4), 9); // "€" at the end
+        assert_eq!(detect("x = b\"\"", 4), 7); // b"" at the end
+        assert_eq!(detect("x = r\"\"", 4), 7); // r"" at the end
+        // The same strings, followed by a semicolon, end at the same place.
+        assert_eq!(detect("x = \"\";", 4), 6); // "";
+        assert_eq!(detect("x = \"a\";", 4), 7); // "a";
+        assert_eq!(detect("x = \"€\";", 4), 9); // "€";
+        assert_eq!(detect("x = b\"\";", 4), 7); // b"";
+        assert_eq!(detect("x = r\"\";", 4), 7); // r"";
+        // The opening double quote is the last byte of `orig`.
+        assert_eq!(detect("x = \"", 4), 4); // " has no end
+        assert_eq!(detect("x = \"a\"\"", 7), 7); // "a"" ends with a lone "
+    }
+
     #[test]
     fn detect_string_incorrect() {
         // Incorrect escapes, regular string.
@@ -198,10 +478,18 @@ mod tests {
         assert_eq!(detect("r###\" X in trailing hashes \"##X#", 0), 0);
         assert_eq!(detect("r###\" too few trailing hashes \"##", 0), 0);
         assert_eq!(detect("-r###\" no trailing hashes \"-", 1), 1);
+        assert_eq!(detect("r####\"x\"###", 0), 0); // r####"x"### one short
+        let hashes = "#".repeat(256); // one more than the maximum
+        assert_eq!(detect(&format!("r{}\"a\"{}", hashes, hashes), 0), 0);
         // Incorrect byte.
-        // @TODO
+        assert_eq!(detect("b\"€\"", 0), 0); // b"€" must be ascii
+        assert_eq!(detect("b\"\\€\"", 0), 0); // b"\€" must be ascii
+        assert_eq!(detect("b'A'", 0), 0); // b'A' is a byte char
+        assert_eq!(detect("bX\"A\"", 0), 0); // bX"A" has an X
         // Incorrect byte raw.
-        // @TODO
+        assert_eq!(detect("br\"€\"", 0), 0); // br"€" must be ascii
+        assert_eq!(detect("br#\"a\"", 0), 0); // br#"a" has no trailing hash
+        assert_eq!(detect("rb\"a\"", 0), 0); // rb"a" is the wrong way round
     }
 
     #[test]
@@ -280,6 +568,133 @@ mod tests {
         assert_eq!(detect("r#\"\\z\\€\"", 0), 0); // non-ascii in r#"\z\"
         assert_eq!(detect("r#\"\\z\\€\\\"\"#", 0), 13); // r#"\z\€\""#
         assert_eq!(detect("r##\"\\z\\€\\\"\"#", 0), 0); // missing hash at end
+        assert_eq!(detect("b", 0), 0); // b
+        assert_eq!(detect("b\"", 0), 0); // b"
+        assert_eq!(detect("b\"a", 0), 0); // b"a
+        assert_eq!(detect("b\"\\", 0), 0); // b"\
+        assert_eq!(detect("br", 0), 0); // br
+        assert_eq!(detect("br#", 0), 0); // br#
+        assert_eq!(detect("br#\"", 0), 0); // br#"
+        assert_eq!(detect("br#\"a\"", 0), 0); // br#"a"
+        assert_eq!(detect("b\"€", 0), 0); // non-ascii after b"
+        assert_eq!(detect("br\"€", 0), 0); // non-ascii after br"
+    }
+
+    #[test]
+    fn find_invalid_string_escapes_correct() {
+        let none: Vec<usize> = vec![];
+        // Valid escapes.
+        let orig = r#""\n\r\t\\\0\'\"\x00\x7F\u{0}\u{10FFFF}\u{00e9}""#;
+        assert_eq!(find_invalid(orig, 0), none);
+        assert_eq!(find_invalid("\"a\\\n  b\\\r\nc\"", 0), none); // line ends
+        assert_eq!(find_invalid(r#""\u{1_F600}\u{10_FF_FF__}""#, 0), none);
+        assert_eq!(find_invalid(r#"b"\x80\xFF\n""#, 0), none); // any byte
+        // Invalid escapes, in strings.
+        assert_eq!(find_invalid(r#""\q""#, 0), vec![1]); // unknown escape
+        assert_eq!(find_invalid(r#""\x80""#, 0), vec![1]); // above 7F
+        assert_eq!(find_invalid(r#""\x7""#, 0), vec![1]); // one hex digit
+        assert_eq!(find_invalid(r#""\u{110000}""#, 0), vec![1]); // too high
+        assert_eq!(find_invalid(r#""\u{D800}""#, 0), vec![1]); // surrogate
+        assert_eq!(find_invalid(r#""\u{}""#, 0), vec![1]); // no digits
+        assert_eq!(find_invalid(r#""\u{1234567}""#, 0), vec![1]); // 7 digits
+        assert_eq!(find_invalid(r#""\u{1_234_567}""#, 0), vec![1]); // 7 too
+        assert_eq!(find_invalid(r#""\u{_1}""#, 0), vec![1]); // _ first
+        assert_eq!(find_invalid(r#""\u{12""#, 0), vec![1]); // no closing }
+        assert_eq!(find_invalid(r#""\u{12" }"#, 0), vec![1]); // } is later
+        assert_eq!(find_invalid(r#""\u12""#, 0), vec![1]); // no opening {
+        assert_eq!(find_invalid("\"\\€\"", 0), vec![1]); // non-ascii
+        // Invalid escapes, in byte strings.
+        assert_eq!(find_invalid(r#"b"\u{41}""#, 0), vec![2]); // no unicode
+        assert_eq!(find_invalid(r#"b"\q""#, 0), vec![2]); // unknown escape
+        // Several invalid escapes, between valid ones.
+        assert_eq!(find_invalid(r#"x = "\n\q\\\x80\t\a""#, 4),
+            vec![7, 11, 17]);
+    }
+
+    #[test]
+    fn find_invalid_string_escapes_incorrect() {
+        let none: Vec<usize> = vec![];
+        assert_eq!(find_invalid(r#"r"\q""#, 0), none); // raw string
+        assert_eq!(find_invalid(r#"br"\q""#, 0), none); // raw byte string
+        assert_eq!(find_invalid(r#""\q"#, 0), none); // not a string
+        assert_eq!(find_invalid("'\\q'", 0), none); // a char
+        assert_eq!(find_invalid("", 0), none); // empty string
+        assert_eq!(find_invalid("\"€\"", 1), none); // part way through €
+        assert_eq!(find_invalid("\"\"", 100), none); // way out of range
+    }
+
+    #[test]
+    fn detect_unterminated_string_correct() {
+        let orig = "let s = \"oops";
+        assert_eq!(detect_unterminated(orig, 8), 13); // "oops
+        assert_eq!(detect_unterminated(orig, 9), 9); // oops
+        assert_eq!(detect_unterminated("\"", 0), 1); // " at the end of input
+        assert_eq!(detect_unterminated("\"a\\\"", 0), 4); // "a\" escaped
+        assert_eq!(detect_unterminated("x r\"a", 2), 5); // r"a
+        assert_eq!(detect_unterminated("r#\"a\"", 0), 5); // r#"a" no hash
+        assert_eq!(detect_unterminated("r##\"a\"#", 0), 7); // r##"a"# one hash
+        assert_eq!(detect_unterminated("b\"a", 0), 3); // b"a
+        assert_eq!(detect_unterminated("br#\"€", 0), 7); // br#"€
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn detect_unterminated_string_incorrect() {
+        assert_eq!(detect_unterminated("\"ok\"", 0), 0); // terminated
+        assert_eq!(detect_unterminated("r#\"ok\"#", 0), 0); // terminated
+        assert_eq!(detect_unterminated("b\"€\"", 0), 0); // terminated, but €
+        assert_eq!(detect_unterminated("r#type", 0), 0); // raw identifier
+        assert_eq!(detect_unterminated("r#", 0), 0); // r# at end of input
+        assert_eq!(detect_unterminated("b'A", 0), 0); // byte char
+        assert_eq!(detect_unterminated("'\"'", 0), 0); // char
+        assert_eq!(detect_unterminated("", 0), 0); // empty string
+        assert_eq!(detect_unterminated("\"€", 1), 1); // part way through €
+        assert_eq!(detect_unterminated("abc", 100), 100); // way out of range
+    }
+
+    #[test]
+    fn detect_c_string_as_expected() {
+        // C strings, and raw C strings.
+        assert_eq!(detect_c_string(r#"c"x""#, 0), 4);
+        assert_eq!(detect_c_string(r#"c"\"€""#, 0), 8); // non-ascii is fine
+        assert_eq!(detect_c_string(r#"cr"x""#, 0), 5);
+        assert_eq!(detect_c_string(r##"cr#"x"#"##, 0), 7);
+        assert_eq!(detect_c_string(r##"cr#"a"b"#;"##, 0), 9);
+        // Not C strings.
+        assert_eq!(detect_c_string(r#""x""#, 0), 0); // a regular string
+        assert_eq!(detect_c_string(r#"b"x""#, 0), 0); // a byte string
+        assert_eq!(detect_c_string("cr#x", 0), 0); // not a raw string
+        assert_eq!(detect_c_string("c\"x", 0), 0); // unterminated
+        assert_eq!(detect_c_string("cx", 0), 0);
+        // Will not panic.
+        assert_eq!(detect_c_string("", 0), 0);
+        assert_eq!(detect_c_string("c", 0), 0);
+        assert_eq!(detect_c_string("c\"", 0), 0);
+        assert_eq!(detect_c_string("cr", 0), 0);
+        assert_eq!(detect_c_string("c", 5), 5);
+    }
+
+    #[test]
+    fn starts_string_as_expected() {
+        // Strings.
+        assert!(starts_string("\"", 0)); // "
+        assert!(starts_string("r\"", 0)); // r"
+        assert!(starts_string("r#", 0)); // r#
+        assert!(starts_string("b\"", 0)); // b"
+        assert!(starts_string("br\"", 0)); // br"
+        assert!(starts_string("br#", 0)); // br#
+        // Not strings.
+        assert!(! starts_string("", 0)); // empty string
+        assert!(! starts_string("r", 0)); // r
+        assert!(! starts_string("rb\"", 0)); // rb"
+        assert!(! starts_string("b'A'", 0)); // b'A' is a byte char
+        assert!(! starts_string("br", 0)); // br
+        assert!(! starts_string("'A'", 0)); // 'A' is a char
+        assert!(! starts_string("abc", 3)); // 3 is after "c"
+        assert!(! starts_string("€", 1)); // part way through the eurobytes
+        // Whenever `detect_string()` finds a string, so does `starts_string()`.
+        let orig = "\"a\" r\"b\" r#\"c\"# b\"d\" br\"e\" br#\"f\"# rb\"g\"";
+        for pos in 0..orig.len() {
+            if detect(orig, pos) != pos { assert!(starts_string(orig, pos)) }
+        }
+    }
+}
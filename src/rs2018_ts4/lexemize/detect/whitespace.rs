@@ -1,11 +1,19 @@
 //! Detects a sequence of whitespace characters.
 
+use super::scanner::ascii_or_tilde as get_aot;
+
 /// Detects a sequence of whitespace characters.
 /// 
 /// Rust uses Pattern_White_Space, and treats it all the same.
 /// There is some debate on whether to simplify things, in future:
 /// internals.rust-lang.org/t/do-we-need-unicode-whitespace/9876
 /// 
+/// Many chars which look like whitespace are not Pattern_White_Space, so Rust
+/// does not treat them as whitespace, and neither does `detect_whitespace()`.
+/// These include the no-break space (U+00A0), the ideographic space (U+3000),
+/// the zero-width space (U+200B) and a byte order mark after the start of the
+/// input (U+FEFF). They are left for `lexemize()` to mark as ‘Xtraneous’.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// * `pos` The character position in `orig` to look at
@@ -57,13 +65,57 @@ pub fn detect_whitespace(orig: &str, pos: usize) -> usize {
     len
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, p: usize) -> &str { orig.get(p..p+1).unwrap_or("~") }
+/// Counts the logical newlines in a sequence of whitespace characters.
+/// 
+/// A `"\r\n"` pair is counted once, not twice. A lone `"\r"` or `"\n"`, and
+/// the Unicode Line Separator and Paragraph Separator, are each counted once.
+/// These are the line terminators of JavaScript, so this is how TypeScript
+/// tooling counts lines. `Lexemizer` uses it to count the lines in each
+/// `Whitespace` Lexeme.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` where the whitespace starts
+/// * `end` The character position after the whitespace ends, usually the
+///   value returned by `detect_whitespace()`
+/// 
+/// ### Returns
+/// The number of logical newlines between `pos` and `end`. If `pos` or `end`
+/// is out of range or not on a character boundary, zero is returned.
+pub fn count_newlines_in_whitespace(
+    orig: &str,
+    pos: usize,
+    end: usize,
+) -> usize {
+    let whitespace = match orig.get(pos..end) {
+        Some(whitespace) => whitespace,
+        None => return 0,
+    };
+    let mut count = 0;
+    let mut chars = whitespace.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // A "\r" followed by a "\n" is a single newline.
+            '\r' => { chars.next_if_eq(&'\n'); count += 1 },
+            '\n' | '\u{2028}' | '\u{2029}' => count += 1,
+            _ => {},
+        }
+    }
+    count
+}
 
 
 #[cfg(test)]
 mod tests {
     use super::detect_whitespace as detect;
+    use super::count_newlines_in_whitespace as count_newlines;
+
+    #[test]
+    fn detect_whitespace_off_char_boundary() {
+        assert_eq!(detect("€  ", 1), 1);
+        assert_eq!(detect("€  ", 2), 2);
+        assert_eq!(detect("€  ", 3), 5);
+    }
 
     #[test]
     fn detect_whitespace_correct() {
@@ -105,6 +157,26 @@ mod tests {
         assert_eq!(detect(orig, 5), 6); // <NL> advance to eoi
     }
 
+    #[test]
+    fn detect_whitespace_rejects_exotic_whitespace() {
+        // Unicode chars which are not Pattern_White_Space.
+        assert_eq!(detect("\u{3000}", 0), 0); // ideographic space
+        assert_eq!(detect("\u{200B}", 0), 0); // zero-width space
+        assert_eq!(detect("\u{200C}", 0), 0); // zero-width non-joiner
+        assert_eq!(detect("\u{200D}", 0), 0); // zero-width joiner
+        assert_eq!(detect("\u{2000}", 0), 0); // en quad
+        assert_eq!(detect("\u{2003}", 0), 0); // em space
+        assert_eq!(detect("\u{202F}", 0), 0); // narrow NBSP
+        assert_eq!(detect("\u{1680}", 0), 0); // ogham space mark
+        assert_eq!(detect("\u{FEFF}", 0), 0); // zero-width NBSP, or BOM
+        // They are not consumed after real whitespace, either. U+200B and
+        // U+2028 share their first two bytes, but only U+2028 is accepted.
+        assert_eq!(detect(" \u{3000} ", 0), 1);
+        assert_eq!(detect("\t\u{200B}\t", 0), 1);
+        assert_eq!(detect("\u{2028}\u{200B}", 0), 3);
+        assert_eq!(detect("\u{200E}\u{200B}\u{200F}", 0), 3);
+    }
+
     #[test]
     fn detect_whitespace_will_not_panic() {
         // Near the end of `orig` input code.
@@ -124,4 +196,27 @@ mod tests {
         assert_eq!(detect("\u{2029}€", 0), 3); // non-ascii after U+2029
     }
 
+    #[test]
+    fn count_newlines_in_whitespace_correct() {
+        let orig = "a \r\n\t\n\r\r\n b";
+        let end = detect(orig, 1);
+        assert_eq!(end, 10);
+        assert_eq!(count_newlines(orig, 1, end), 4); // <CR><NL>, <NL>, ...
+        assert_eq!(count_newlines(orig, 3, end), 4); // <NL> counted alone
+        assert_eq!(count_newlines(orig, 1, 3), 1); // lone <CR> at the end
+        assert_eq!(count_newlines("\n\r", 0, 2), 2); // <NL><CR> is two
+        assert_eq!(count_newlines("\r\r", 0, 2), 2); // lone <CR>s
+        assert_eq!(count_newlines(" \t ", 0, 3), 0); // no newlines
+        // Unicode line separators, but not "next line".
+        let orig = "\u{2028}\u{2029}\u{0085}\r\n";
+        assert_eq!(count_newlines(orig, 0, detect(orig, 0)), 3);
+    }
+
+    #[test]
+    fn count_newlines_in_whitespace_will_not_panic() {
+        assert_eq!(count_newlines("", 0, 0), 0); // empty string
+        assert_eq!(count_newlines("\n", 0, 100), 0); // `end` out of range
+        assert_eq!(count_newlines("\n", 1, 0), 0); // `end` before `pos`
+        assert_eq!(count_newlines("\u{2028}", 1, 3), 0); // `pos` mid-char
+    }
 }
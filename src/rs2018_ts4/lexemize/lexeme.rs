@@ -1,21 +1,36 @@
-//! An enum and a struct used by `lexemize()`.
+//! An enum and some structs used by `lexemize()`.
 
 use std::fmt;
+use std::ops::Range;
 
-/// The edition of Rust that the input code is written in.
-#[derive(Clone,Copy,PartialEq)]
+/// The category of a Lexeme, like `Identifier` or `Punctuation`.
+/// 
+/// `LexemeKind` can be used as a `HashMap` key, and sorted. Its order is the
+/// order that the variants are declared in, which is alphabetical.
+#[derive(Clone,Copy,Debug,Eq,Hash,Ord,PartialEq,PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LexemeKind {
+    /// 
+    Attribute,
     /// 
     Character,
     /// 
     Comment,
     /// 
+    DocComment,
+    /// 
     Identifier,
     /// 
+    Keyword,
+    /// 
+    Lifetime,
+    /// 
     Number,
     /// 
     Punctuation,
     /// 
+    Shebang,
+    /// 
     String,
     /// 
     Whitespace,
@@ -24,14 +39,36 @@ pub enum LexemeKind {
 }
 
 impl LexemeKind {
-    /// @TODO impl fmt::Display for LexemeKind
-    pub fn to_string(&self) -> &str {
+    /// Every `LexemeKind`, in alphabetical order.
+    pub const ALL: [LexemeKind; 13] = [
+        Self::Attribute,
+        Self::Character,
+        Self::Comment,
+        Self::DocComment,
+        Self::Identifier,
+        Self::Keyword,
+        Self::Lifetime,
+        Self::Number,
+        Self::Punctuation,
+        Self::Shebang,
+        Self::String,
+        Self::Whitespace,
+        Self::Xtraneous,
+    ];
+
+    /// Returns the name of the `LexemeKind`, without allocating a `String`.
+    pub fn as_str(&self) -> &'static str {
         match self {
+            Self::Attribute   => "Attribute",
             Self::Character   => "Character",
             Self::Comment     => "Comment",
+            Self::DocComment  => "DocComment",
             Self::Identifier  => "Identifier",
+            Self::Keyword     => "Keyword",
+            Self::Lifetime    => "Lifetime",
             Self::Number      => "Number",
             Self::Punctuation => "Punctuation",
+            Self::Shebang     => "Shebang",
             Self::String      => "String",
             Self::Whitespace  => "Whitespace",
             Self::Xtraneous   => "Xtraneous",
@@ -39,26 +76,311 @@ impl LexemeKind {
     }
 }
 
-///
-pub struct Lexeme {
+impl fmt::Display for LexemeKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.pad(self.as_str())
+    }
+}
+
+/// A snippet of Rust code, and its category.
+/// 
+/// The lifetime `'a` is the lifetime of the original Rust code, `orig`, which
+/// was passed to `lexemize()`. Each `snippet` borrows from `orig`, so no
+/// allocation happens per Lexeme.
+/// 
+/// With the `serde` feature, a Lexeme can be serialized, and its `kind` is
+/// serialized as its name, like `"Number"`. Because `snippet` is borrowed, it
+/// can only be deserialized from JSON which contains no escape sequences.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lexeme<'a> {
+    /// The character position within the line where the Lexeme starts. The
+    /// first column is 1.
+    pub column: usize,
     /// Category of the Lexeme.
     pub kind: LexemeKind,
+    /// The line number where the Lexeme starts. The first line is 1.
+    pub line_number: usize,
     /// The position that the Lexeme starts, relative to the start of `orig`.
     /// Zero indexed.
     pub pos: usize,
+    /// The part of `orig` which the Lexeme covers.
+    pub snippet: &'a str,
+}
+
+impl Lexeme<'_> {
+    /// Returns the byte range of the Lexeme within `orig`, so that
+    /// `&orig[lexeme.text_range()]` is the Lexeme’s `snippet`.
+    /// 
+    /// If the `snippet` is empty, which `lexemize()` never produces, the range
+    /// is `pos..pos`.
+    pub fn text_range(&self) -> Range<usize> {
+        self.pos..self.pos + self.snippet.len()
+    }
+
+    /// Formats the Lexeme on one line, like `to_string()`, but with the
+    /// options in `opts`.
+    pub fn to_string_with(&self, opts: &DisplayOpts) -> String {
+        // Render newlines, tabs and carriage returns readably, if required.
+        // The `snippet` itself is not altered.
+        let snippet = if opts.escape_control_chars {
+            self.snippet
+                .replace("\n", "<NL>")
+                .replace("\t", "<TB>")
+                .replace("\r", "<CR>")
+        } else {
+            self.snippet.into()
+        };
+        format!("{: <kw$} {: >pw$}  {}", self.kind, self.pos, snippet,
+            kw = opts.kind_width, pw = opts.pos_width)
+        //        |||
+        //        ||+-- target width is `pos_width` characters
+        //        |+--- align right
+        //        +---- fill with spaces
+    }
+
+    /// Formats the Lexeme as a JSON object, without needing the `serde`
+    /// feature.
     /// 
-    pub snippet: String,
+    /// The output matches what `serde_json` produces with the `serde` feature,
+    /// like `{"column":1,"kind":"Number","line_number":1,"pos":0,`
+    /// `"snippet":"4"}`.
+    pub fn to_json(&self) -> String {
+        format!("{{\"column\":{},\"kind\":\"{}\",\"line_number\":{},\
+            \"pos\":{},\"snippet\":\"{}\"}}", self.column, self.kind,
+            self.line_number, self.pos, escape_json(self.snippet))
+    }
+}
+
+// Escapes a snippet for use inside a JSON string, like `serde_json` does. So
+// double quotes, backslashes and control chars are escaped, but non-ascii
+// chars are not.
+fn escape_json(snippet: &str) -> String {
+    let mut out = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            // Other control chars, like U+0000, use a unicode escape.
+            c if (c as u32) < 0x20 =>
+                out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl fmt::Display for Lexeme<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.to_string_with(&DisplayOpts::new()))
+    }
+}
+
+/// Controls how `Lexemes::to_string_with()` formats Lexemes.
+/// 
+/// `DisplayOpts::new()` gives the same format as `to_string()`. Use
+/// `eoi_marker()`, `escape_control_chars()`, `kind_width()` and `pos_width()`
+/// to change it, for example to embed lexer output in other tooling.
+/// ```
+/// # use opinionated_rust_to_typescript::lexer::{lexemize,DisplayOpts};
+/// let opts = DisplayOpts::new().eoi_marker(None).kind_width(8);
+/// assert_eq!(lexemize("a\n").to_string_with(&opts),
+///     "Lexemes found: 2\n\
+///      Identifier    0  a\n\
+///      Whitespace    1  <NL>");
+/// ```
+pub struct DisplayOpts {
+    /// Ends the last line, which starts `EndOfInput`. `None` omits that line.
+    pub eoi_marker: Option<&'static str>,
+    /// Whether newlines, tabs and carriage returns are shown as `<NL>`, `<TB>`
+    /// and `<CR>`.
+    pub escape_control_chars: bool,
+    /// The minimum width of the column which contains each `LexemeKind`.
+    pub kind_width: usize,
+    /// The minimum width of the column which contains each position.
+    pub pos_width: usize,
+}
+
+impl DisplayOpts {
+    /// Creates a default DisplayOpts object, which matches `to_string()`.
+    pub fn new() -> Self { DisplayOpts::default() }
+    /// Overrides the default `<EOI>` marker, or omits the `EndOfInput` line.
+    pub fn eoi_marker(mut self, replacement_value: Option<&'static str>) -> Self {
+        self.eoi_marker = replacement_value;
+        return self;
+    }
+    /// Overrides whether control chars are escaped, which they are by default.
+    pub fn escape_control_chars(mut self, replacement_value: bool) -> Self {
+        self.escape_control_chars = replacement_value;
+        return self;
+    }
+    /// Overrides the default `LexemeKind` column width, 16.
+    pub fn kind_width(mut self, replacement_value: usize) -> Self {
+        self.kind_width = replacement_value;
+        return self;
+    }
+    /// Overrides the default position column width, 4.
+    pub fn pos_width(mut self, replacement_value: usize) -> Self {
+        self.pos_width = replacement_value;
+        return self;
+    }
+}
+
+impl Default for DisplayOpts {
+    /// Creates a default DisplayOpts object, like `DisplayOpts::new()` does.
+    fn default() -> Self {
+        DisplayOpts {
+            eoi_marker: Some("<EOI>"),
+            escape_control_chars: true,
+            kind_width: 16,
+            pos_width: 4,
+        }
+    }
+}
+
+/// The Lexemes found by `lexemize()`, and where the input code ends.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lexemes<'a> {
+    /// The column after the last Lexeme. The first column is 1.
+    pub end_column: usize,
+    /// The line number after the last Lexeme. The first line is 1.
+    pub end_line_number: usize,
+    /// The position after the last Lexeme, which is the length of `orig`.
+    pub end_pos: usize,
+    /// Every Lexeme found in `orig`, in order.
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub lexemes: Vec<Lexeme<'a>>,
+}
+
+impl<'a> Lexemes<'a> {
+    /// Iterates over the Lexemes of a particular kind, in order.
+    /// 
+    /// Nothing is allocated, so this is a cheap way to find, for example, every
+    /// comment or every identifier.
+    /// ```
+    /// # use opinionated_rust_to_typescript::lexer::{lexemize,LexemeKind};
+    /// let lexemes = lexemize("/// Doc\nfn f() {} // Not doc");
+    /// let docs: Vec<&str> = lexemes.filter_kind(LexemeKind::DocComment)
+    ///     .map(|l| l.snippet)
+    ///     .collect();
+    /// assert_eq!(docs, vec!["/// Doc"]);
+    /// ```
+    pub fn filter_kind(
+        &self,
+        kind: LexemeKind,
+    ) -> impl Iterator<Item = &Lexeme<'a>> {
+        self.lexemes.iter().filter(move |lexeme| lexeme.kind == kind)
+    }
+
+    /// Reconstructs the original input code, by joining every Lexeme’s snippet.
+    /// 
+    /// The Lexemes cover every char of `orig`, including ‘Xtraneous’ ones, so
+    /// the result should always equal `orig`. A byte order mark at the start of
+    /// `orig` is skipped by `lexemize()`, so it is added back, because the
+    /// first Lexeme starts after it.
+    /// ```
+    /// # use opinionated_rust_to_typescript::lexer::lexemize;
+    /// let orig = "let ¢ = 1; // €";
+    /// assert_eq!(lexemize(orig).reconstruct(), orig);
+    /// ```
+    pub fn reconstruct(&self) -> String {
+        let mut orig = String::with_capacity(self.end_pos);
+        // If the Lexemes start at 3, `lexemize()` skipped a byte order mark.
+        let start = self.lexemes.first().map_or(self.end_pos, |l| l.pos);
+        if start == 3 { orig.push('\u{FEFF}') }
+        for lexeme in &self.lexemes { orig.push_str(lexeme.snippet) }
+        orig
+    }
+
+    /// Counts the Lexemes of each kind, and the size of the input code.
+    /// 
+    /// This is handy for understanding what a Rust file contains, before
+    /// transpiling it.
+    /// ```
+    /// # use opinionated_rust_to_typescript::lexer::{lexemize,LexemeKind};
+    /// let stats = lexemize("let x = 1;").summary();
+    /// assert_eq!(stats.count(LexemeKind::Whitespace), 3);
+    /// assert_eq!(stats.bytes, 10);
+    /// ```
+    pub fn summary(&self) -> LexemeStats {
+        let mut counts = [0; LexemeKind::ALL.len()];
+        for lexeme in &self.lexemes { counts[lexeme.kind as usize] += 1 }
+        LexemeStats {
+            bytes: self.end_pos,
+            counts,
+            lines: self.end_line_number,
+        }
+    }
+
+    /// Formats the Lexemes one per line, like `to_string()`, but with the
+    /// options in `opts`. See [`DisplayOpts`].
+    pub fn to_string_with(&self, opts: &DisplayOpts) -> String {
+        let mut lines = vec![format!("Lexemes found: {}", self.lexemes.len())];
+        for lexeme in &self.lexemes { lines.push(lexeme.to_string_with(opts)) }
+        if let Some(eoi_marker) = opts.eoi_marker {
+            lines.push(format!("{: <kw$} {: >pw$}  {}",
+                "EndOfInput", self.end_pos, eoi_marker,
+                kw = opts.kind_width, pw = opts.pos_width));
+        }
+        lines.join("\n")
+    }
+
+    /// Formats the Lexemes as JSON, without needing the `serde` feature.
+    /// 
+    /// The output is a JSON object, whose `lexemes` property is an array which
+    /// contains each Lexeme, formatted by `Lexeme::to_json()`. It matches what
+    /// `serde_json` produces with the `serde` feature.
+    /// ```
+    /// # use opinionated_rust_to_typescript::lexer::lexemize;
+    /// assert_eq!(lexemize("\"a\"").to_json(),
+    ///     r#"{"end_column":4,"end_line_number":1,"end_pos":3,"lexemes":["#
+    ///     .to_owned() + r#"{"column":1,"kind":"String","line_number":1,"#
+    ///     + r#""pos":0,"snippet":"\"a\""}]}"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let lexemes: Vec<String> = self.lexemes.iter()
+            .map(|lexeme| lexeme.to_json())
+            .collect();
+        format!("{{\"end_column\":{},\"end_line_number\":{},\"end_pos\":{},\
+            \"lexemes\":[{}]}}", self.end_column, self.end_line_number,
+            self.end_pos, lexemes.join(","))
+    }
+}
+
+impl fmt::Display for Lexemes<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.to_string_with(&DisplayOpts::new()))
+    }
+}
+
+/// Statistics about some Lexemes, returned by `Lexemes::summary()`.
+pub struct LexemeStats {
+    /// The length of the input code, in bytes.
+    pub bytes: usize,
+    /// The number of Lexemes of each kind, in the order of `LexemeKind::ALL`.
+    pub counts: [usize; LexemeKind::ALL.len()],
+    /// The number of lines in the input code. An empty input has one line.
+    pub lines: usize,
+}
+
+impl LexemeStats {
+    /// Returns the number of Lexemes of a particular kind.
+    pub fn count(&self, kind: LexemeKind) -> usize {
+        self.counts[kind as usize]
+    }
 }
 
-impl fmt::Display for Lexeme {
+impl fmt::Display for LexemeStats {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let kind = self.kind.to_string();
-        let snippet = self.snippet.replace("\n", "<NL>");
-        write!(fmt, "{: <16} {: >4}  {}", kind, self.pos, snippet)
-        //                     |||
-        //                     ||+-- target width is four characters
-        //                     |+--- align right
-        //                     +---- fill with spaces
+        for kind in LexemeKind::ALL.iter() {
+            writeln!(fmt, "{: <16} {: >4}", kind, self.count(*kind))?;
+        }
+        writeln!(fmt, "Bytes            {: >4}", self.bytes)?;
+        write!(fmt, "Lines            {: >4}", self.lines)
     }
 }
 
@@ -66,26 +388,324 @@ impl fmt::Display for Lexeme {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::lexemize::lexemize;
     
     #[test]
     fn lexeme_kind_to_string_as_expected() {
+        assert_eq!(LexemeKind::Attribute.to_string(),   "Attribute");
         assert_eq!(LexemeKind::Character.to_string(),   "Character");
         assert_eq!(LexemeKind::Comment.to_string(),     "Comment");
+        assert_eq!(LexemeKind::DocComment.to_string(),  "DocComment");
         assert_eq!(LexemeKind::Identifier.to_string(),  "Identifier");
+        assert_eq!(LexemeKind::Keyword.to_string(),     "Keyword");
+        assert_eq!(LexemeKind::Lifetime.to_string(),    "Lifetime");
         assert_eq!(LexemeKind::Number.to_string(),      "Number");
         assert_eq!(LexemeKind::Punctuation.to_string(), "Punctuation");
+        assert_eq!(LexemeKind::Shebang.to_string(),     "Shebang");
         assert_eq!(LexemeKind::String.to_string(),      "String");
         assert_eq!(LexemeKind::Whitespace.to_string(),  "Whitespace");
         assert_eq!(LexemeKind::Xtraneous.to_string(),   "Xtraneous");
     }
 
+    #[test]
+    fn lexeme_kind_as_str_as_expected() {
+        assert_eq!(LexemeKind::Attribute.as_str(),   "Attribute");
+        assert_eq!(LexemeKind::Character.as_str(),   "Character");
+        assert_eq!(LexemeKind::Comment.as_str(),     "Comment");
+        assert_eq!(LexemeKind::DocComment.as_str(),  "DocComment");
+        assert_eq!(LexemeKind::Identifier.as_str(),  "Identifier");
+        assert_eq!(LexemeKind::Keyword.as_str(),     "Keyword");
+        assert_eq!(LexemeKind::Lifetime.as_str(),    "Lifetime");
+        assert_eq!(LexemeKind::Number.as_str(),      "Number");
+        assert_eq!(LexemeKind::Punctuation.as_str(), "Punctuation");
+        assert_eq!(LexemeKind::Shebang.as_str(),     "Shebang");
+        assert_eq!(LexemeKind::String.as_str(),      "String");
+        assert_eq!(LexemeKind::Whitespace.as_str(),  "Whitespace");
+        assert_eq!(LexemeKind::Xtraneous.as_str(),   "Xtraneous");
+    }
+
     #[test]
     fn lexeme_to_string_as_expected() {
         let lexeme = Lexeme {
+            column: 45,
             kind: LexemeKind::Character,
+            line_number: 6,
             pos: 123,
-            snippet: "yup".into(),
+            snippet: "yup",
         };
         assert_eq!(lexeme.to_string(), "Character         123  yup");
     }
+
+    #[test]
+    fn lexeme_to_string_renders_control_chars() {
+        let lexeme = Lexeme {
+            column: 1,
+            kind: LexemeKind::Whitespace,
+            line_number: 1,
+            pos: 0,
+            snippet: "\t\n",
+        };
+        assert_eq!(lexeme.to_string(), "Whitespace          0  <TB><NL>");
+        assert_eq!(lexeme.snippet, "\t\n"); // the snippet is unchanged
+        let lexeme = Lexeme {
+            column: 1,
+            kind: LexemeKind::Whitespace,
+            line_number: 1,
+            pos: 7,
+            snippet: " \r\n",
+        };
+        assert_eq!(lexeme.to_string(), "Whitespace          7   <CR><NL>");
+    }
+
+    #[test]
+    fn lexeme_text_range_as_expected() {
+        let orig = "\u{FEFF}fn é() {\n    'a' // b\n}\r\n€ r#\"c\"#";
+        for lexeme in lexemize(orig).lexemes.iter() {
+            assert_eq!(&orig[lexeme.text_range()], lexeme.snippet);
+        }
+        // An empty snippet.
+        let lexeme = Lexeme {
+            column: 1,
+            kind: LexemeKind::Xtraneous,
+            line_number: 1,
+            pos: 5,
+            snippet: "",
+        };
+        assert_eq!(lexeme.text_range(), 5..5);
+    }
+
+    #[test]
+    fn lexemes_to_string_as_expected() {
+        let result = Lexemes {
+            end_column: 28,
+            end_line_number: 1,
+            end_pos: 123,
+            lexemes: vec![
+                Lexeme {
+                    column: 1,
+                    kind: LexemeKind::Comment,
+                    line_number: 1,
+                    pos: 0,
+                    snippet: "/* This is a comment */",
+                },
+                Lexeme {
+                    column: 24,
+                    kind: LexemeKind::Number,
+                    line_number: 1,
+                    pos: 23,
+                    snippet: "44.4",
+                },
+            ],
+        };
+        assert_eq!(result.to_string(),
+            "Lexemes found: 2\n\
+             Comment             0  /* This is a comment */\n\
+             Number             23  44.4\n\
+             EndOfInput        123  <EOI>"
+        );
+    }
+
+    #[test]
+    fn lexemes_to_string_with_as_expected() {
+        let result = lexemize("x\t");
+        // The default options match `to_string()`.
+        assert_eq!(result.to_string_with(&DisplayOpts::new()),
+            result.to_string());
+        // The EndOfInput line can be suppressed.
+        let opts = DisplayOpts::new().eoi_marker(None);
+        assert_eq!(result.to_string_with(&opts),
+            "Lexemes found: 2\n\
+             Identifier          0  x\n\
+             Whitespace          1  <TB>");
+        // Or given a different marker, with different widths and raw control
+        // chars.
+        let opts = DisplayOpts::new()
+            .eoi_marker(Some("$"))
+            .escape_control_chars(false)
+            .kind_width(11)
+            .pos_width(1);
+        assert_eq!(result.to_string_with(&opts),
+            "Lexemes found: 2\n\
+             Identifier  0  x\n\
+             Whitespace  1  \t\n\
+             EndOfInput  2  $");
+        assert_eq!(result.lexemes[1].to_string_with(&opts), "Whitespace  1  \t");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn lexemes_serde_round_trip() {
+        let lexemes = Lexemes {
+            end_column: 4,
+            end_line_number: 1,
+            end_pos: 3,
+            lexemes: vec![
+                Lexeme {
+                    column: 1,
+                    kind: LexemeKind::Number,
+                    line_number: 1,
+                    pos: 0,
+                    snippet: "4u8",
+                },
+            ],
+        };
+        let json = serde_json::to_string(&lexemes).unwrap();
+        assert_eq!(json, "{\"end_column\":4,\"end_line_number\":1,\
+            \"end_pos\":3,\"lexemes\":[{\"column\":1,\"kind\":\"Number\",\
+            \"line_number\":1,\"pos\":0,\"snippet\":\"4u8\"}]}");
+        let round_trip: Lexemes = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip.to_string(), lexemes.to_string());
+        assert!(round_trip.lexemes[0].kind == LexemeKind::Number);
+        assert_eq!(round_trip.lexemes[0].column, 1);
+    }
+
+    #[test]
+    fn lexemes_to_json_as_expected() {
+        let json = lexemize("\tx = \"\\\"\";\n").to_json();
+        assert_eq!(json, "{\"end_column\":1,\"end_line_number\":2,\
+            \"end_pos\":11,\"lexemes\":[\
+            {\"column\":1,\"kind\":\"Whitespace\",\"line_number\":1,\
+            \"pos\":0,\"snippet\":\"\\t\"},\
+            {\"column\":2,\"kind\":\"Identifier\",\"line_number\":1,\
+            \"pos\":1,\"snippet\":\"x\"},\
+            {\"column\":3,\"kind\":\"Whitespace\",\"line_number\":1,\
+            \"pos\":2,\"snippet\":\" \"},\
+            {\"column\":4,\"kind\":\"Punctuation\",\"line_number\":1,\
+            \"pos\":3,\"snippet\":\"=\"},\
+            {\"column\":5,\"kind\":\"Whitespace\",\"line_number\":1,\
+            \"pos\":4,\"snippet\":\" \"},\
+            {\"column\":6,\"kind\":\"String\",\"line_number\":1,\
+            \"pos\":5,\"snippet\":\"\\\"\\\\\\\"\\\"\"},\
+            {\"column\":10,\"kind\":\"Punctuation\",\"line_number\":1,\
+            \"pos\":9,\"snippet\":\";\"},\
+            {\"column\":11,\"kind\":\"Whitespace\",\"line_number\":1,\
+            \"pos\":10,\"snippet\":\"\\n\"}]}");
+        // JSON strings may not contain raw control chars.
+        assert!(! json.chars().any(|c| (c as u32) < 0x20));
+        // Other control chars use short or unicode escapes. Non-ascii does not.
+        let json = lexemize("\u{8}\u{c}\u{0}\r€").to_json();
+        assert!(! json.chars().any(|c| (c as u32) < 0x20));
+        assert!(json.contains(r#""snippet":"\b""#));
+        assert!(json.contains(r#""snippet":"\f""#));
+        assert!(json.contains(r#""snippet":"\u0000""#));
+        assert!(json.contains(r#""snippet":"\r""#));
+        assert!(json.contains(r#""snippet":"€""#));
+        // Empty input.
+        assert_eq!(lexemize("").to_json(), "{\"end_column\":1,\
+            \"end_line_number\":1,\"end_pos\":0,\"lexemes\":[]}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn lexemes_to_json_matches_serde() {
+        let orig = "fn f() {\n\t\"\\\"\\u{0}\" /* € */ '\\'' \u{1}\u{7f}\n}";
+        let lexemes = lexemize(orig);
+        assert_eq!(lexemes.to_json(), serde_json::to_string(&lexemes).unwrap());
+    }
+
+    #[test]
+    fn lexeme_kind_hash_and_ord() {
+        use std::collections::HashSet;
+        // Each kind is distinct, so can be a `HashSet` or `HashMap` key.
+        let set: HashSet<LexemeKind> =
+            LexemeKind::ALL.iter().copied().collect();
+        assert_eq!(set.len(), LexemeKind::ALL.len());
+        assert!(set.contains(&LexemeKind::DocComment));
+        // Sorting follows the declared order, which is alphabetical.
+        let mut sorted: Vec<LexemeKind> = LexemeKind::ALL.iter().rev()
+            .copied().collect();
+        sorted.sort();
+        assert_eq!(sorted, LexemeKind::ALL.to_vec());
+        assert!(LexemeKind::Attribute < LexemeKind::Xtraneous);
+        assert_eq!(LexemeKind::Number.max(LexemeKind::Comment),
+            LexemeKind::Number);
+        // `Debug` prints the variant’s name.
+        assert_eq!(format!("{:?}", LexemeKind::DocComment), "DocComment");
+    }
+
+    #[test]
+    fn lexeme_kind_all_matches_as_usize() {
+        // `LexemeStats` relies on this, to index its `counts`.
+        for (i, kind) in LexemeKind::ALL.iter().enumerate() {
+            assert_eq!(*kind as usize, i);
+        }
+    }
+
+    #[test]
+    fn lexemes_filter_kind_as_expected() {
+        let orig = "let a = 1 + 0x2_u8;\n// 3\nfn f() -> f32 { 4.5 }";
+        let lexemes = lexemize(orig);
+        let numbers: Vec<&str> = lexemes.filter_kind(LexemeKind::Number)
+            .map(|l| l.snippet)
+            .collect();
+        assert_eq!(numbers, vec!["1", "0x2_u8", "4.5"]);
+        // The Lexemes keep their positions.
+        let positions: Vec<usize> = lexemes.filter_kind(LexemeKind::Number)
+            .map(|l| l.pos)
+            .collect();
+        assert_eq!(positions, vec![8, 12, 41]);
+        // No Lexemes of that kind.
+        assert_eq!(lexemes.filter_kind(LexemeKind::String).count(), 0);
+        assert_eq!(lexemize("").filter_kind(LexemeKind::Number).count(), 0);
+    }
+
+    #[test]
+    fn lexemes_reconstruct_round_trips() {
+        let origs = [
+            "",
+            "const A: u8 = 1;\n",
+            "#!/usr/bin/env run-cargo-script\n#![allow(x)]\nfn main() {}",
+            "let s = r#\"raw \"# string\"#; /* multi\nline */ 'a' 'b'",
+            "~ ¶ € \u{00A0} \u{3000}~~", // Xtraneous
+            "let é = \"€\";\r\n\t// ü\u{2028}ß", // non-ascii
+            "\"unterminated string", // Xtraneous, identifier, whitespace
+            "/* unterminated comment", // punctuation, identifiers, whitespace
+            "\u{FEFF}", // just a byte order mark
+            "\u{FEFF}fn f() {}", // a leading byte order mark
+            "\u{FEFF}\u{FEFF}", // a second byte order mark is Xtraneous
+            "x\u{FEFF}", // a byte order mark after the start is Xtraneous
+        ];
+        for orig in origs.iter() {
+            let lexemes = lexemize(orig);
+            assert_eq!(lexemes.reconstruct(), *orig);
+            assert_eq!(lexemes.end_pos, orig.len());
+        }
+    }
+
+    #[test]
+    fn lexemes_summary_as_expected() {
+        let stats = lexemize("#[test]\nfn a() -> u8 { 'x'; \"y\"; 1 } // z\n€")
+            .summary();
+        assert_eq!(stats.count(LexemeKind::Attribute), 1);   // #[test]
+        assert_eq!(stats.count(LexemeKind::Character), 1);   // 'x'
+        assert_eq!(stats.count(LexemeKind::Comment), 1);     // // z
+        assert_eq!(stats.count(LexemeKind::DocComment), 0);
+        assert_eq!(stats.count(LexemeKind::Identifier), 2);  // a u8
+        assert_eq!(stats.count(LexemeKind::Keyword), 1);     // fn
+        assert_eq!(stats.count(LexemeKind::Lifetime), 0);
+        assert_eq!(stats.count(LexemeKind::Number), 1);      // 1
+        assert_eq!(stats.count(LexemeKind::Punctuation), 7); // ()->{;;}
+        assert_eq!(stats.count(LexemeKind::Shebang), 0);
+        assert_eq!(stats.count(LexemeKind::String), 1);      // "y"
+        assert_eq!(stats.count(LexemeKind::Whitespace), 11);
+        assert_eq!(stats.count(LexemeKind::Xtraneous), 1);   // €
+        assert_eq!(stats.bytes, 45);
+        assert_eq!(stats.lines, 3);
+        assert_eq!(lexemize("").summary().to_string(),
+            "Attribute           0\n\
+             Character           0\n\
+             Comment             0\n\
+             DocComment          0\n\
+             Identifier          0\n\
+             Keyword             0\n\
+             Lifetime            0\n\
+             Number              0\n\
+             Punctuation         0\n\
+             Shebang             0\n\
+             String              0\n\
+             Whitespace          0\n\
+             Xtraneous           0\n\
+             Bytes               0\n\
+             Lines               1");
+    }
 }
@@ -1,56 +1,67 @@
 //! Transforms raw Rust 2018 code into Lexemes.
 
-use std::fmt;
-
-use super::lexeme::{Lexeme,LexemeKind};
+use crate::transpile::config::RsEdition;
+use super::lexeme::{Lexeme,LexemeKind,Lexemes};
+use super::line_starts::advance_column;
+use super::detect::attribute::detect_attribute;
 use super::detect::character::detect_character;
-use super::detect::comment::detect_comment;
-use super::detect::identifier::detect_identifier;
+use super::detect::comment::{detect_comment,detect_doc_comment};
+use super::detect::identifier::{detect_identifier,detect_identifier_2015};
+use super::detect::keyword::{detect_keyword,detect_keyword_2015};
+use super::detect::lifetime::detect_lifetime;
 use super::detect::number::detect_number;
 use super::detect::punctuation::detect_punctuation;
-use super::detect::string::detect_string;
-use super::detect::whitespace::detect_whitespace;
-
-///
-pub struct LexemizeResult {
-    ///
-    pub end_pos: usize,
-    ///
-    pub lexemes: Vec<Lexeme>,
-}
+use super::detect::shebang::detect_shebang;
+use super::detect::string::{detect_c_string,detect_string};
+use super::detect::whitespace::{count_newlines_in_whitespace,detect_whitespace};
 
-impl fmt::Display for LexemizeResult {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "Lexemes found: {}\n", self.lexemes.len())?;
-        for lexeme in &self.lexemes {
-            fmt.write_str(&lexeme.to_string())?;
-            fmt.write_str("\n")?;
-        }
-        write!(fmt, "EndOfInput       {: >4}  <EOI>", self.end_pos)
-        //                              |||
-        //                              ||+-- target width is four characters
-        //                              |+--- align right
-        //                              +---- fill with spaces
-    }
-}
+/// A `detect_*()` function, and the kind of `Lexeme` which it detects.
+pub type DetectorAndKind = (fn (&str, usize) -> usize, LexemeKind);
 
 /// An array which associates the `detect_*()` functions with `LexemeKind`s.
 /// 
 /// Note that a `String` can start with an `"r"` character, so `detect_string()`
-/// is placed before `detect_identifier()`.
-pub const DETECTORS_AND_KINDS: [(
-    fn (&str, usize) -> usize,
-    LexemeKind,
-); 7] = [
+/// is placed before `detect_identifier()`. And `detect_lifetime()` rejects
+/// chars like `'a'`, so it can safely be placed before `detect_character()`.
+/// `detect_shebang()` only ever detects a Lexeme at the start of the input,
+/// and it rejects `#![`, so it can safely be placed before `detect_attribute()`.
+/// `detect_keyword()` must be placed before `detect_identifier()`, because
+/// every keyword is also a valid identifier. Likewise `detect_doc_comment()`
+/// must be placed before `detect_comment()`, which detects any comment.
+pub const DETECTORS_AND_KINDS: [DetectorAndKind; 12] = [
+    (detect_shebang,     LexemeKind::Shebang),
+    (detect_attribute,   LexemeKind::Attribute),
+    (detect_lifetime,    LexemeKind::Lifetime),
     (detect_character,   LexemeKind::Character),
+    (detect_doc_comment, LexemeKind::DocComment),
     (detect_comment,     LexemeKind::Comment),
     (detect_string,      LexemeKind::String),
+    (detect_keyword,     LexemeKind::Keyword),
     (detect_identifier,  LexemeKind::Identifier),
     (detect_number,      LexemeKind::Number),
     (detect_punctuation, LexemeKind::Punctuation),
     (detect_whitespace,  LexemeKind::Whitespace),
 ];
 
+/// Like [`DETECTORS_AND_KINDS`], but for Rust 2015 code.
+/// 
+/// The only differences are that raw identifiers, like `r#type`, are not
+/// detected, and that `async`, `await`, `dyn` and `try` are not keywords.
+pub const DETECTORS_AND_KINDS_2015: [DetectorAndKind; 12] = [
+    (detect_shebang,         LexemeKind::Shebang),
+    (detect_attribute,       LexemeKind::Attribute),
+    (detect_lifetime,        LexemeKind::Lifetime),
+    (detect_character,       LexemeKind::Character),
+    (detect_doc_comment,     LexemeKind::DocComment),
+    (detect_comment,         LexemeKind::Comment),
+    (detect_string,          LexemeKind::String),
+    (detect_keyword_2015,    LexemeKind::Keyword),
+    (detect_identifier_2015, LexemeKind::Identifier),
+    (detect_number,          LexemeKind::Number),
+    (detect_punctuation,     LexemeKind::Punctuation),
+    (detect_whitespace,      LexemeKind::Whitespace),
+];
+
 /// Transforms a Rust 2018 program into a vector of `Lexemes`.
 /// 
 /// The primary purpose of `lexemize()` is to quickly divide Rust code into
@@ -59,6 +70,10 @@ pub const DETECTORS_AND_KINDS: [(
 /// The ‘everything else’ section is then divided into literals, punctuation,
 /// whitespace and identifiers. Anything left over is marked as ‘xtraneous’.
 /// 
+/// A byte order mark (U+FEFF) at the very start of `orig` is skipped, like
+/// rustc does, so it does not become a Lexeme. A byte order mark anywhere else
+/// is ‘xtraneous’.
+/// 
 /// Any input string can be lexemized, so this function never returns any kind
 /// of error. Checking `orig` for semantic correctness should be done later on,
 /// during tokenization and parsing.
@@ -67,129 +82,384 @@ pub const DETECTORS_AND_KINDS: [(
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// 
 /// ### Returns
-/// `lexemize()` returns a [`LexemizeResult`] object.
+/// `lexemize()` returns a [`Lexemes`] object.
 pub fn lexemize(
     orig: &str
-) -> LexemizeResult {
-    // Initialise `len`, and some mutable variables.
-    let len = orig.len();
-    let mut pos = 0;
-    let mut xtra_pos = 0;
-    let mut result = LexemizeResult {
-        end_pos: 0,
-        lexemes: vec![],
-    };
+) -> Lexemes<'_> {
+    lexemize_with_edition(orig, RsEdition::Latest)
+}
 
-    // Loop until we reach the last character of the input string.
-    'outer: while pos < len {
-        // Only try to detect a Lexeme if this is the start of a character.
-        if orig.is_char_boundary(pos) {
-            // Step through the array of `detect_*()` functions, and their
-            // associated `LexemeKinds`.
-            for (detector, kind) in DETECTORS_AND_KINDS.iter() {
-                // Possibly add one or two Lexemes to `result`.
-                let next_pos = detect(
-                    *detector,
-                    *kind,
-                    orig,
-                    pos,
-                    xtra_pos,
-                    &mut result
-                );
-                // If a Lexeme has been detected at this character position,
-                // `detect()` will have returned the character position of the
-                // end of that Lexeme.
-                if next_pos != pos {
-                    pos = next_pos;
-                    xtra_pos = pos;
-                    continue 'outer;
-                }
-            }
-            // Anything else is an unidentifiable character, which will be
-            // picked up by the `xtra_pos != pos` conditional in `detect()`.
+/// Transforms a Rust program of any supported edition into `Lexemes`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code
+/// * `edition` The edition of Rust that `orig` is written in
+/// 
+/// ### Returns
+/// `lexemize_with_edition()` returns a [`Lexemes`] object.
+pub fn lexemize_with_edition(
+    orig: &str,
+    edition: RsEdition,
+) -> Lexemes<'_> {
+    collect_lexemes(Lexemizer::with_edition(orig, edition))
+}
+
+/// Transforms a Rust 2018 program into `Lexemes`, with wider tabs.
+/// 
+/// By default, a tab advances the column by one, like any other char. But
+/// editors usually show a tab as wider, so an error message which points into
+/// tab-indented code can be misaligned. With a `tab_width` of 4, a tab instead
+/// advances the column to 5, 9, 13, etc, whichever is next.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `tab_width` The distance between tab stops, where 1 (or 0) means that a
+///   tab is as wide as any other char
+/// 
+/// ### Returns
+/// `lexemize_with_tab_width()` returns a [`Lexemes`] object.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::*;
+/// let lexemes = lexemize_with_tab_width("\tx", 4);
+/// assert_eq!(lexemes.lexemes[1].column, 5);
+/// ```
+pub fn lexemize_with_tab_width(
+    orig: &str,
+    tab_width: usize,
+) -> Lexemes<'_> {
+    collect_lexemes(Lexemizer::new(orig).tab_width(tab_width))
+}
+
+/// Transforms the start of a Rust 2018 program into `Lexemes`, up to a limit.
+/// 
+/// This protects tooling from pathological inputs, like a huge generated file.
+/// `lexemize_with_limit()` stops before the first Lexeme which would end after
+/// `max_bytes`, so the result is always well-formed, and never ends part way
+/// through a Lexeme. A detector may still look past `max_bytes` to find where
+/// that Lexeme ends, but no further Lexemes are detected.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `max_bytes` The position which no Lexeme may end after
+/// 
+/// ### Returns
+/// A [`Lexemes`] object, and `true` if it was truncated. If so, its `end_pos`,
+/// `end_line_number` and `end_column` are where the first Lexeme which was
+/// left out begins. Otherwise, the `Lexemes` are the same as `lexemize()`
+/// returns.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::*;
+/// let (lexemes, truncated) = lexemize_with_limit("let x = 1;", 6);
+/// assert_eq!(lexemes.lexemes.len(), 4); // "let", " ", "x" and " "
+/// assert_eq!(lexemes.end_pos, 6);
+/// assert!(truncated);
+/// ```
+pub fn lexemize_with_limit(
+    orig: &str,
+    max_bytes: usize,
+) -> (Lexemes<'_>, bool) {
+    let mut lexemizer = Lexemizer::new(orig);
+    let mut lexemes = vec![];
+    while let Some(lexeme) = lexemizer.next() {
+        // The Lexemizer is now at the end of this Lexeme. If that is after the
+        // limit, end the Lexemes where this Lexeme begins.
+        if lexemizer.pos > max_bytes {
+            let lexemes = Lexemes {
+                end_column: lexeme.column,
+                end_line_number: lexeme.line_number,
+                end_pos: lexeme.pos,
+                lexemes,
+            };
+            return (lexemes, true);
         }
+        lexemes.push(lexeme);
+    }
+    let lexemes = Lexemes {
+        end_column: lexemizer.column,
+        end_line_number: lexemizer.line_number,
+        end_pos: lexemizer.pos,
+        lexemes,
+    };
+    (lexemes, false)
+}
 
-        // Step forward one byte.
-        pos += 1;
+// Collects every Lexeme, and then records where the input code ends.
+fn collect_lexemes(mut lexemizer: Lexemizer) -> Lexemes {
+    let lexemes = lexemizer.by_ref().collect();
+    Lexemes {
+        end_column: lexemizer.column,
+        end_line_number: lexemizer.line_number,
+        end_pos: lexemizer.pos,
+        lexemes,
     }
+}
 
-    // If there are unidentifiable characters at the end of `orig`, add a final 
-    // `Xtraneous` Lexeme before returning `result`.
-    if xtra_pos != pos {
-        result.lexemes.push(Lexeme {
-            kind: LexemeKind::Xtraneous,
-            pos: xtra_pos,
-            snippet: orig[xtra_pos..pos].to_string(),
-        });
+/// Transforms a Rust 2018 program into `Lexemes`, and reports ambiguities.
+/// 
+/// `lexemize()` picks the first detector in [`DETECTORS_AND_KINDS`] which
+/// finds a Lexeme, so the order of that array matters. For example,
+/// `detect_string()` must come before `detect_identifier()`, or `r"x"` would
+/// be lexemized as the identifier `r` followed by the string `"x"`.
+/// 
+/// `lexemize_checked()` is a testing aid, which helps to catch mistakes in that
+/// order. For each Lexeme, it runs every other detector at the same position,
+/// and records a warning for each one which finds a Lexeme with a different
+/// end position. Detectors which agree on the end position, like
+/// `detect_keyword()` and `detect_identifier()` for `fn`, are not reported.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// 
+/// ### Returns
+/// The same [`Lexemes`] object as `lexemize()` returns, and a vector of
+/// warnings, like `"1:1 String wins, ending at 4, but Identifier ends at 1"`.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::*;
+/// let (lexemes, warnings) = lexemize_checked("let s = 1;");
+/// assert_eq!(lexemes.lexemes.len(), 8);
+/// assert!(warnings.is_empty());
+/// ```
+pub fn lexemize_checked(
+    orig: &str
+) -> (Lexemes<'_>, Vec<String>) {
+    let lexemes = lexemize(orig);
+    let mut warnings = vec![];
+    for lexeme in &lexemes.lexemes {
+        // Only the detectors which lost to this Lexeme’s detector can disagree.
+        // ‘Xtraneous’ characters were not detected by anything.
+        if lexeme.kind == LexemeKind::Xtraneous { continue }
+        let end = lexeme.pos + lexeme.snippet.len();
+        for (detector, kind) in DETECTORS_AND_KINDS.iter() {
+            if *kind == lexeme.kind { continue }
+            let other_end = detector(orig, lexeme.pos);
+            if other_end != lexeme.pos && other_end != end {
+                warnings.push(format!("{}:{} {} wins, ending at {}, but {} \
+                    ends at {}", lexeme.line_number, lexeme.column,
+                    lexeme.kind, end, kind, other_end));
+            }
+        }
     }
+    (lexemes, warnings)
+}
 
-    result.end_pos = pos;
-    result
+/// Detects the kind of the Lexeme which begins at a position, and where it ends.
+/// 
+/// `next_lexeme_kind()` steps through [`DETECTORS_AND_KINDS`] in the same order
+/// as `lexemize()`, but stops at the first match, and allocates nothing. So it
+/// suits queries like ‘what is the token at the cursor?’.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `pos` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If a Lexeme begins at `pos`, its kind and the character position after it
+/// ends. Otherwise, for example if `pos` begins an ‘xtraneous’ character, or is
+/// out of range, `None`.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::next_lexeme_kind;
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexeme::LexemeKind;
+/// let found = next_lexeme_kind("let x = 1;", 4);
+/// assert!(found == Some((LexemeKind::Identifier, 5)));
+/// ```
+pub fn next_lexeme_kind(
+    orig: &str,
+    pos: usize,
+) -> Option<(LexemeKind, usize)> {
+    detect_first(&DETECTORS_AND_KINDS, orig, pos)
 }
 
-fn detect(
-    detector: fn (&str, usize) -> usize,
-    kind: LexemeKind,
+// Returns the kind and end position of the first Lexeme that one of the
+// `detectors_and_kinds` finds at `pos`, or `None` if none of them find one.
+fn detect_first(
+    detectors_and_kinds: &[DetectorAndKind],
     orig: &str,
     pos: usize,
-    xtra_pos: usize,
-    result: &mut LexemizeResult,
-) -> usize {
-    // If the passed-in `detector()` does not detect the Lexeme, it will return
-    // the same char-position as `pos`. In that case, just return `pos`.
-    let next_pos = detector(orig, pos);
-    if next_pos == pos { return pos }
-
-    // If any ‘Xtraneous’ characters precede this Lexeme, record them before
-    // recording this Lexeme.
-    if xtra_pos != pos {
-        result.lexemes.push(Lexeme {
-            kind: LexemeKind::Xtraneous,
-            pos: xtra_pos,
-            snippet: orig[xtra_pos..pos].to_string(),
-        });
-    }
-    result.lexemes.push(Lexeme {
-        kind,
-        pos,
-        snippet: orig[pos..next_pos].to_string(),
-    });
-
-    // Tell `lexemize()` the character position of the end of the Lexeme.
-    next_pos
+) -> Option<(LexemeKind, usize)> {
+    // Only try to detect a Lexeme if this is the start of a character.
+    if ! orig.is_char_boundary(pos) { return None }
+    // Step through the array of `detect_*()` functions, and their associated
+    // `LexemeKinds`.
+    for (detector, kind) in detectors_and_kinds.iter() {
+        // If the `detector()` does not detect the Lexeme, it will return the
+        // same char-position as `pos`.
+        let next_pos = detector(orig, pos);
+        if next_pos != pos { return Some((*kind, next_pos)) }
+    }
+    // Anything else is an unidentifiable character.
+    None
+}
+
+/// An iterator which transforms a Rust 2018 program into `Lexeme`s, one by one.
+/// 
+/// `Lexemizer` produces the same `Lexeme`s as `lexemize()`, but it does not
+/// allocate a vector to hold them all, so it suits large input code.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::Lexemizer;
+/// let mut lexemizer = Lexemizer::new("abc;");
+/// assert_eq!(lexemizer.next().unwrap().snippet, "abc");
+/// assert_eq!(lexemizer.next().unwrap().snippet, ";");
+/// assert!(lexemizer.next().is_none());
+/// ```
+pub struct Lexemizer<'a> {
+    // Whether `detect_c_string()` is tried before the other detectors.
+    c_strings: bool,
+    // The column of the next Lexeme. The first column is 1.
+    column: usize,
+    // The `detect_*()` functions for the edition of Rust that `orig` is in.
+    detectors_and_kinds: &'static [DetectorAndKind],
+    // The line number of the next Lexeme. The first line is 1.
+    line_number: usize,
+    // The original Rust code, assumed to conform to the 2018 edition.
+    orig: &'a str,
+    // The position that the next Lexeme starts.
+    pos: usize,
+    // The distance between tab stops. 1 means a tab is as wide as other chars.
+    tab_width: usize,
 }
 
+impl<'a> Lexemizer<'a> {
+    /// Creates a `Lexemizer`, which starts at the beginning of `orig`.
+    pub fn new(orig: &'a str) -> Self {
+        Self::with_edition(orig, RsEdition::Latest)
+    }
 
+    /// Creates a `Lexemizer` for a particular edition of Rust.
+    /// 
+    /// If `orig` begins with a byte order mark (U+FEFF), the `Lexemizer`
+    /// starts after it, at position 3. Its column is still 1.
+    pub fn with_edition(orig: &'a str, edition: RsEdition) -> Self {
+        Lexemizer {
+            c_strings: false,
+            column: 1,
+            detectors_and_kinds: match edition {
+                RsEdition::Rs2015 => &DETECTORS_AND_KINDS_2015,
+                RsEdition::Latest | RsEdition::Rs2018 => &DETECTORS_AND_KINDS,
+            },
+            line_number: 1,
+            orig,
+            // Skip a byte order mark, which is three bytes long in UTF-8.
+            pos: if orig.starts_with('\u{FEFF}') { 3 } else { 0 },
+            tab_width: 1,
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::{LexemizeResult,lexemize};
-    use super::super::lexeme::{Lexeme,LexemeKind};
+    /// Sets whether C strings, like `c"Hello"` and `cr#"Hello"#`, are detected.
+    /// 
+    /// C strings need the 2021 edition of Rust, so by default `c"Hello"` is
+    /// lexemized as the identifier `c` followed by the string `"Hello"`, like
+    /// rustc does for Rust 2018. If `replacement_value` is `true`, the whole of
+    /// `c"Hello"` becomes one `String` Lexeme instead.
+    /// ```
+    /// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::Lexemizer;
+    /// let orig = r#"c"Hi""#;
+    /// assert_eq!(Lexemizer::new(orig).count(), 2);
+    /// assert_eq!(Lexemizer::new(orig).c_strings(true).count(), 1);
+    /// ```
+    pub fn c_strings(mut self, replacement_value: bool) -> Self {
+        self.c_strings = replacement_value;
+        return self;
+    }
 
-    #[test]
-    fn lexemize_result_to_string_as_expected() {
-        let result = LexemizeResult {
-            end_pos: 123,
-            lexemes: vec![
-                Lexeme {
-                    kind: LexemeKind::Comment,
-                    pos: 0,
-                    snippet: "/* This is a comment */".into(),
-                },
-                Lexeme {
-                    kind: LexemeKind::Number,
-                    pos: 23,
-                    snippet: "44.4".into(),
-                },
-            ],
+    /// Sets the distance between tab stops, which is 1 by default.
+    /// 
+    /// With a `tab_width` of 4, a tab advances the column to 5, 9, 13, etc,
+    /// whichever is next. A `tab_width` of 0 is treated like 1.
+    pub fn tab_width(mut self, replacement_value: usize) -> Self {
+        self.tab_width = replacement_value;
+        return self;
+    }
+
+    // Returns a Lexeme which starts at `pos` and ends at `end`, and then
+    // advances `pos`, `line_number` and `column` to the end of its snippet.
+    fn make_lexeme(&mut self, kind: LexemeKind, end: usize) -> Lexeme<'a> {
+        let snippet = &self.orig[self.pos..end];
+        let lexeme = Lexeme {
+            column: self.column,
+            kind,
+            line_number: self.line_number,
+            pos: self.pos,
+            snippet,
         };
-        assert_eq!(result.to_string(),
-            "Lexemes found: 2\n\
-             Comment             0  /* This is a comment */\n\
-             Number             23  44.4\n\
-             EndOfInput        123  <EOI>"
-        );
+        // In whitespace, a "\r\n" pair, a lone "\r", and the Unicode Line
+        // Separator and Paragraph Separator each begin a new line. Only the
+        // chars after the last of them advance the column.
+        let mut rest = snippet;
+        if kind == LexemeKind::Whitespace {
+            let newlines =
+                count_newlines_in_whitespace(self.orig, self.pos, end);
+            if let Some((i, c)) = snippet.char_indices().rev().find(|(_, c)|
+                matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')) {
+                self.line_number += newlines;
+                self.column = 1;
+                rest = &snippet[i + c.len_utf8()..];
+            }
+        }
+        // Elsewhere, a newline begins a new line. A tab advances the column to
+        // the next tab stop. Any other char, even if it’s non-ascii, advances
+        // the column by one.
+        for c in rest.chars() {
+            if c == '\n' {
+                self.line_number += 1;
+                self.column = 1;
+            } else {
+                self.column = advance_column(self.column, c, self.tab_width);
+            }
+        }
+        self.pos = end;
+        lexeme
     }
+}
+
+impl<'a> Iterator for Lexemizer<'a> {
+    type Item = Lexeme<'a>;
+
+    fn next(&mut self) -> Option<Lexeme<'a>> {
+        let len = self.orig.len();
+        // Any unidentifiable characters will start at `pos`.
+        let mut i = self.pos;
+
+        // Loop until we reach the last character of the input string.
+        while i < len {
+            // Try each `detect_*()` function, in order, at `i`. A C string
+            // begins with a "c", so it must be tried before identifiers.
+            let c_string_end = if self.c_strings {
+                detect_c_string(self.orig, i) } else { i };
+            let found = if c_string_end != i {
+                Some((LexemeKind::String, c_string_end))
+            } else {
+                detect_first(self.detectors_and_kinds, self.orig, i)
+            };
+            if let Some((kind, next_pos)) = found {
+                // If any ‘Xtraneous’ characters precede this Lexeme, return
+                // them first. The Lexeme will be detected again next time.
+                return Some(if i != self.pos {
+                    self.make_lexeme(LexemeKind::Xtraneous, i)
+                } else {
+                    self.make_lexeme(kind, next_pos)
+                })
+            }
+
+            // Step forward one byte.
+            i += 1;
+        }
+
+        // If there are unidentifiable characters at the end of `orig`, return a
+        // final `Xtraneous` Lexeme. Otherwise, the iterator is finished.
+        if i != self.pos {
+            Some(self.make_lexeme(LexemeKind::Xtraneous, i))
+        } else {
+            None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::transpile::config::RsEdition;
+    use super::{Lexemizer,lexemize,lexemize_with_edition,next_lexeme_kind};
+    use super::{lexemize_checked,lexemize_with_limit,lexemize_with_tab_width};
 
     #[test]
     fn lexemize_all_lexemes() {
@@ -199,6 +469,276 @@ mod tests {
              EndOfInput          0  <EOI>");
     }
 
+    #[test]
+    fn lexemize_line_numbers_and_columns() {
+        let orig = "let a = 1;\n/* multi\nline */ \"two\nlines\"\n\n  é~b";
+        let result = lexemize(orig);
+        let positions: Vec<(usize, usize, &str)> = result.lexemes.iter()
+            .map(|l| (l.line_number, l.column, l.snippet))
+            .collect();
+        assert_eq!(positions, vec![
+            (1, 1, "let"),
+            (1, 4, " "),
+            (1, 5, "a"),
+            (1, 6, " "),
+            (1, 7, "="),
+            (1, 8, " "),
+            (1, 9, "1"),
+            (1, 10, ";"),
+            (1, 11, "\n"),
+            (2, 1, "/* multi\nline */"), // starts on line 2, ends on line 3
+            (3, 8, " "),
+            (3, 9, "\"two\nlines\""), // starts on line 3, ends on line 4
+            (4, 7, "\n\n  "),
+            (6, 3, "é"), // two bytes wide, but one column
+            (6, 4, "~"), // Xtraneous
+            (6, 5, "b"),
+        ]);
+        assert_eq!(result.end_line_number, 6);
+        assert_eq!(result.end_column, 6);
+        // Empty string.
+        let result = lexemize("");
+        assert_eq!((result.end_line_number, result.end_column), (1, 1));
+        // Xtraneous at the end of the input.
+        let result = lexemize("\n¶");
+        assert_eq!(result.lexemes[1].kind.as_str(), "Xtraneous");
+        assert_eq!(result.lexemes[1].line_number, 2);
+        assert_eq!(result.lexemes[1].column, 1);
+        assert_eq!((result.end_line_number, result.end_column), (2, 2));
+    }
+
+    #[test]
+    fn lexemize_line_numbers_across_whitespace() {
+        let line_numbers = |orig| -> Vec<(usize, usize, &str)> {
+            lexemize(orig).lexemes.iter()
+                .filter(|l| l.kind.as_str() != "Whitespace")
+                .map(|l| (l.line_number, l.column, l.snippet))
+                .collect()
+        };
+        // A "\r\n" is one newline, and so is a lone "\r", "\n", U+2028 or
+        // U+2029.
+        let orig = "a\r\n b\r\r\n\n c\r  d\u{2028}e\u{2029}\r\nf";
+        assert_eq!(line_numbers(orig),
+            vec![(1, 1, "a"), (2, 2, "b"), (5, 2, "c"), (6, 3, "d"),
+            (7, 1, "e"), (9, 1, "f")]);
+        // A string or comment still only counts "\n".
+        assert_eq!(line_numbers("\"\r\" /*\r\n*/ a"),
+            vec![(1, 1, "\"\r\""), (1, 5, "/*\r\n*/"), (2, 4, "a")]);
+        // The end of the input.
+        let result = lexemize("a\r\n\r");
+        assert_eq!((result.end_line_number, result.end_column), (3, 1));
+    }
+
+    #[test]
+    fn lexemize_checked_reports_ambiguities() {
+        // `r"x"` is claimed by both `detect_string()` and
+        // `detect_identifier()`, and the string wins.
+        let (lexemes, warnings) = lexemize_checked(r#"r"x""#);
+        assert_eq!(lexemes.lexemes.len(), 1);
+        assert_eq!(lexemes.lexemes[0].kind.as_str(), "String");
+        assert_eq!(warnings,
+            vec!["1:1 String wins, ending at 4, but Identifier ends at 1"]);
+        // A comment, which also begins with the punctuation `/`.
+        let (_, warnings) = lexemize_checked("a\n  // b");
+        assert_eq!(warnings,
+            vec!["2:3 Comment wins, ending at 8, but Punctuation ends at 5"]);
+        // A keyword is also an identifier, but they agree on where it ends.
+        let (lexemes, warnings) = lexemize_checked("fn f() {}");
+        assert_eq!(lexemes.to_string(), lexemize("fn f() {}").to_string());
+        assert!(warnings.is_empty());
+        // Nothing detects ‘Xtraneous’ characters, so they never disagree.
+        let (_, warnings) = lexemize_checked("~¶");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn lexemize_with_limit_truncates_cleanly() {
+        let orig = "const A: u8 = 1;\nconst B: &str = \"a long string\";\n";
+        // The limit is part way through the second "const".
+        let (result, truncated) = lexemize_with_limit(orig, 20);
+        assert!(truncated);
+        assert_eq!(result.lexemes.len(), 12);
+        assert_eq!(result.reconstruct(), "const A: u8 = 1;\n");
+        assert_eq!(result.end_pos, 17);
+        assert_eq!((result.end_line_number, result.end_column), (2, 1));
+        // The limit is part way through the string, which is left out whole.
+        let (result, truncated) = lexemize_with_limit(orig, 40);
+        assert!(truncated);
+        assert_eq!(result.reconstruct(), "const A: u8 = 1;\nconst B: &str = ");
+        assert_eq!((result.end_line_number, result.end_column), (2, 17));
+        // The limit is exactly at the end of a Lexeme.
+        let (result, truncated) = lexemize_with_limit(orig, 16);
+        assert!(truncated);
+        assert_eq!(result.reconstruct(), "const A: u8 = 1;");
+        // A limit of zero, or at or beyond the end of the input.
+        let (result, truncated) = lexemize_with_limit(orig, 0);
+        assert!(truncated);
+        assert!(result.lexemes.is_empty());
+        assert_eq!((result.end_pos, result.end_column), (0, 1));
+        for max_bytes in [orig.len(), 1000].iter() {
+            let (result, truncated) = lexemize_with_limit(orig, *max_bytes);
+            assert!(! truncated);
+            assert_eq!(result.to_string(), lexemize(orig).to_string());
+        }
+        // Empty input is never truncated.
+        assert!(! lexemize_with_limit("", 0).1);
+    }
+
+    #[test]
+    fn lexemize_hash_contexts() {
+        let kinds = |orig: &str| -> Vec<(&str, String)> {
+            lexemize(orig).lexemes.iter()
+                .map(|l| (l.kind.as_str(), l.snippet.to_string()))
+                .collect()
+        };
+        let k = |kind: &'static str, snippet: &str| (kind, snippet.to_string());
+        // A hash followed by a space is a single-char punctuation.
+        assert_eq!(kinds("# foo"), vec![k("Punctuation", "#"),
+            k("Whitespace", " "), k("Identifier", "foo")]);
+        assert_eq!(kinds("#"), vec![k("Punctuation", "#")]);
+        // Attributes, which are not split into punctuation.
+        assert_eq!(kinds("#[a]"), vec![k("Attribute", "#[a]")]);
+        assert_eq!(kinds("#![a]"), vec![k("Attribute", "#![a]")]);
+        // A shebang, which can only be at the very start.
+        assert_eq!(kinds("#!\n#[a] # b"), vec![k("Shebang", "#!"),
+            k("Whitespace", "\n"), k("Attribute", "#[a]"),
+            k("Whitespace", " "), k("Punctuation", "#"),
+            k("Whitespace", " "), k("Identifier", "b")]);
+        assert_eq!(kinds("a #!"), vec![k("Identifier", "a"),
+            k("Whitespace", " "), k("Punctuation", "#"),
+            k("Punctuation", "!")]);
+        // An unterminated attribute falls back to punctuation.
+        assert_eq!(kinds("#[a"), vec![k("Punctuation", "#"),
+            k("Punctuation", "["), k("Identifier", "a")]);
+    }
+
+    #[test]
+    fn lexemize_generics() {
+        let snippets = |orig: &str| -> Vec<String> {
+            lexemize(orig).lexemes.iter()
+                .map(|l| l.snippet.to_string())
+                .collect()
+        };
+        // Turbofish.
+        assert_eq!(snippets("Vec::<u8>::new()"),
+            vec!["Vec", "::", "<", "u8", ">", "::", "new", "(", ")"]);
+        assert_eq!(snippets("x.parse::<i32>()"),
+            vec!["x", ".", "parse", "::", "<", "i32", ">", "(", ")"]);
+        // Nested generics, and shifts, both use `>>`.
+        assert_eq!(snippets("Vec<Vec<u8>>"),
+            vec!["Vec", "<", "Vec", "<", "u8", ">>"]);
+        assert_eq!(snippets("a>>b>>=c"), vec!["a", ">>", "b", ">>=", "c"]);
+        // A path.
+        assert_eq!(snippets("std::mem::take"),
+            vec!["std", "::", "mem", "::", "take"]);
+    }
+
+    #[test]
+    fn lexemize_tab_width() {
+        let orig = "fn f() {\n\tlet a\t= 1;\n\t\ta\n}";
+        let columns = |tab_width: usize| -> Vec<(usize, usize, &str)> {
+            lexemize_with_tab_width(orig, tab_width).lexemes.iter()
+                .filter(|l| l.kind.as_str() != "Whitespace")
+                .skip(5) // "fn f() {"
+                .map(|l| (l.line_number, l.column, l.snippet))
+                .collect()
+        };
+        // With a tab width of 4, tabs advance to the next tab stop.
+        assert_eq!(columns(4), vec![
+            (2, 5, "let"),
+            (2, 9, "a"),
+            (2, 13, "="), // the tab after "a" only advances 3 columns
+            (2, 15, "1"),
+            (2, 16, ";"),
+            (3, 9, "a"),
+            (4, 1, "}"),
+        ]);
+        // A tab width of 1 is the default, and 0 is treated like 1.
+        let default: Vec<(usize, usize)> = lexemize(orig).lexemes.iter()
+            .map(|l| (l.line_number, l.column))
+            .collect();
+        for tab_width in [0, 1].iter() {
+            let result = lexemize_with_tab_width(orig, *tab_width);
+            let positions: Vec<(usize, usize)> = result.lexemes.iter()
+                .map(|l| (l.line_number, l.column))
+                .collect();
+            assert_eq!(positions, default);
+        }
+        assert_eq!(columns(1)[0], (2, 2, "let"));
+        // The end column counts tabs too.
+        let result = lexemize_with_tab_width("a\t", 8);
+        assert_eq!(result.end_column, 9);
+    }
+
+    #[test]
+    fn lexemizer_matches_lexemize() {
+        let orig = "fn main() {\n    let ¶a = 'b'; // c\n    \"d\"€\n}~¢";
+        let expected: Vec<String> = lexemize(orig).lexemes.iter()
+            .map(|l| format!("{} {}:{}", l, l.line_number, l.column))
+            .collect();
+        let actual: Vec<String> = Lexemizer::new(orig)
+            .map(|l| format!("{} {}:{}", l, l.line_number, l.column))
+            .collect();
+        assert_eq!(actual, expected);
+        // The trailing Xtraneous run is the final item.
+        assert_eq!(actual.last().unwrap(), "Xtraneous          48  ~¢ 4:2");
+        // Empty string.
+        assert!(Lexemizer::new("").next().is_none());
+    }
+
+    #[test]
+    fn next_lexeme_kind_matches_lexemize() {
+        // The first Lexeme of each input.
+        let origs = ["#!/bin/sh\nx", "#[a] b", "'a: loop", "'a' b", "/* a */b",
+            "// a\nb", "r#\"a\"# b", "fn main", "r#fn a", "1.5e3f32 a",
+            "..= a", " \t\nb", "'static", "b'a' c"];
+        for orig in origs.iter() {
+            let first = &lexemize(orig).lexemes[0];
+            let found = next_lexeme_kind(orig, 0);
+            assert!(found == Some((first.kind, first.snippet.len())), "{}", orig);
+        }
+        // Later Lexemes, part way through the input.
+        let orig = "let x = \"y\"; // z";
+        for lexeme in lexemize(orig).lexemes.iter() {
+            let found = next_lexeme_kind(orig, lexeme.pos);
+            let end = lexeme.pos + lexeme.snippet.len();
+            assert!(found == Some((lexeme.kind, end)), "{}", lexeme.snippet);
+        }
+        // Xtraneous chars, and invalid `pos`.
+        assert!(next_lexeme_kind("¶a", 0).is_none()); // xtraneous
+        assert!(next_lexeme_kind("€", 1).is_none()); // part way through
+        assert!(next_lexeme_kind("", 0).is_none()); // empty string
+        assert!(next_lexeme_kind("abc", 100).is_none()); // way out of range
+    }
+
+    #[test]
+    fn lexemize_byte_order_mark() {
+        // A byte order mark at the start is skipped, but positions still count
+        // its three bytes.
+        let result = lexemize("\u{FEFF}const A: u8 = 1;");
+        assert_eq!(result.lexemes[0].snippet, "const");
+        assert_eq!(result.lexemes[0].pos, 3);
+        assert_eq!(result.lexemes[0].column, 1);
+        assert_eq!(result.lexemes.len(), 11);
+        assert_eq!(result.end_pos, 19);
+        // A shebang can follow it.
+        assert_eq!(lexemize("\u{FEFF}#!/bin/x").to_string(),
+            "Lexemes found: 1\n\
+             Shebang             3  #!/bin/x\n\
+             EndOfInput         11  <EOI>");
+        // Just a byte order mark.
+        assert_eq!(lexemize("\u{FEFF}").to_string(),
+            "Lexemes found: 0\n\
+             EndOfInput          3  <EOI>");
+        // Anywhere else, it is xtraneous.
+        assert_eq!(lexemize("a\u{FEFF}").to_string(),
+            "Lexemes found: 2\n\
+             Identifier          0  a\n\
+             Xtraneous           1  \u{FEFF}\n\
+             EndOfInput          4  <EOI>");
+        assert_eq!(lexemize("\u{FEFF}\u{FEFF}").lexemes[0].pos, 3);
+    }
+
     #[test]
     fn lexemize_characters() {
         // Three Characters.
@@ -213,15 +753,39 @@ mod tests {
 
     #[test]
     fn lexemize_comments() {
-        // Three Comments.
+        // A DocComment and two Comments.
         assert_eq!(lexemize("/**A/*A'*/*///B\n//C").to_string(),
             "Lexemes found: 4\n\
-             Comment             0  /**A/*A'*/*/\n\
+             DocComment          0  /**A/*A'*/*/\n\
              Comment            12  //B\n\
              Whitespace         15  <NL>\n\
              Comment            16  //C\n\
              EndOfInput         19  <EOI>"
         );
+        // Three Comments.
+        assert_eq!(lexemize("/*A/*A'*/*///B\n//C").to_string(),
+            "Lexemes found: 4\n\
+             Comment             0  /*A/*A'*/*/\n\
+             Comment            11  //B\n\
+             Whitespace         14  <NL>\n\
+             Comment            15  //C\n\
+             EndOfInput         18  <EOI>"
+        );
+        // Doc comments, and comments which look like them.
+        assert_eq!(lexemize("/// doc\n// normal\n//// not\n/**A*//*!B*//**/")
+            .to_string(),
+            "Lexemes found: 9\n\
+             DocComment          0  /// doc\n\
+             Whitespace          7  <NL>\n\
+             Comment             8  // normal\n\
+             Whitespace         17  <NL>\n\
+             Comment            18  //// not\n\
+             Whitespace         26  <NL>\n\
+             DocComment         27  /**A*/\n\
+             DocComment         33  /*!B*/\n\
+             Comment            39  /**/\n\
+             EndOfInput         43  <EOI>"
+        );
     }
 
     #[test]
@@ -238,6 +802,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lexemize_attributes() {
+        // Two Attributes.
+        assert_eq!(lexemize("#![no_std]\n#[cfg(all(a, b))]fn").to_string(),
+            "Lexemes found: 4\n\
+             Attribute           0  #![no_std]\n\
+             Whitespace         10  <NL>\n\
+             Attribute          11  #[cfg(all(a, b))]\n\
+             Keyword            28  fn\n\
+             EndOfInput         30  <EOI>"
+        );
+        // An unterminated attribute falls back to Punctuation.
+        assert_eq!(lexemize("#[a").to_string(),
+            "Lexemes found: 3\n\
+             Punctuation         0  #\n\
+             Punctuation         1  [\n\
+             Identifier          2  a\n\
+             EndOfInput          3  <EOI>"
+        );
+    }
+
+    #[test]
+    fn lexemize_editions() {
+        // In 2018, `r#fn` is a raw identifier, and `async` is a keyword.
+        let orig = "r#fn async";
+        let expected_2018 =
+            "Lexemes found: 3\n\
+             Identifier          0  r#fn\n\
+             Whitespace          4   \n\
+             Keyword             5  async\n\
+             EndOfInput         10  <EOI>";
+        assert_eq!(lexemize(orig).to_string(), expected_2018);
+        assert_eq!(lexemize_with_edition(orig, RsEdition::Rs2018).to_string(),
+            expected_2018);
+        assert_eq!(lexemize_with_edition(orig, RsEdition::Latest).to_string(),
+            expected_2018);
+        // In 2015, `r#fn` is just `r`, `#` and `fn`, and `async` is not a
+        // keyword.
+        assert_eq!(lexemize_with_edition(orig, RsEdition::Rs2015).to_string(),
+            "Lexemes found: 5\n\
+             Identifier          0  r\n\
+             Punctuation         1  #\n\
+             Keyword             2  fn\n\
+             Whitespace          4   \n\
+             Identifier          5  async\n\
+             EndOfInput         10  <EOI>");
+        // Raw strings are the same in both editions.
+        assert_eq!(lexemize_with_edition("r#\"a\"#", RsEdition::Rs2015)
+            .to_string(),
+            "Lexemes found: 1\n\
+             String              0  r#\"a\"#\n\
+             EndOfInput          6  <EOI>");
+    }
+
+    #[test]
+    fn lexemizer_c_strings() {
+        let describe = |lexemizer: Lexemizer| lexemizer
+            .map(|l| format!("{} {}", l.kind, l.snippet))
+            .collect::<Vec<String>>();
+        // By default, like Rust 2018, a C string is an identifier and a string.
+        let orig = r##"c"x" cr#"y"#"##;
+        assert_eq!(describe(Lexemizer::new(orig)), vec![
+            "Identifier c", "String \"x\"", "Whitespace  ",
+            "Identifier cr", "Punctuation #", "String \"y\"", "Punctuation #"]);
+        assert_eq!(describe(Lexemizer::new(orig).c_strings(false)),
+            describe(Lexemizer::new(orig)));
+        // With C strings enabled, each is one String Lexeme.
+        assert_eq!(describe(Lexemizer::new(orig).c_strings(true)), vec![
+            "String c\"x\"", "Whitespace  ", "String cr#\"y\"#"]);
+        // Identifiers which begin with "c", and other strings, are unchanged.
+        let orig = r#"cat c"#;
+        assert_eq!(describe(Lexemizer::new(orig).c_strings(true)),
+            describe(Lexemizer::new(orig)));
+        let orig = r#"ac"x" b"y" "z""#;
+        assert_eq!(describe(Lexemizer::new(orig).c_strings(true)),
+            describe(Lexemizer::new(orig)));
+    }
+
+    #[test]
+    fn lexemize_keywords() {
+        // A raw identifier is never a Keyword.
+        assert_eq!(lexemize("fn function r#fn").to_string(),
+            "Lexemes found: 5\n\
+             Keyword             0  fn\n\
+             Whitespace          2   \n\
+             Identifier          3  function\n\
+             Whitespace         11   \n\
+             Identifier         12  r#fn\n\
+             EndOfInput         16  <EOI>"
+        );
+    }
+
+    #[test]
+    fn lexemize_lifetimes() {
+        // A Character, and three Lifetimes.
+        assert_eq!(lexemize("'a' 'a 'static '_").to_string(),
+            "Lexemes found: 7\n\
+             Character           0  'a'\n\
+             Whitespace          3   \n\
+             Lifetime            4  'a\n\
+             Whitespace          6   \n\
+             Lifetime            7  'static\n\
+             Whitespace         14   \n\
+             Lifetime           15  '_\n\
+             EndOfInput         17  <EOI>"
+        );
+        // A label.
+        assert_eq!(lexemize("break'outer;").to_string(),
+            "Lexemes found: 3\n\
+             Keyword             0  break\n\
+             Lifetime            5  'outer\n\
+             Punctuation        11  ;\n\
+             EndOfInput         12  <EOI>"
+        );
+    }
+
     #[test]
     fn lexemize_numbers() {
         // Three Numbers.
@@ -262,6 +942,54 @@ mod tests {
              Punctuation         3  >>=\n\
              EndOfInput          6  <EOI>"
         );
+        // Inclusive ranges, paths and return types.
+        assert_eq!(lexemize("0..=3::x->").to_string(),
+            "Lexemes found: 6\n\
+             Number              0  0\n\
+             Punctuation         1  ..=\n\
+             Number              4  3\n\
+             Punctuation         5  ::\n\
+             Identifier          7  x\n\
+             Punctuation         8  ->\n\
+             EndOfInput         10  <EOI>"
+        );
+    }
+
+    #[test]
+    fn lexemize_underscores() {
+        // A lone underscore is Punctuation, but `_x` is an Identifier.
+        assert_eq!(lexemize("_ _,_x(_)").to_string(),
+            "Lexemes found: 8\n\
+             Punctuation         0  _\n\
+             Whitespace          1   \n\
+             Punctuation         2  _\n\
+             Punctuation         3  ,\n\
+             Identifier          4  _x\n\
+             Punctuation         6  (\n\
+             Punctuation         7  _\n\
+             Punctuation         8  )\n\
+             EndOfInput          9  <EOI>"
+        );
+    }
+
+    #[test]
+    fn lexemize_shebangs() {
+        // A Shebang, which is only detected at the start of the input.
+        assert_eq!(lexemize("#!/bin/x\n#!y").to_string(),
+            "Lexemes found: 5\n\
+             Shebang             0  #!/bin/x\n\
+             Whitespace          8  <NL>\n\
+             Punctuation         9  #\n\
+             Punctuation        10  !\n\
+             Identifier         11  y\n\
+             EndOfInput         12  <EOI>"
+        );
+        // An inner attribute is not a Shebang.
+        assert_eq!(lexemize("#![feature(x)]").to_string(),
+            "Lexemes found: 1\n\
+             Attribute           0  #![feature(x)]\n\
+             EndOfInput         14  <EOI>"
+        );
     }
 
     #[test]
@@ -281,11 +1009,11 @@ mod tests {
         // Three Whitespace.
         assert_eq!(lexemize("\t\ta \n\nb\r ").to_string(),
             "Lexemes found: 5\n\
-             Whitespace          0  \t\t\n\
+             Whitespace          0  <TB><TB>\n\
              Identifier          2  a\n\
              Whitespace          3   <NL><NL>\n\
              Identifier          6  b\n\
-             Whitespace          7  \r \n\
+             Whitespace          7  <CR> \n\
              EndOfInput          9  <EOI>"
       );
     }
@@ -307,10 +1035,17 @@ mod tests {
              EndOfInput          3  <EOI>"
         );
         // Ascii.
-        assert_eq!(lexemize("é¢€±").to_string(),
+        assert_eq!(lexemize("§¢€±").to_string(),
             "Lexemes found: 1\n\
-             Xtraneous           0  é¢€±\n\
+             Xtraneous           0  §¢€±\n\
              EndOfInput          9  <EOI>"
         );
+        // Non-ascii identifier chars are not Xtraneous.
+        assert_eq!(lexemize("é¢").to_string(),
+            "Lexemes found: 2\n\
+             Identifier          0  é\n\
+             Xtraneous           2  ¢\n\
+             EndOfInput          4  <EOI>"
+        );
     }
 }
@@ -0,0 +1,259 @@
+//! Converts character positions into line numbers and columns.
+
+/// Finds the position where each line of `orig` starts.
+/// 
+/// Only `"\n"` begins a new line. So a Windows style `"\r\n"` line ending is
+/// treated as a `"\r"` at the end of a line. This matches `Lexemizer`, unless
+/// its whitespace contains a lone `"\r"`, or a Unicode Line Separator or
+/// Paragraph Separator, which `Lexemizer` also counts as a new line.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// 
+/// ### Returns
+/// A vector of positions, in ascending order. The first is always zero, even
+/// if `orig` is empty. If `orig` ends with a newline, the last position is
+/// `orig.len()`, where the empty final line starts.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::line_starts::*;
+/// assert_eq!(line_starts("ab\ncd\n"), vec![0, 3, 6]);
+/// ```
+pub fn line_starts(orig: &str) -> Vec<usize> {
+    // The first line starts at zero, and every other line starts after a "\n".
+    let mut starts = vec![0];
+    starts.extend(orig.match_indices('\n').map(|(i, _)| i + 1));
+    starts
+}
+
+/// Converts a position into a line number and column, in O(log n) time.
+/// 
+/// ### Arguments
+/// * `line_starts` The vector returned by `line_starts()`
+/// * `pos` The character position in `orig` to look up
+/// 
+/// ### Returns
+/// A `(line_number, column)` tuple. The first line is 1, and the first column
+/// is 1. Note that the column counts bytes, not chars, so it can differ from a
+/// `Lexeme`’s column when the line contains non-ascii chars. For a column which
+/// counts chars, use `byte_to_char_col()`.
+pub fn pos_to_line_col(line_starts: &[usize], pos: usize) -> (usize, usize) {
+    // Find the last line which starts at or before `pos`.
+    let index = match line_starts.binary_search(&pos) {
+        Ok(index) => index,
+        Err(index) => index.saturating_sub(1),
+    };
+    let start = line_starts.get(index).copied().unwrap_or(0);
+    (index + 1, pos - start + 1)
+}
+
+/// Converts a position into a column which counts chars, rather than bytes.
+/// 
+/// TypeScript tooling usually expects columns to count chars, so a `€` (which
+/// is three bytes wide) only advances the column by one. This is how the
+/// `Lexemizer` counts columns, too. A tab also advances the column by one. To
+/// count tabs as wider, use `byte_to_tab_col()`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `line_start` The position where the line starts, from `line_starts()`
+/// * `pos` The character position in `orig` to look up
+/// 
+/// ### Returns
+/// The column of `pos`, where the first column is 1. If `pos` is part way
+/// through a non-ascii char, it is rounded down to the start of that char. If
+/// `pos` is out of range, it is treated as the end of `orig`.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::line_starts::*;
+/// assert_eq!(byte_to_char_col("a\n€b", 2, 5), 2); // the "b"
+/// ```
+pub fn byte_to_char_col(orig: &str, line_start: usize, pos: usize) -> usize {
+    byte_to_tab_col(orig, line_start, pos, 1)
+}
+
+/// Like `byte_to_char_col()`, but a tab advances to the next tab stop.
+/// 
+/// Editors disagree about how wide a tab is, so error messages which point
+/// into tab-indented code can be misaligned. With a `tab_width` of 4, a tab at
+/// column 1 or 3 advances to column 5, like it does in an editor.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `line_start` The position where the line starts, from `line_starts()`
+/// * `pos` The character position in `orig` to look up
+/// * `tab_width` The distance between tab stops, where 1 (or 0) means that a
+///   tab is as wide as any other char
+/// 
+/// ### Returns
+/// The column of `pos`, where the first column is 1.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::line_starts::*;
+/// assert_eq!(byte_to_tab_col("\tlet x;", 0, 1, 4), 5); // the "l"
+/// assert_eq!(byte_to_tab_col("a\tb", 0, 2, 4), 5); // the "b"
+/// ```
+pub fn byte_to_tab_col(
+    orig: &str,
+    line_start: usize,
+    pos: usize,
+    tab_width: usize,
+) -> usize {
+    // Round both positions down to the start of a char, within `orig`.
+    let floor = |mut p: usize| {
+        p = p.min(orig.len());
+        while ! orig.is_char_boundary(p) { p -= 1 }
+        p
+    };
+    let (start, end) = (floor(line_start), floor(pos));
+    if end <= start { return 1 }
+    orig[start..end].chars()
+        .fold(1, |column, c| advance_column(column, c, tab_width))
+}
+
+/// Returns the column after a char, which is not a newline.
+/// 
+/// ### Arguments
+/// * `column` The column of `c`, where the first column is 1
+/// * `c` The char, which is only treated specially if it is a tab
+/// * `tab_width` The distance between tab stops, where 1 (or 0) means that a
+///   tab is as wide as any other char
+/// 
+/// ### Returns
+/// `column + 1`, unless `c` is a tab, in which case it is the column of the
+/// next tab stop.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::line_starts::*;
+/// assert_eq!(advance_column(1, 'a', 4), 2);
+/// assert_eq!(advance_column(2, '\t', 4), 5);
+/// assert_eq!(advance_column(5, '\t', 4), 9);
+/// ```
+pub fn advance_column(column: usize, c: char, tab_width: usize) -> usize {
+    if c != '\t' || tab_width <= 1 { return column + 1 }
+    // Tab stops are at columns 1, 1 + tab_width, 1 + 2 * tab_width, etc.
+    (column - 1) / tab_width * tab_width + tab_width + 1
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_starts_as_expected() {
+        assert_eq!(line_starts(""), vec![0]); // empty input
+        assert_eq!(line_starts("abc"), vec![0]); // no newline
+        assert_eq!(line_starts("a\nb"), vec![0, 2]);
+        assert_eq!(line_starts("a\n"), vec![0, 2]); // trailing newline
+        assert_eq!(line_starts("\n\n"), vec![0, 1, 2]); // only newlines
+        assert_eq!(line_starts("a\r\nb\r\n"), vec![0, 3, 6]); // CRLF
+        assert_eq!(line_starts("a\rb"), vec![0]); // a lone "\r" is not a NL
+        assert_eq!(line_starts("€\n€"), vec![0, 4]); // non-ascii
+    }
+
+    #[test]
+    fn pos_to_line_col_as_expected() {
+        // Empty input.
+        let starts = line_starts("");
+        assert_eq!(pos_to_line_col(&starts, 0), (1, 1));
+        // CRLF.
+        let starts = line_starts("ab\r\ncd");
+        assert_eq!(pos_to_line_col(&starts, 0), (1, 1)); // a
+        assert_eq!(pos_to_line_col(&starts, 2), (1, 3)); // <CR>
+        assert_eq!(pos_to_line_col(&starts, 3), (1, 4)); // <NL>
+        assert_eq!(pos_to_line_col(&starts, 4), (2, 1)); // c
+        assert_eq!(pos_to_line_col(&starts, 6), (2, 3)); // end of input
+        // Trailing newline.
+        let starts = line_starts("a\n");
+        assert_eq!(pos_to_line_col(&starts, 1), (1, 2)); // <NL>
+        assert_eq!(pos_to_line_col(&starts, 2), (2, 1)); // end of input
+        // Many lines.
+        let starts = line_starts("\n\nab\n\ncd");
+        assert_eq!(pos_to_line_col(&starts, 3), (3, 2)); // b
+        assert_eq!(pos_to_line_col(&starts, 7), (5, 2)); // d
+    }
+
+    #[test]
+    fn pos_to_line_col_matches_lexemizer() {
+        use super::super::lexemize::lexemize;
+        let orig = "const A: u8 = 1;\r\n\n  // x\nconst B: u8 = 2;\n";
+        let starts = line_starts(orig);
+        for lexeme in lexemize(orig).lexemes {
+            assert_eq!(pos_to_line_col(&starts, lexeme.pos),
+                (lexeme.line_number, lexeme.column));
+        }
+    }
+
+    #[test]
+    fn byte_to_char_col_as_expected() {
+        // Ascii, so chars and bytes are the same.
+        assert_eq!(byte_to_char_col("abc", 0, 0), 1); // a
+        assert_eq!(byte_to_char_col("abc", 0, 2), 3); // c
+        assert_eq!(byte_to_char_col("abc", 0, 3), 4); // end of input
+        // Multi-byte chars, on the second line.
+        let orig = "x\n€é;";
+        let starts = line_starts(orig);
+        assert_eq!(byte_to_char_col(orig, starts[1], 2), 1); // €
+        assert_eq!(byte_to_char_col(orig, starts[1], 5), 2); // é
+        assert_eq!(byte_to_char_col(orig, starts[1], 7), 3); // ;
+        assert_eq!(pos_to_line_col(&starts, 7), (2, 6)); // ; counted in bytes
+        // Part way through a multi-byte char rounds down.
+        assert_eq!(byte_to_char_col(orig, starts[1], 3), 1); // in €
+        assert_eq!(byte_to_char_col(orig, starts[1], 4), 1); // in €
+        assert_eq!(byte_to_char_col(orig, starts[1], 6), 2); // in é
+    }
+
+    #[test]
+    fn byte_to_char_col_matches_lexemizer() {
+        use super::super::lexemize::lexemize;
+        let orig = "let é = \"€\";\n  /* ü */ 'ß'\n";
+        let starts = line_starts(orig);
+        for lexeme in lexemize(orig).lexemes {
+            let line_start = starts[lexeme.line_number - 1];
+            assert_eq!(byte_to_char_col(orig, line_start, lexeme.pos),
+                lexeme.column);
+        }
+    }
+
+    #[test]
+    fn byte_to_tab_col_as_expected() {
+        // A tab-indented line.
+        let orig = "fn f() {\n\tlet x = 1;\n\t\tx\n}";
+        let starts = line_starts(orig);
+        assert_eq!(byte_to_tab_col(orig, starts[1], 10, 4), 5); // let
+        assert_eq!(byte_to_tab_col(orig, starts[1], 14, 4), 9); // x
+        assert_eq!(byte_to_tab_col(orig, starts[2], 23, 4), 9); // x
+        assert_eq!(byte_to_tab_col(orig, starts[2], 23, 1), 3); // x
+        // A tab after some other chars only advances to the next tab stop.
+        assert_eq!(byte_to_tab_col("ab\tc", 0, 3, 4), 5); // c
+        assert_eq!(byte_to_tab_col("abcd\te", 0, 5, 4), 9); // e
+        assert_eq!(byte_to_tab_col("€\tc", 0, 4, 4), 5); // c
+        // A tab width of 0 is treated like 1.
+        assert_eq!(byte_to_tab_col("\t\tc", 0, 2, 0), 3); // c
+    }
+
+    #[test]
+    fn byte_to_tab_col_matches_lexemizer() {
+        use super::super::lexemize::lexemize_with_tab_width;
+        let orig = "fn f() {\n\tlet a\t= 1;\n \t/* b */\t'c'\n}\n";
+        let starts = line_starts(orig);
+        for lexeme in lexemize_with_tab_width(orig, 4).lexemes {
+            let line_start = starts[lexeme.line_number - 1];
+            assert_eq!(byte_to_tab_col(orig, line_start, lexeme.pos, 4),
+                lexeme.column);
+        }
+    }
+
+    #[test]
+    fn byte_to_char_col_will_not_panic() {
+        assert_eq!(byte_to_char_col("", 0, 0), 1); // empty input
+        assert_eq!(byte_to_char_col("€", 0, 100), 2); // out of range
+        assert_eq!(byte_to_char_col("€", 100, 100), 1); // both out of range
+        assert_eq!(byte_to_char_col("ab", 2, 1), 1); // `pos` before the line
+        assert_eq!(byte_to_char_col("€€", 1, 5), 2); // start is inside a €
+    }
+
+    #[test]
+    fn pos_to_line_col_will_not_panic() {
+        assert_eq!(pos_to_line_col(&[], 0), (1, 1)); // no line starts
+        assert_eq!(pos_to_line_col(&[], 5), (1, 6)); // no line starts
+        assert_eq!(pos_to_line_col(&[0, 2], 100), (2, 99)); // out of range
+    }
+}
@@ -3,3 +3,4 @@
 pub mod detect;
 pub mod lexeme;
 pub mod lexemize;
+pub mod line_starts;
@@ -1,22 +1,2927 @@
 //! Tools for transpiling Rust 2018 to TypeScript 4 using the ‘Gungho’ strategy.
 
+use crate::transpile::config::{Config,TsMajor};
 use crate::transpile::result::TranspileResult;
+use super::lexemize::detect::comment::CommentScan;
+use super::lexemize::detect::character::detect_multi_char_character;
+use super::lexemize::detect::comment::detect_multiline_comment_status;
+use super::lexemize::detect::number::detect_number_detailed;
+use super::lexemize::detect::number::float_is_js_compatible;
+use super::lexemize::detect::number::number_exceeds_js_safe_integer;
+use super::lexemize::detect::number::number_exceeds_u128;
+use super::lexemize::detect::number::uppercase_radix_hint;
+use super::lexemize::detect::string::detect_unterminated_string;
+use super::lexemize::lexeme::{Lexeme,LexemeKind};
+use super::lexemize::lexemize::lexemize;
 
 /// Transpiles Rust 2018 code to TypeScript 4 code using the ‘Gungho’ strategy.
 /// 
+/// The input is split into statements, which end with a `;`, or with a `}`
+/// which closes a top-level block. Each statement is transpiled independently.
+/// 
+/// Currently, these top-level statements are transpiled: `const` declarations,
+/// like `const X: u8 = 4;`, `let` statements, functions, `for` loops over a
+/// range, `if` statements, structs, fieldless enums and print macros, like
+/// `println!()`. Attributes, like `#[inline]`, and `pub` are dropped. Any other
+/// statement is reported as an `UnsupportedSyntax` error. Whitespace and
+/// comments between the lexemes are ignored, and the final semicolon may be
+/// left out.
+/// 
+/// The value can also be an identifier, and can be cast, like `y as f64`,
+/// which becomes `(y as number)`. JavaScript has no `u8`, so a narrowing cast,
+/// like `n as u8`, becomes a call to a polyfilled helper, like `r$t$u8(n)`.
+/// 
+/// A `let` statement, like `let mut n: u8 = 4;`, becomes a TypeScript `let`
+/// statement, like `let n: number = 4;`. The type is optional. A TypeScript
+/// `let` is always mutable, so `mut` is dropped. An immutable `let` which is
+/// never reassigned could become a `const`, but for now it stays a `let`.
+/// 
+/// As well as numbers, a value can be `true` or `false`, a char literal, or a
+/// string literal, which is transpiled by `rust_string_to_ts()`.
+/// A char, like `'A'` or `'\n'`, becomes a single-character string, like `"A"`
+/// or `"\n"`. A byte char, like `b'A'`, becomes a number, like `65`. A char
+/// literal with several chars, like `'ab'`, is reported as an
+/// `UnsupportedSyntax` error, which suggests using a string literal instead.
+/// 
+/// A `for` loop over a range, like `for i in 0..n {}`, becomes a TypeScript
+/// `for (let i = 0; i < n; i++) { }` loop, and `0..=n` becomes `i <= n`. Each
+/// bound must be an integer or an identifier, and the loop body may only
+/// contain `const` declarations. The `...` range, which was removed from Rust,
+/// is reported as an `UnsupportedSyntax` error, wherever it appears.
+/// 
+/// A function, like `fn f(x: u8) -> bool { x > 1 }`, becomes a TypeScript
+/// `function f(x: number): boolean { return x > 1; }` declaration. The types
+/// are mapped by `map_rust_type_to_ts()`, and a missing return type becomes
+/// `void`. The body is passed through unchanged, except that a final
+/// expression, which Rust returns implicitly, gets an explicit `return`. A
+/// final expression which ends with a `}`, like an `if` or a struct literal,
+/// does not, yet. Generic functions are not supported yet.
+/// 
+/// An `if` statement, like `if a > b { a } else { b }`, is copied verbatim,
+/// except that each condition is wrapped in brackets, like
+/// `if (a > b) { a } else { b }`. `else if` chains are supported, but `if let`
+/// is not supported yet.
+/// 
+/// Function bodies and `if` statements keep any trailing commas, like in
+/// `f(a, b,)`, unless `Config::strip_trailing_commas` is set. Then they become
+/// `f(a, b)`, which suits older JavaScript engines.
+/// 
+/// A struct, like `struct Point { pub x: f64, y: f64 }`, becomes a TypeScript
+/// interface, like `interface Point { x: number; y: number; }`, which is added
+/// to `type_lines`. The fields’ types are mapped by `map_rust_type_to_ts()`,
+/// and `pub` is dropped. Tuple structs, like `struct P(f64, f64);`, and
+/// generic structs are not supported yet.
+/// 
+/// A fieldless enum, like `enum Color { Red, Green = 2 }`, becomes a TypeScript
+/// enum, like `enum Color { Red, Green = 2 }`, which is added to `type_lines`.
+/// Variants with data, like `A(u8)`, and generic enums are not supported yet.
+/// 
+/// A type can be compound, like `Vec<u8>`, `[u8; 4]` or `Option<&str>`. A
+/// generic type with no TypeScript equivalent yet, like `HashMap<K, V>`,
+/// becomes `any`, and an `AnyType` warning is recorded in the `warnings`
+/// vector.
+/// 
+/// A `println!("{} + {}", a, 1)` call becomes a TypeScript
+/// `` console.log(`${a} + ${1}`) `` call, and `eprintln!` becomes
+/// `console.error()`. `print!` and `eprint!` are transpiled the same way, so
+/// their output still ends with a newline. Only `{}` placeholders are
+/// supported, so a placeholder like `{name}` or `{:?}` is reported as an
+/// `UnsupportedSyntax` error.
+/// 
+/// A method call, like `s.len()`, is passed through unchanged. If JavaScript
+/// has no method with that name, a polyfill for it is added, once, from
+/// `METHOD_POLYFILLS`. The `.len()` polyfill works on strings, and on arrays,
+/// which is what a `Vec` becomes. Methods which JavaScript already has, like
+/// `v.push(x)` and `s.trim()`, need no polyfill. In function bodies and `if`
+/// statements, `n.to_string()` becomes `String(n)`, so it needs no polyfill
+/// either.
+/// 
+/// Comments between statements are valid TypeScript, so they are kept. A
+/// comment on the same line as the end of a statement stays on that line. Doc
+/// comments, like `/// Doc`, become plain comments, like `// Doc`.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// 
 /// ### Returns
-/// @TODO document what this function returns
+/// A [`TranspileResult`], with one or more of its `main_lines` for each
+/// statement, except that structs and enums are added to its `type_lines`. If a
+/// statement is malformed or unsupported, an error is recorded in its `errors`
+/// vector, with the line number and column where the problem was found. A
+/// string which is never closed is reported as an `UnterminatedString` error,
+/// and a multiline comment which is never closed is reported as an
+/// `UnterminatedComment` error. Nothing after the opening double quote or `/*`
+/// is transpiled.
+/// 
+/// To preserve line numbers, empty `main_lines` are added where needed, so that
+/// each statement begins on the same line as it does in `orig`. The whitespace
+/// which indents a statement is recorded in `original_whitespace`, so that
+/// `to_string()` can reproduce it.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// let result = rs2018_ts4_gungho("const A: u8 = 1;\n\nconst B: f32 = 2.5;");
+/// assert_eq!(result.main_lines,
+///     vec!["const A: number = 1;", "", "const B: number = 2.5;"]);
+/// ```
 pub fn rs2018_ts4_gungho(
     orig: &str
 ) -> TranspileResult {
-    if orig.contains("FOUR") {
-        TranspileResult::new()
-            .push_main_line("const FOUR: Number = 4;")
+    rs2018_ts4_gungho_with_ts_major(orig, &TsMajor::Latest)
+}
+
+/// Transpiles Rust 2018 code to a particular major version of TypeScript,
+/// using the ‘Gungho’ strategy.
+/// 
+/// The only difference is how an integer larger than `Number.MAX_SAFE_INTEGER`
+/// is transpiled. TypeScript 4 has `bigint` literals, so
+/// `const BIG: u64 = 9007199254740993;` becomes
+/// `const BIG: bigint = 9007199254740993n;`. Under TypeScript 3 the integer
+/// stays a `number`, which cannot represent it exactly, and a `LossOfPrecision`
+/// warning is recorded in the `warnings` vector. A cast, like `BIG as u8`, is
+/// never a `bigint`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `ts_major` The major version of TypeScript to output
+/// 
+/// ### Returns
+/// A [`TranspileResult`], like `rs2018_ts4_gungho()` returns.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// # use opinionated_rust_to_typescript::transpile::config::TsMajor;
+/// let orig = "const BIG: u64 = 9007199254740993;";
+/// let result = rs2018_ts4_gungho_with_ts_major(orig, &TsMajor::Ts4);
+/// assert_eq!(result.main_lines, vec!["const BIG: bigint = 9007199254740993n;"]);
+/// let result = rs2018_ts4_gungho_with_ts_major(orig, &TsMajor::Ts3);
+/// assert_eq!(result.main_lines, vec!["const BIG: number = 9007199254740993;"]);
+/// assert_eq!(result.warnings.len(), 1);
+/// ```
+pub fn rs2018_ts4_gungho_with_ts_major(
+    orig: &str,
+    ts_major: &TsMajor,
+) -> TranspileResult {
+    rs2018_ts4_gungho_with_config(orig, &Config::new().ts_major(*ts_major))
+}
+
+/// Transpiles Rust 2018 code to TypeScript using the ‘Gungho’ strategy, and
+/// the `ts_major` and `use_primitive_types` settings of a [`Config`].
+/// 
+/// If `use_primitive_types` is `false`, types become TypeScript’s wrapper
+/// object types, so `const A: f32 = 1.5;` becomes `const A: Number = 1.5;`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `config` The settings to use. Its `rs_edition` and `strategy` are ignored
+/// 
+/// ### Returns
+/// A [`TranspileResult`], like `rs2018_ts4_gungho()` returns.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// # use opinionated_rust_to_typescript::transpile::config::Config;
+/// let orig = "let s: &str = \"a\";";
+/// let result = rs2018_ts4_gungho_with_config(orig, &Config::new());
+/// assert_eq!(result.main_lines, vec!["let s: string = \"a\";"]);
+/// let config = Config::new().use_primitive_types(false);
+/// let result = rs2018_ts4_gungho_with_config(orig, &config);
+/// assert_eq!(result.main_lines, vec!["let s: String = \"a\";"]);
+/// ```
+pub fn rs2018_ts4_gungho_with_config(
+    orig: &str,
+    config: &Config,
+) -> TranspileResult {
+    let ts_major = &config.ts_major;
+    // Lexemize `orig`, ignoring whitespace and comments.
+    let lexemes = lexemize(orig);
+    let significant: Vec<&Lexeme> = lexemes.lexemes.iter()
+        .filter(|l| l.kind != LexemeKind::Whitespace
+            && l.kind != LexemeKind::Comment
+            && l.kind != LexemeKind::DocComment)
+        .collect();
+
+    // An unterminated string or multiline comment runs to the end of the
+    // input, so nothing from its opening double quote or "/*" onwards can be
+    // transpiled.
+    let unterminated = significant.iter()
+        .position(|l| starts_unterminated(orig, l));
+    let (significant, unterminated) = match unterminated {
+        Some(i) => (&significant[..i], Some(significant[i])),
+        None => (&significant[..], None),
+    };
+    let end_pos = unterminated.map_or(orig.len(), |l| l.pos);
+
+    // Comments are kept, unless they are inside a statement.
+    let comments: Vec<&Lexeme> = lexemes.lexemes.iter()
+        .filter(|l| l.pos < end_pos && (l.kind == LexemeKind::Comment
+            || l.kind == LexemeKind::DocComment))
+        .collect();
+    let mut comments = comments.into_iter().peekable();
+
+    // Transpile each statement independently.
+    let mut result = TranspileResult::new();
+    // The line where the last comment which was kept ends, or 0.
+    let mut comment_end_line = 0;
+    // The Lexemes of each statement which was transpiled, so polyfills are not
+    // added for statements which were rejected.
+    let mut transpiled_lexemes: Vec<&Lexeme> = vec![];
+    for statement in split_statements(significant) {
+        // Keep any comments before the statement, and skip any inside it. A
+        // multiline comment ends as many lines after it begins as it has
+        // newlines.
+        let start = statement[0].pos;
+        let last = statement[statement.len() - 1];
+        let end = last.pos + last.snippet.len();
+        while let Some(comment) = comments.next_if(|c| c.pos < end) {
+            if comment.pos >= start { continue }
+            result = push_comment(result, comment);
+            comment_end_line =
+                comment.line_number + comment.snippet.matches('\n').count();
+        }
+        // Attributes and `pub` are dropped, like `#[derive(Debug)]` before a
+        // struct, or the `pub` in `pub fn f() {}`.
+        let statement = match skip_attributes_and_visibility(statement, 0) {
+            Ok(i) if i < statement.len() => &statement[i..],
+            _ => {
+                result = result.push_unsupported_syntax_error(
+                    statement[0].column, statement[0].line_number,
+                    "Unsupported statement");
+                continue;
+            },
+        };
+        // Add blank lines, so the statement begins on its original line.
+        let line_number = statement[0].line_number;
+        while result.main_lines.len() + 1 < line_number {
+            result = result.push_main_line("");
+        }
+        // A char literal holds one char, so `'ab'` should probably have been
+        // the string literal `"ab"`. This is checked first, because the
+        // lexemizer may have split it into Xtraneous Lexemes.
+        if let Some(lexeme) = statement.iter().find(|l|
+            detect_multi_char_character(orig, l.pos) != l.pos) {
+            result = result.push_unsupported_syntax_error(lexeme.column,
+                lexeme.line_number,
+                "Chars hold one character, use double quotes for a string");
+            continue;
+        }
+        // Anything the lexemizer could not identify cannot be transpiled.
+        if let Some(lexeme) = statement.iter()
+            .find(|l| l.kind == LexemeKind::Xtraneous) {
+            result = result.push_unsupported_syntax_error(lexeme.column,
+                lexeme.line_number, "Unsupported characters");
+            continue;
+        }
+        // The `...` range pattern was removed from Rust, in favour of `..=`.
+        if let Some(lexeme) = statement.iter().find(|l|
+            l.kind == LexemeKind::Punctuation && l.snippet == "...") {
+            result = result.push_unsupported_syntax_error(lexeme.column,
+                lexeme.line_number, "The `...` range was removed, use `..=`");
+            continue;
+        }
+        // Rust cannot parse an integer larger than `u128::MAX`, even though
+        // the lexemizer accepts it.
+        if let Some(lexeme) = statement.iter().find(|l|
+            l.kind == LexemeKind::Number && number_exceeds_u128(l.snippet, 0)) {
+            result = result.push_invalid_number_error(lexeme.column,
+                lexeme.line_number, "Integer is larger than u128::MAX");
+            continue;
+        }
+        // An uppercase radix prefix, like "0XAB", lexemizes as "0" and "XAB",
+        // which is probably a typo. The statement is still transpiled, but is
+        // likely to be malformed.
+        for lexeme in statement.iter() {
+            if lexeme.kind != LexemeKind::Number { continue }
+            if let Some(hint) = uppercase_radix_hint(orig, lexeme.pos) {
+                result = result.push_hint_warning(lexeme.column,
+                    lexeme.line_number, hint);
+            }
+        }
+        // TypeScript 3 has no `bigint`, so a `number` must approximate it.
+        if *ts_major == TsMajor::Ts3 {
+            if let Some(lexeme) = statement.iter().find(|l|
+                l.kind == LexemeKind::Number
+                && number_exceeds_js_safe_integer(l.snippet, 0)) {
+                result = result.push_loss_of_precision_warning(lexeme.column,
+                    lexeme.line_number,
+                    "Integer is larger than Number.MAX_SAFE_INTEGER");
+            }
+        }
+        let mut types = TypeContext {
+            any_types: vec![],
+            use_primitive_types: config.use_primitive_types,
+        };
+        let transpiled = match statement[0].snippet {
+            "enum" => transpile_enum(statement),
+            "fn" => transpile_fn(orig, statement, &mut types,
+                config.strip_trailing_commas),
+            "for" => transpile_for(statement, ts_major, &mut types),
+            "if" => transpile_if(orig, statement,
+                config.strip_trailing_commas),
+            "let" => transpile_let(statement, ts_major, &mut types)
+                .map_err(|i| (i, "Malformed let statement")),
+            "struct" => transpile_struct(statement, &mut types),
+            snippet if is_print_macro(snippet)
+                => transpile_print(orig, statement, ts_major),
+            "const" => transpile_const(statement, ts_major, &mut types)
+                .map_err(|i| (i, "Malformed const declaration")),
+            _ => Err((0, "Unsupported statement")),
+        };
+        for (column, line_number) in types.any_types {
+            result = result.push_any_type_warning(column, line_number,
+                "Unsupported generic type, using `any`");
+        }
+        if transpiled.is_ok() {
+            transpiled_lexemes.extend_from_slice(statement);
+        }
+        match transpiled {
+            // Enums and interfaces are types, so they belong in `type_lines`.
+            Ok(ts_type) if statement[0].snippet == "enum"
+                || statement[0].snippet == "struct"
+                => result = result.push_type_line(&ts_type),
+            // A function body can span several lines. Its first line keeps its
+            // indentation, and later lines are copied verbatim.
+            // A statement which begins on the line where a comment ends, like
+            // `/* a\n b */ const A: u8 = 1;`, is appended to that line. So
+            // later comments stay on their original lines.
+            Ok(lines) => for (i, line) in lines.split('\n').enumerate() {
+                let after_comment = comment_end_line == line_number
+                    && result.main_lines.len() == line_number;
+                result = if i > 0 { result.push_main_line(line) }
+                else if after_comment {
+                    if let Some(last) = result.main_lines.last_mut() {
+                        last.push(' ');
+                        last.push_str(line);
+                    }
+                    result
+                } else {
+                    let indent = indentation(&lexemes.lexemes, statement[0]);
+                    result.push_indented_main_line(indent, line)
+                };
+            },
+            // Report the line number and column of the unexpected Lexeme, or
+            // of the end of the input if the declaration is incomplete. But if
+            // it was cut short by an unterminated string or comment, that is
+            // reported instead.
+            Err((i, message)) => {
+                let (column, line_number) = match statement.get(i) {
+                    Some(lexeme) => (lexeme.column, lexeme.line_number),
+                    None if unterminated.is_some() => continue,
+                    None => (lexemes.end_column, lexemes.end_line_number),
+                };
+                result = result.push_unsupported_syntax_error(column,
+                    line_number, message);
+            }
+        }
+    }
+    // Keep any comments after the last statement.
+    for comment in comments { result = push_comment(result, comment) }
+    push_unterminated_error(add_polyfills(result, &transpiled_lexemes),
+        unterminated)
+}
+
+// Returns the whitespace which indents `lexeme`, if it is the first Lexeme on
+// its line. Otherwise, returns an empty string. `all` must contain every
+// Lexeme, including whitespace, sorted by position.
+fn indentation<'a>(all: &[Lexeme<'a>], lexeme: &Lexeme) -> &'a str {
+    let i = match all.binary_search_by_key(&lexeme.pos, |l| l.pos) {
+        Ok(i) if i > 0 => i,
+        _ => return "",
+    };
+    let previous = &all[i - 1];
+    if previous.kind != LexemeKind::Whitespace { return "" }
+    match previous.snippet.rfind('\n') {
+        Some(newline) => &previous.snippet[newline + 1..],
+        // Whitespace at the start of the input also indents the first line.
+        None if i == 1 => previous.snippet,
+        None => "",
+    }
+}
+
+// Checks whether a Lexeme begins a string or multiline comment which is never
+// closed. The lexemizer splits these into Punctuation, Identifier and
+// Xtraneous Lexemes, because it cannot detect them.
+fn starts_unterminated(orig: &str, lexeme: &Lexeme) -> bool {
+    match lexeme.kind {
+        LexemeKind::Comment | LexemeKind::DocComment | LexemeKind::String
+            => false,
+        _ => detect_unterminated_string(orig, lexeme.pos) != lexeme.pos
+            || detect_multiline_comment_status(orig, lexeme.pos)
+                == CommentScan::Unterminated,
+    }
+}
+
+// Reports an unterminated string at its opening double quote (or prefix), or
+// an unterminated multiline comment at its "/*".
+fn push_unterminated_error(
+    result: TranspileResult,
+    unterminated: Option<&Lexeme>,
+) -> TranspileResult {
+    match unterminated {
+        Some(lexeme) if lexeme.snippet == "/" => result
+            .push_unterminated_comment_error(lexeme.column,
+                lexeme.line_number, "Unterminated comment"),
+        Some(lexeme) => result.push_unterminated_string_error(lexeme.column,
+            lexeme.line_number, "Unterminated string"),
+        None => result,
+    }
+}
+
+// Adds a comment to `main_lines`, on the same line as it is in `orig`. If that
+// line already has some TypeScript, the comment is appended to it.
+fn push_comment(
+    mut result: TranspileResult,
+    comment: &Lexeme,
+) -> TranspileResult {
+    // Doc comments, like "/// Doc" and "//! Doc", become plain comments. But
+    // four or more slashes, like "//// Not a doc", is already a plain comment.
+    let snippet = comment.snippet;
+    let plain = if (snippet.starts_with("///") && ! snippet.starts_with("////"))
+        || snippet.starts_with("//!") { format!("//{}", &snippet[3..]) }
+        else { snippet.to_string() };
+    // A multiline comment is split into several `main_lines`, so that the
+    // line numbers are preserved.
+    for (i, line) in plain.split('\n').enumerate() {
+        let line_number = comment.line_number + i;
+        // If this line already has some TypeScript, append the comment.
+        if result.main_lines.len() >= line_number {
+            if let Some(last) = result.main_lines.last_mut() {
+                last.push(' ');
+                last.push_str(line);
+                continue;
+            }
+        }
+        // Otherwise, add blank lines, so the comment is on its original line.
+        while result.main_lines.len() + 1 < line_number {
+            result = result.push_main_line("");
+        }
+        result = result.push_main_line(line);
+    }
+    result
+}
+
+/// Rust methods which a call like `.len()` may need a TypeScript polyfill for.
+/// 
+/// Each entry is a method name, and the polyfill and type lines it needs, or
+/// `None` if JavaScript already has a method which does the same thing. The
+/// Gungho strategy does not track types, so `.len()` is polyfilled for strings
+/// and for arrays, and `.push(x)` is assumed to be called on a `Vec`, which
+/// becomes an `Array`.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// let (_, len) = METHOD_POLYFILLS.iter().find(|m| m.0 == "len").unwrap();
+/// assert_eq!(len.unwrap().1, "interface String { len(): number } \
+///     interface Array<T> { len(): number }");
+/// let (_, push) = METHOD_POLYFILLS.iter().find(|m| m.0 == "push").unwrap();
+/// assert!(push.is_none());
+/// ```
+pub static METHOD_POLYFILLS: [(&str, Option<(&str, &str)>); 4] = [
+    // Wraps JavaScript’s `length` accessor, for a `String` or a `Vec`.
+    ("len", Some(("String.prototype.len=Array.prototype.len=\
+function(){return this.length}",
+        "interface String { len(): number } \
+interface Array<T> { len(): number }"))),
+    // JavaScript’s `Array.prototype.push()`.
+    ("push", None),
+    // `x.to_string()` becomes `String(x)` where it is called.
+    ("to_string", None),
+    // JavaScript’s `String.prototype.trim()`.
+    ("trim", None),
+];
+
+// Returns the polyfill and type lines for each method in `METHOD_POLYFILLS`
+// which is called in `lexemes`, like `.len(`, in the order of the table. Each
+// method only appears once, however many times it is called.
+fn method_polyfills(lexemes: &[&Lexeme]) -> Vec<(&'static str, &'static str)> {
+    METHOD_POLYFILLS.iter()
+        .filter(|(method, _)| lexemes.windows(3).any(|w| w[0].snippet == "."
+            && w[1].snippet == *method && w[2].snippet == "("))
+        .filter_map(|(_, polyfill)| *polyfill)
+        .collect()
+}
+
+// Adds the polyfills and types which the Lexemes need.
+fn add_polyfills(
+    mut result: TranspileResult,
+    lexemes: &[&Lexeme],
+) -> TranspileResult {
+    // Each method call with no JavaScript equivalent, like `.len()`.
+    let methods = method_polyfills(lexemes);
+    // Each narrowing cast, like `as u8`, needs a helper function. A float
+    // literal before the `as` needs a different helper to an integer.
+    let narrowing_casts: Vec<(&str, &str)> = lexemes.windows(3)
+        .filter(|w| w[1].kind == LexemeKind::Keyword && w[1].snippet == "as")
+        .filter_map(|w| narrowing_cast_polyfill(w[2].snippet,
+            w[0].kind == LexemeKind::Number && is_float_literal(w[0].snippet)))
+        .collect();
+    // If there is no such method call or narrowing cast, no polyfills are
+    // needed.
+    if methods.is_empty() && narrowing_casts.is_empty() { return result }
+    // The polyfill function is declared after the main section, but is hoisted,
+    // so it can be called before the main section begins.
+    result.main_section_begins = ";r$t$();";
+    result.polyfill_section_begins = ";function r$t$(){";
+    result.polyfill_section_ends = "};";
+    // Duplicate helper functions are ignored.
+    let lines = methods.into_iter().chain(narrowing_casts);
+    for (polyfill_line, type_line) in lines {
+        result = result
+            .push_polyfill_line(polyfill_line)
+            .push_type_line(type_line);
+    }
+    result
+}
+
+// Returns the polyfill and type lines for a helper function, which wraps a
+// number like Rust’s `as` does when it casts to a narrower integer type.
+// JavaScript has no `u8`, so `300 as u8` would otherwise still be 300, rather
+// than 44. The helper is created inside the polyfill function, so it is
+// assigned to a variable which the type line declares at the top level. Unlike
+// `globalThis`, which ES5 lacks, that works for TypeScript 3 and 4 targets.
+// Returns `None` for types which TypeScript can cast to with its own `as`.
+// 
+// Rust casts a float differently: it saturates, so `300.0 as u8` is 255, and
+// NaN becomes 0. If `is_float` is set, the helper clamps the number to the
+// type’s range, and then truncates it. `|0` and `>>>0` also turn NaN into 0.
+fn narrowing_cast_polyfill(
+    rust_ty: &str,
+    is_float: bool,
+) -> Option<(&'static str, &'static str)> {
+    if is_float { return saturating_cast_polyfill(rust_ty) }
+    Some(match rust_ty {
+        "u8" => (";r$t$u8=function(n){return n&255}",
+            "var r$t$u8: (n: number) => number;"),
+        "u16" => (";r$t$u16=function(n){return n&65535}",
+            "var r$t$u16: (n: number) => number;"),
+        "u32" => (";r$t$u32=function(n){return n>>>0}",
+            "var r$t$u32: (n: number) => number;"),
+        "i8" => (";r$t$i8=function(n){return n<<24>>24}",
+            "var r$t$i8: (n: number) => number;"),
+        "i16" => (";r$t$i16=function(n){return n<<16>>16}",
+            "var r$t$i16: (n: number) => number;"),
+        "i32" => (";r$t$i32=function(n){return n|0}",
+            "var r$t$i32: (n: number) => number;"),
+        _ => return None,
+    })
+}
+
+// Returns the polyfill and type lines for a helper function, which casts a
+// float to a narrower integer type like Rust’s `as` does. See
+// `narrowing_cast_polyfill()`.
+fn saturating_cast_polyfill(
+    rust_ty: &str,
+) -> Option<(&'static str, &'static str)> {
+    Some(match rust_ty {
+        "u8" => (";r$t$f2u8=function(n){\
+return Math.min(Math.max(n,0),255)|0}",
+            "var r$t$f2u8: (n: number) => number;"),
+        "u16" => (";r$t$f2u16=function(n){\
+return Math.min(Math.max(n,0),65535)|0}",
+            "var r$t$f2u16: (n: number) => number;"),
+        "u32" => (";r$t$f2u32=function(n){\
+return Math.min(Math.max(n,0),4294967295)>>>0}",
+            "var r$t$f2u32: (n: number) => number;"),
+        "i8" => (";r$t$f2i8=function(n){\
+return Math.min(Math.max(n,-128),127)|0}",
+            "var r$t$f2i8: (n: number) => number;"),
+        "i16" => (";r$t$f2i16=function(n){\
+return Math.min(Math.max(n,-32768),32767)|0}",
+            "var r$t$f2i16: (n: number) => number;"),
+        "i32" => (";r$t$f2i32=function(n){\
+return Math.min(Math.max(n,-2147483648),2147483647)|0}",
+            "var r$t$f2i32: (n: number) => number;"),
+        _ => return None,
+    })
+}
+
+// Whether `snippet` is a float literal, like `1.5`, `1e3` or `2f64`.
+fn is_float_literal(snippet: &str) -> bool {
+    match detect_number_detailed(snippet, 0) {
+        Some(info) => info.has_fraction || info.has_exponent
+            || matches!(info.suffix, Some("f32") | Some("f64")),
+        None => false,
+    }
+}
+
+// Splits `lexemes` into statements. A statement ends with a ";" or "}" which is
+// not inside braces, or at the end of the input. A "}" followed by a ";", like
+// in `const A: u8 = { 1 };`, does not end the statement. Nor does a "}"
+// followed by an `else`, or a ";" inside square brackets, like in `[u8; 4]`.
+fn split_statements<'a, 'b>(lexemes: &'b [&'b Lexeme<'a>])
+    -> Vec<&'b [&'b Lexeme<'a>]> {
+    let mut statements = vec![];
+    let mut depth = 0;
+    let mut brackets = 0;
+    let mut start = 0;
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if lexeme.kind != LexemeKind::Punctuation { continue }
+        match lexeme.snippet {
+            "{" => depth += 1,
+            // A stray "}" is treated as the end of a statement.
+            "}" => if depth > 0 { depth -= 1 },
+            "[" => { brackets += 1; continue },
+            "]" => { if brackets > 0 { brackets -= 1 } continue },
+            ";" if brackets > 0 => continue,
+            ";" => {},
+            _ => continue,
+        }
+        let next = lexemes.get(i + 1).map(|l| l.snippet);
+        let continues = next == Some(";") || next == Some("else");
+        if depth == 0 && lexeme.snippet != "{"
+            && ! (lexeme.snippet == "}" && continues) {
+            statements.push(&lexemes[start..=i]);
+            start = i + 1;
+        }
+    }
+    // Any Lexemes after the last statement form an unterminated statement.
+    if start < lexemes.len() { statements.push(&lexemes[start..]) }
+    statements
+}
+
+// Returns the index of the Lexeme after any attributes, like `#[inline]`, and
+// any visibility, like `pub` or `pub(crate)`, from index `i`. TypeScript has
+// neither, so they are dropped. Returns `Err` with the index of the end of
+// `lexemes`, if a `pub(` is not closed.
+fn skip_attributes_and_visibility(
+    lexemes: &[&Lexeme],
+    mut i: usize,
+) -> Result<usize, usize> {
+    use LexemeKind::{Attribute,Keyword,Punctuation};
+    while expect(lexemes, i, Attribute, None).is_ok() { i += 1 }
+    if expect(lexemes, i, Keyword, Some("pub")).is_ok() {
+        i += 1;
+        if expect(lexemes, i, Punctuation, Some("(")).is_ok() {
+            while expect(lexemes, i, Punctuation, Some(")")).is_err() {
+                if i >= lexemes.len() { return Err(i) }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+    Ok(i)
+}
+
+// Transpiles a `const NAME: TYPE = VALUE;` statement, where VALUE is accepted
+// by `transpile_value()`. Returns the TypeScript line, or the index of the
+// first Lexeme which does not fit the pattern.
+fn transpile_const(
+    lexemes: &[&Lexeme],
+    ts_major: &TsMajor,
+    types: &mut TypeContext,
+) -> Result<String, usize> {
+    use LexemeKind::{Identifier,Keyword,Punctuation};
+    // The "const NAME: TYPE =" part.
+    expect(lexemes, 0, Keyword, Some("const"))?;
+    let name = expect(lexemes, 1, Identifier, None)?;
+    expect(lexemes, 2, Punctuation, Some(":"))?;
+    let (ts_ty, i) = expect_type(lexemes, 3, types)?;
+    expect(lexemes, i, Punctuation, Some("="))?;
+    let (value, is_bigint, mut i) = transpile_value(lexemes, i + 1, ts_major)?;
+    // A semicolon should end the statement, but it can be left out at the end
+    // of input.
+    if expect(lexemes, i, Punctuation, Some(";")).is_ok() { i += 1 }
+    if i < lexemes.len() { return Err(i) }
+    let ts_ty = if is_bigint { "bigint".into() } else { ts_ty };
+    Ok(format!("const {}: {} = {};", name, ts_ty, value))
+}
+
+// Transpiles a `let NAME: TYPE = VALUE;` statement, where `mut` and the type
+// are optional, and VALUE is accepted by `transpile_value()`. Returns the
+// TypeScript line, or the index of the first Lexeme which does not fit the
+// pattern.
+fn transpile_let(
+    lexemes: &[&Lexeme],
+    ts_major: &TsMajor,
+    types: &mut TypeContext,
+) -> Result<String, usize> {
+    use LexemeKind::{Identifier,Keyword,Punctuation};
+    // The "let mut NAME" part. TypeScript’s `let` is always mutable.
+    expect(lexemes, 0, Keyword, Some("let"))?;
+    let mut i = if expect(lexemes, 1, Keyword, Some("mut")).is_ok() { 2 }
+        else { 1 };
+    let name = expect(lexemes, i, Identifier, None)?;
+    i += 1;
+    // The optional ": TYPE" part.
+    let mut ts_ty = None;
+    if expect(lexemes, i, Punctuation, Some(":")).is_ok() {
+        let (ty, next) = expect_type(lexemes, i + 1, types)?;
+        ts_ty = Some(ty);
+        i = next;
+    }
+    expect(lexemes, i, Punctuation, Some("="))?;
+    let (value, is_bigint, mut i) = transpile_value(lexemes, i + 1, ts_major)?;
+    // A semicolon should end the statement, but it can be left out at the end
+    // of input.
+    if expect(lexemes, i, Punctuation, Some(";")).is_ok() { i += 1 }
+    if i < lexemes.len() { return Err(i) }
+    let ts_ty = match ts_ty {
+        Some(_) if is_bigint => ": bigint".into(),
+        Some(ts_ty) => format!(": {}", ts_ty),
+        None => String::new(),
+    };
+    Ok(format!("let {}{} = {};", name, ts_ty, value))
+}
+
+// Transpiles the value which starts at `i`. It can be a number or identifier,
+// optionally negated, or `true`, `false`, a char or a string. It can then be
+// followed by an `as TYPE` cast. Under TypeScript 4, an integer larger than
+// `Number.MAX_SAFE_INTEGER` which is not cast becomes a `bigint`. Returns the
+// TypeScript, whether it is a `bigint`, and the index after the value ends, or
+// the index of the first Lexeme which does not fit.
+fn transpile_value(
+    lexemes: &[&Lexeme],
+    i: usize,
+    ts_major: &TsMajor,
+) -> Result<(String, bool, usize), usize> {
+    use LexemeKind::{Character,Identifier,Keyword,Number,Punctuation,String};
+    // An optional minus sign, and then a number or identifier. Or a boolean,
+    // char or string, which cannot be negated.
+    let sign = if expect(lexemes, i, Punctuation, Some("-")).is_ok() { "-" }
+        else { "" };
+    let mut i = i + sign.len();
+    let mut is_bigint = false;
+    let mut is_float = false;
+    let value = if let Ok(number) = expect(lexemes, i, Number, None) {
+        is_float = is_float_literal(number);
+        // A cast, like `as u8`, needs a `number`, not a `bigint`.
+        is_bigint = *ts_major != TsMajor::Ts3
+            && number_exceeds_js_safe_integer(number, 0)
+            && expect(lexemes, i + 1, Keyword, Some("as")).is_err();
+        if is_bigint { format!("{}n", number_to_ts(number)) }
+            else { number_to_ts(number) }
+    } else if let Ok(identifier) = expect(lexemes, i, Identifier, None) {
+        identifier.into()
+    } else if sign.is_empty() {
+        match expect(lexemes, i, Keyword, None) {
+            Ok(boolean) if boolean == "true" || boolean == "false"
+                => boolean.into(),
+            _ => match expect(lexemes, i, String, None) {
+                Ok(string) => rust_string_to_ts(string, ts_major),
+                Err(_) => char_to_ts(expect(lexemes, i, Character, None)?)
+                    .ok_or(i)?,
+            },
+        }
     } else {
-        TranspileResult::new()
-            .push_main_line("const ROUGHLY_PI: Number = 3.14;")
+        return Err(i)
+    };
+    i += 1;
+    let mut value = format!("{}{}", sign, value);
+    // An optional cast. A narrowing cast, like `as u8`, wraps the value using a
+    // polyfill, which saturates a float. Otherwise, TypeScript’s `as` is used.
+    if expect(lexemes, i, Keyword, Some("as")).is_ok() {
+        let cast_ty = expect(lexemes, i + 1, Identifier, None)?;
+        value = if narrowing_cast_polyfill(cast_ty, is_float).is_some() {
+            let float = if is_float { "f2" } else { "" };
+            format!("r$t${}{}({})", float, cast_ty, value)
+        } else {
+            format!("({} as {})", value, map_primitive_type(cast_ty, true))
+        };
+        i += 2;
     }
+    Ok((value, is_bigint, i))
+}
+
+// Transpiles a char literal, like `'A'`, to a single-character TypeScript
+// string, like `"A"`. Escapes which TypeScript shares, like `\n` and `\x41`,
+// are kept. A byte char literal, like `b'A'`, becomes a number, like `65`.
+// Returns `None` if `snippet` is not a char literal.
+fn char_to_ts(snippet: &str) -> Option<String> {
+    let (is_byte, inner) = match snippet.strip_prefix('b') {
+        Some(rest) => (true, rest),
+        None => (false, snippet),
+    };
+    let inner = inner.strip_prefix('\'')?.strip_suffix('\'')?;
+    // The char, with any escape decoded, and its TypeScript string escape.
+    let (c, escaped) = match inner.strip_prefix('\\') {
+        None => {
+            let c = inner.chars().next()?;
+            (c, if c == '"' { "\\\"".into() } else { c.to_string() })
+        },
+        Some(escape) => match escape.as_bytes().first()? {
+            b'n' => ('\n', "\\n".into()),
+            b'r' => ('\r', "\\r".into()),
+            b't' => ('\t', "\\t".into()),
+            b'0' => ('\0', "\\0".into()),
+            b'\\' => ('\\', "\\\\".into()),
+            b'"' => ('"', "\\\"".into()),
+            b'\'' => ('\'', "'".into()),
+            b'x' => {
+                let code = u8::from_str_radix(escape.get(1..3)?, 16).ok()?;
+                (code as char, format!("\\x{}", &escape[1..3]))
+            },
+            b'u' => {
+                // Rust allows underscores, like `\u{1_F600}`, but TypeScript
+                // does not.
+                let hex: String = escape.get(2..escape.len() - 1)?.chars()
+                    .filter(|c| *c != '_').collect();
+                let code = u32::from_str_radix(&hex, 16).ok()?;
+                (std::char::from_u32(code)?, format!("\\u{{{}}}", hex))
+            },
+            _ => return None,
+        },
+    };
+    Some(if is_byte { (c as u32).to_string() }
+        else { format!("\"{}\"", escaped) })
+}
+
+// Transpiles a `for NAME in START..END { BODY }` loop, where START and END are
+// each an integer or an identifier. An inclusive range, `START..=END`, is also
+// supported. Currently, the body may only contain `const` declarations.
+// Returns the TypeScript line, or the index of the first Lexeme which does not
+// fit the pattern, and a message.
+fn transpile_for(
+    lexemes: &[&Lexeme],
+    ts_major: &TsMajor,
+    types: &mut TypeContext,
+) -> Result<String, (usize, &'static str)> {
+    use LexemeKind::{Identifier,Keyword,Punctuation};
+    let malformed = |i| (i, "Malformed for loop");
+    // The "for NAME in" part.
+    expect(lexemes, 0, Keyword, Some("for")).map_err(malformed)?;
+    let name = expect(lexemes, 1, Identifier, None).map_err(malformed)?;
+    expect(lexemes, 2, Keyword, Some("in")).map_err(malformed)?;
+    // The range. An unbounded range, like "..n" or "0..", has no TypeScript
+    // equivalent.
+    let unsupported = |i| (i, "Unsupported range expression");
+    let start = expect_range_bound(lexemes, 3).map_err(unsupported)?;
+    let comparison = match expect(lexemes, 4, Punctuation, None) {
+        Ok("..") => "<",
+        Ok("..=") => "<=",
+        _ => return Err(unsupported(4)),
+    };
+    let end = expect_range_bound(lexemes, 5).map_err(unsupported)?;
+    // The body, which must be the last part of the statement.
+    expect(lexemes, 6, Punctuation, Some("{")).map_err(malformed)?;
+    let last = lexemes.len() - 1;
+    if last < 7 || expect(lexemes, last, Punctuation, Some("}")).is_err() {
+        return Err(malformed(lexemes.len()));
+    }
+    let mut body = String::new();
+    let mut offset = 7;
+    for statement in split_statements(&lexemes[7..last]) {
+        let line = transpile_const(statement, ts_major, types)
+            .map_err(|i| (offset + i, "Malformed const declaration"))?;
+        body.push(' ');
+        body.push_str(&line);
+        offset += statement.len();
+    }
+    Ok(format!("for (let {} = {}; {} {} {}; {}++) {{{} }}",
+        name, start, name, comparison, end, name, body))
+}
+
+// Transpiles a `fn NAME(ARG: TYPE, ...) -> TYPE { BODY }` declaration. Each
+// type must be accepted by `expect_type()`. The body is copied from `orig`
+// verbatim, except for any stripped trailing commas, and an inserted `return`
+// before the final expression. Returns the TypeScript, which may contain
+// newlines, or the index of the first Lexeme which does not fit the pattern,
+// and a message.
+fn transpile_fn(
+    orig: &str,
+    lexemes: &[&Lexeme],
+    types: &mut TypeContext,
+    strip_trailing_commas: bool,
+) -> Result<String, (usize, &'static str)> {
+    use LexemeKind::{Identifier,Keyword,Punctuation};
+    let malformed = |i| (i, "Malformed function declaration");
+    // The "fn NAME" part. Generics, like "fn f<T>", are not supported yet.
+    expect(lexemes, 0, Keyword, Some("fn")).map_err(malformed)?;
+    let name = expect(lexemes, 1, Identifier, None).map_err(malformed)?;
+    if expect(lexemes, 2, Punctuation, Some("<")).is_ok() {
+        return Err((2, "Generic functions are not supported yet"));
+    }
+    // The "(ARG: TYPE, ...)" part. A trailing comma is allowed.
+    expect(lexemes, 2, Punctuation, Some("(")).map_err(malformed)?;
+    let mut args = vec![];
+    let mut i = 3;
+    while expect(lexemes, i, Punctuation, Some(")")).is_err() {
+        let arg = expect(lexemes, i, Identifier, None).map_err(malformed)?;
+        expect(lexemes, i + 1, Punctuation, Some(":")).map_err(malformed)?;
+        let (ts_ty, next) = expect_type(lexemes, i + 2, types)
+            .map_err(malformed)?;
+        args.push(format!("{}: {}", arg, ts_ty));
+        i = next;
+        if expect(lexemes, i, Punctuation, Some(",")).is_ok() { i += 1 }
+        else if expect(lexemes, i, Punctuation, Some(")")).is_err() {
+            return Err(malformed(i));
+        }
+    }
+    i += 1;
+    // The optional "-> TYPE" part.
+    let ts_return = if expect(lexemes, i, Punctuation, Some("->")).is_ok() {
+        let (ts_ty, next) = expect_type(lexemes, i + 1, types)
+            .map_err(malformed)?;
+        i = next;
+        ts_ty
+    } else { "void".into() };
+    // The body, which must be the last part of the statement.
+    expect(lexemes, i, Punctuation, Some("{")).map_err(malformed)?;
+    let (open, close) = (lexemes[i], lexemes[lexemes.len() - 1]);
+    if lexemes.len() == i + 1 || close.snippet != "}" {
+        return Err(malformed(lexemes.len()));
+    }
+    let copy = |from, to|
+        copy_verbatim(orig, lexemes, from, to, strip_trailing_commas);
+    let end = close.pos + close.snippet.len();
+    // Rust returns the final expression implicitly, so TypeScript needs an
+    // explicit `return`. Without a return type, the function returns `()`,
+    // so nothing is worth returning.
+    let last = lexemes.len() - 1;
+    let body = match trailing_expression(lexemes, i, last) {
+        Some(j) if ts_return != "void" => {
+            let expr_end =
+                lexemes[last - 1].pos + lexemes[last - 1].snippet.len();
+            format!("{}return {};{}", copy(open.pos, lexemes[j].pos),
+                copy(lexemes[j].pos, expr_end), copy(expr_end, end))
+        },
+        _ => copy(open.pos, end),
+    };
+    Ok(format!("function {}({}): {} {}",
+        name, args.join(", "), ts_return, body))
+}
+
+// Finds the final expression in the block between the "{" at `open` and the
+// "}" at `close`, like the `a + 1` in `{ let a = 2; a + 1 }`. It starts after
+// the last ";" which is not nested in brackets, or after the last such "}"
+// which the expression cannot continue from, like the one in `S { a: 1 }.a`.
+// Any outer attributes, like `#[allow(x)]`, are skipped. Returns the index of
+// its first Lexeme, or `None` if the block ends with a statement, is empty, or
+// ends with an explicit `return`.
+fn trailing_expression(
+    lexemes: &[&Lexeme],
+    open: usize,
+    close: usize,
+) -> Option<usize> {
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (j, lexeme) in lexemes.iter().enumerate().take(close).skip(open + 1) {
+        if lexeme.kind != LexemeKind::Punctuation { continue }
+        match lexeme.snippet {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" => depth -= 1,
+            "}" => {
+                depth -= 1;
+                if depth == 0 && ! continues_expression(lexemes[j + 1]) {
+                    start = j + 1
+                }
+            },
+            ";" if depth == 0 => start = j + 1,
+            _ => {},
+        }
+    }
+    while start < close && lexemes[start].kind == LexemeKind::Attribute
+        && lexemes[start].snippet.starts_with("#[") { start += 1 }
+    if start == close || lexemes[start].snippet == "return" { None }
+    else { Some(start) }
+}
+
+// Whether `lexeme` can continue an expression which ends with a "}", like the
+// `.` in `S { a: 1 }.a`, the `?` in `match x { _ => y }?`, or a binary
+// operator. If not, the "}" ends a statement.
+fn continues_expression(lexeme: &Lexeme) -> bool {
+    match lexeme.kind {
+        LexemeKind::Keyword => lexeme.snippet == "as",
+        LexemeKind::Punctuation => matches!(lexeme.snippet,
+            "." | "?" | "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<"
+            | ">>" | "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||"
+            | ".." | "..="),
+        _ => false,
+    }
+}
+
+// Transpiles an `if COND { BODY }` statement, which can be followed by any
+// number of `else if COND { BODY }` parts, and an `else { BODY }` part. Rust
+// and TypeScript `if` statements look alike, except that TypeScript needs
+// brackets around each condition. So the statement is copied from `orig`
+// verbatim, with brackets added, which preserves its line breaks. Trailing
+// commas can be stripped. Returns the TypeScript, which may contain newlines,
+// or the index of the first Lexeme which does not fit the pattern, and a
+// message.
+fn transpile_if(
+    orig: &str,
+    lexemes: &[&Lexeme],
+    strip_trailing_commas: bool,
+) -> Result<String, (usize, &'static str)> {
+    use LexemeKind::{Keyword,Punctuation};
+    let malformed = |i| (i, "Malformed if statement");
+    // The position in `orig` where each condition starts and ends.
+    let mut conditions = vec![];
+    let mut i = 0;
+    loop {
+        // The "if COND" part. Pattern matching is not supported yet.
+        expect(lexemes, i, Keyword, Some("if")).map_err(malformed)?;
+        if expect(lexemes, i + 1, Keyword, Some("let")).is_ok() {
+            return Err((i + 1, "`if let` is not supported yet"));
+        }
+        // The condition ends at the first "{" which is not inside brackets.
+        let start = i + 1;
+        let mut depth = 0;
+        i = start;
+        while depth > 0
+            || expect(lexemes, i, Punctuation, Some("{")).is_err() {
+            match expect(lexemes, i, Punctuation, None) {
+                Ok("(") | Ok("[") => depth += 1,
+                Ok(")") | Ok("]") if depth > 0 => depth -= 1,
+                _ if i >= lexemes.len() => return Err(malformed(i)),
+                _ => {},
+            }
+            i += 1;
+        }
+        if i == start { return Err(malformed(i)) }
+        let last = lexemes[i - 1];
+        conditions.push((lexemes[start].pos, last.pos + last.snippet.len()));
+        // The "{ BODY }" part, which ends at the matching "}".
+        i = expect_block_end(lexemes, i).map_err(malformed)?;
+        // An optional "else if ...", or "else { BODY }" which ends the chain.
+        if expect(lexemes, i, Keyword, Some("else")).is_err() { break }
+        if expect(lexemes, i + 1, Keyword, Some("if")).is_ok() {
+            i += 1;
+            continue;
+        }
+        i = expect_block_end(lexemes, i + 1).map_err(malformed)?;
+        break;
+    }
+    // A semicolon can follow the statement.
+    if expect(lexemes, i, Punctuation, Some(";")).is_ok() { i += 1 }
+    if i < lexemes.len() { return Err(malformed(i)) }
+    // Copy the statement, adding brackets around each condition.
+    let copy = |from, to|
+        copy_verbatim(orig, lexemes, from, to, strip_trailing_commas);
+    let last = lexemes[i - 1];
+    let mut ts = String::new();
+    let mut from = lexemes[0].pos;
+    for (start, end) in conditions {
+        ts.push_str(&copy(from, start));
+        ts.push('(');
+        ts.push_str(&copy(start, end));
+        ts.push(')');
+        from = end;
+    }
+    ts.push_str(&copy(from, last.pos + last.snippet.len()));
+    Ok(ts)
+}
+
+// Copies `orig` from position `from` up to position `to`. Each `x.to_string()`
+// in that range becomes `String(x)`. If `strip` is true, each trailing comma in
+// `lexemes` which is in that range is left out.
+fn copy_verbatim(
+    orig: &str,
+    lexemes: &[&Lexeme],
+    from: usize,
+    to: usize,
+    strip: bool,
+) -> String {
+    // Each edit replaces `orig[start..end]` with some text. An edit where
+    // `start == end` just inserts the text.
+    let mut edits: Vec<(usize, usize, &str)> = vec![];
+    for i in 1..lexemes.len() {
+        let comma = lexemes[i - 1];
+        if strip && has_trailing_comma_before(lexemes, i) {
+            edits.push((comma.pos, comma.pos + comma.snippet.len(), ""));
+        }
+    }
+    for (dot, w) in lexemes.windows(4).enumerate() {
+        if w[0].snippet != "." || w[1].snippet != "to_string"
+            || w[2].snippet != "(" || w[3].snippet != ")" { continue }
+        if let Some(start) = method_receiver_start(lexemes, dot) {
+            edits.push((lexemes[start].pos, lexemes[start].pos, "String("));
+            edits.push((w[0].pos, w[3].pos + 1, ")"));
+        }
+    }
+    // At the same position, an insertion comes before a replacement.
+    edits.sort_by_key(|&(start, end, _)| (start, end));
+    let mut ts = String::new();
+    let mut pos = from;
+    for (start, end, text) in edits {
+        if start < from || start >= to || end > to { continue }
+        ts.push_str(&orig[pos..start]);
+        ts.push_str(text);
+        pos = end;
+    }
+    ts.push_str(&orig[pos..to]);
+    ts
+}
+
+// Returns the index of the first Lexeme of the receiver of the method call
+// whose "." is at index `dot`, like the `a.b(c)` in `a.b(c).to_string()`. The
+// receiver is a literal, identifier or bracketed expression, followed by any
+// number of calls, indexes, fields and paths. Returns `None` if there is no
+// receiver.
+fn method_receiver_start(lexemes: &[&Lexeme], dot: usize) -> Option<usize> {
+    use LexemeKind::{Character,Identifier,Keyword,Number,Punctuation,String};
+    let is = |i: usize, snippets: &[&str]| lexemes[i].kind == Punctuation
+        && snippets.contains(&lexemes[i].snippet);
+    let mut i = dot;
+    loop {
+        // Step back over any calls, indexes and brackets, like the `(c)` in
+        // `b(c)`.
+        while i > 0 && is(i - 1, &[")", "]"]) {
+            i = matching_open(lexemes, i - 1)?;
+        }
+        // Then over a literal or identifier, like the `b` in `b(c)`.
+        let atom = i > 0 && match lexemes[i - 1].kind {
+            Character | Identifier | Number | String => true,
+            Keyword => matches!(lexemes[i - 1].snippet, "false" | "self"
+                | "Self" | "true"),
+            _ => false,
+        };
+        if atom { i -= 1 } else if i == dot { return None }
+        // A field, method or path, like the `.` in `a.b`, joins another part.
+        if atom && i > 0 && is(i - 1, &[".", "::"]) { i -= 1 }
+        else { return Some(i) }
+    }
+}
+
+// Returns the index of the "(" or "[" which matches the ")" or "]" at `close`,
+// or `None` if there is no match.
+fn matching_open(lexemes: &[&Lexeme], close: usize) -> Option<usize> {
+    let mut depth = 0;
+    for i in (0..=close).rev() {
+        if lexemes[i].kind != LexemeKind::Punctuation { continue }
+        match lexemes[i].snippet {
+            ")" | "]" | "}" => depth += 1,
+            "(" | "[" | "{" => depth -= 1,
+            _ => {},
+        }
+        if depth == 0 { return Some(i) }
+    }
+    None
+}
+
+/// Whether the Lexeme before a closing bracket is a trailing comma.
+/// 
+/// Rust allows a comma after the last item in a list, like in `f(a, b,)`,
+/// `[1, 2,]` or `S { x: 1, }`.
+/// 
+/// ### Arguments
+/// * `lexemes` Lexemes with any whitespace and comments removed
+/// * `close_index` The index of a `)`, `]` or `}` in `lexemes`
+/// 
+/// ### Returns
+/// `true` if the Lexeme at `close_index` is a `)`, `]` or `}`, and the Lexeme
+/// before it is a `,`. Otherwise, `false`.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexeme::*;
+/// # use opinionated_rust_to_typescript::rs2018_ts4::lexemize::lexemize::*;
+/// let lexemes = lexemize("f(a, b, )");
+/// let significant: Vec<&Lexeme> = lexemes.lexemes.iter()
+///     .filter(|l| l.kind != LexemeKind::Whitespace)
+///     .collect();
+/// assert!(has_trailing_comma_before(&significant, 6));
+/// assert!(! has_trailing_comma_before(&significant, 4));
+/// ```
+pub fn has_trailing_comma_before(
+    lexemes: &[&Lexeme],
+    close_index: usize,
+) -> bool {
+    if close_index == 0 { return false }
+    let (comma, close) = match lexemes.get(close_index) {
+        Some(close) => (lexemes[close_index - 1], close),
+        None => return false,
+    };
+    let closes = close.snippet == ")" || close.snippet == "]"
+        || close.snippet == "}";
+    close.kind == LexemeKind::Punctuation && closes
+        && comma.kind == LexemeKind::Punctuation && comma.snippet == ","
+}
+
+// Returns the index after the "}" which closes the "{" at `i`. Otherwise,
+// returns the index of the Lexeme which does not fit.
+fn expect_block_end(
+    lexemes: &[&Lexeme],
+    i: usize,
+) -> Result<usize, usize> {
+    expect(lexemes, i, LexemeKind::Punctuation, Some("{"))?;
+    let mut depth = 0;
+    for (j, lexeme) in lexemes.iter().enumerate().skip(i) {
+        if lexeme.kind != LexemeKind::Punctuation { continue }
+        match lexeme.snippet {
+            "{" => depth += 1,
+            "}" => depth -= 1,
+            _ => continue,
+        }
+        if depth == 0 { return Ok(j + 1) }
+    }
+    Err(lexemes.len())
+}
+
+// Transpiles an `enum NAME { VARIANT, ... }` definition to a TypeScript enum.
+// Only fieldless variants are supported, optionally with an integer
+// discriminant, like `Red = 1`. Attributes on the variants are dropped.
+// Returns the enum, or the index of the first Lexeme which does not fit the
+// pattern, and a message.
+fn transpile_enum(
+    lexemes: &[&Lexeme],
+) -> Result<String, (usize, &'static str)> {
+    use LexemeKind::{Attribute,Identifier,Keyword,Number,Punctuation};
+    let malformed = |i| (i, "Malformed enum");
+    // The "enum NAME {" part.
+    expect(lexemes, 0, Keyword, Some("enum")).map_err(malformed)?;
+    let name = expect(lexemes, 1, Identifier, None).map_err(malformed)?;
+    match expect(lexemes, 2, Punctuation, None) {
+        Ok("{") => {},
+        Ok("<") => return Err((2, "Generic enums are not supported yet")),
+        _ => return Err(malformed(2)),
+    }
+    // The variants, separated by commas, with an optional trailing comma.
+    let mut variants = vec![];
+    let mut i = 3;
+    while expect(lexemes, i, Punctuation, Some("}")).is_err() {
+        while expect(lexemes, i, Attribute, None).is_ok() { i += 1 }
+        let variant = expect(lexemes, i, Identifier, None).map_err(malformed)?;
+        i += 1;
+        match expect(lexemes, i, Punctuation, None) {
+            // A discriminant, like `Red = 1`, which may be negative.
+            Ok("=") => {
+                let minus = expect(lexemes, i + 1, Punctuation, Some("-"))
+                    .is_ok();
+                let j = if minus { i + 2 } else { i + 1 };
+                let value = expect(lexemes, j, Number, None)
+                    .map_err(malformed)?;
+                variants.push(format!("{} = {}{}", variant,
+                    if minus { "-" } else { "" }, number_to_ts(value)));
+                i = j + 1;
+            },
+            Ok("(") | Ok("{") => return Err((i,
+                "Enum variants with data are not supported yet")),
+            _ => variants.push(variant.to_string()),
+        }
+        // A comma is needed before the next variant.
+        if expect(lexemes, i, Punctuation, Some(",")).is_ok() { i += 1 }
+        else if expect(lexemes, i, Punctuation, Some("}")).is_err() {
+            return Err(malformed(i));
+        }
+    }
+    if i + 1 < lexemes.len() { return Err(malformed(i + 1)) }
+    if variants.is_empty() { return Ok(format!("enum {} {{ }}", name)) }
+    Ok(format!("enum {} {{ {} }}", name, variants.join(", ")))
+}
+
+// Transpiles a `struct NAME { FIELD: TYPE, ... }` definition to a TypeScript
+// interface. Each type must be accepted by `expect_type()`. A field can be
+// `pub`, or `pub(crate)`, which TypeScript interfaces have no equivalent of,
+// so it is dropped. A unit struct, like `struct Unit;`, becomes an empty
+// interface. Returns the interface, or the index of the first Lexeme which
+// does not fit the pattern, and a message.
+fn transpile_struct(
+    lexemes: &[&Lexeme],
+    types: &mut TypeContext,
+) -> Result<String, (usize, &'static str)> {
+    use LexemeKind::{Identifier,Keyword,Punctuation};
+    let malformed = |i| (i, "Malformed struct");
+    // The "struct NAME" part.
+    expect(lexemes, 0, Keyword, Some("struct")).map_err(malformed)?;
+    let name = expect(lexemes, 1, Identifier, None).map_err(malformed)?;
+    match expect(lexemes, 2, Punctuation, None) {
+        Ok("{") => {},
+        Ok(";") if lexemes.len() == 3
+            => return Ok(format!("interface {} {{ }}", name)),
+        Ok("(") => return Err((2, "Tuple structs are not supported yet")),
+        Ok("<") => return Err((2, "Generic structs are not supported yet")),
+        _ => return Err(malformed(2)),
+    }
+    // The fields, separated by commas, with an optional trailing comma.
+    let mut fields = String::new();
+    let mut i = 3;
+    while expect(lexemes, i, Punctuation, Some("}")).is_err() {
+        // Attributes, like `#[allow(dead_code)]`, and `pub` are dropped.
+        i = skip_attributes_and_visibility(lexemes, i).map_err(malformed)?;
+        let field = expect(lexemes, i, Identifier, None).map_err(malformed)?;
+        expect(lexemes, i + 1, Punctuation, Some(":")).map_err(malformed)?;
+        let (ts_ty, next) = expect_type(lexemes, i + 2, types)
+            .map_err(malformed)?;
+        fields.push_str(&format!(" {}: {};", field, ts_ty));
+        i = next;
+        // A comma is needed before the next field.
+        if expect(lexemes, i, Punctuation, Some(",")).is_ok() { i += 1 }
+        else if expect(lexemes, i, Punctuation, Some("}")).is_err() {
+            return Err(malformed(i));
+        }
+    }
+    if i + 1 < lexemes.len() { return Err(malformed(i + 1)) }
+    Ok(format!("interface {} {{{} }}", name, fields))
+}
+
+// Checks whether an identifier is the name of a macro which `transpile_print()`
+// can transpile.
+fn is_print_macro(snippet: &str) -> bool {
+    matches!(snippet, "print" | "println" | "eprint" | "eprintln")
+}
+
+// Transpiles a `println!(FORMAT, ARG, ...);` macro call, or `print!`,
+// `eprint!` or `eprintln!`. FORMAT must be a string literal which only has
+// `{}` placeholders. Each ARG ends at a comma which is not inside brackets, and
+// is transpiled by `transpile_value()`. An ARG which continues after the value
+// with an operator or bracket, like `x + 1` or `f(a, b)`, is copied verbatim.
+// Returns the TypeScript line, or the index of the first Lexeme which does not
+// fit the pattern, and a message.
+fn transpile_print(
+    orig: &str,
+    lexemes: &[&Lexeme],
+    ts_major: &TsMajor,
+) -> Result<String, (usize, &'static str)> {
+    use LexemeKind::{Identifier,Punctuation};
+    let malformed = |i| (i, "Malformed print macro");
+    // The "println!(" part. Standard error is `console.error()`.
+    let method = match expect(lexemes, 0, Identifier, None) {
+        Ok("eprint") | Ok("eprintln") => "error",
+        _ => "log",
+    };
+    expect(lexemes, 1, Punctuation, Some("!")).map_err(malformed)?;
+    expect(lexemes, 2, Punctuation, Some("(")).map_err(malformed)?;
+    // The format string, which is optional for `println!()`, and then the
+    // arguments, each after a comma. A trailing comma is allowed.
+    let format = expect(lexemes, 3, LexemeKind::String, None).ok();
+    let mut i = if format.is_some() { 4 } else { 3 };
+    let mut args = vec![];
+    while format.is_some()
+        && expect(lexemes, i, Punctuation, Some(",")).is_ok()
+        && expect(lexemes, i + 1, Punctuation, Some(")")).is_err() {
+        let end = argument_end(lexemes, i + 1);
+        if end == i + 1 { return Err(malformed(end)) }
+        let arg = match transpile_value(lexemes, i + 1, ts_major) {
+            Ok((arg, _, next)) if next == end => arg,
+            Ok((_, _, next)) | Err(next) if next < end
+                && lexemes[next].kind == Punctuation => {
+                let last = lexemes[end - 1];
+                copy_verbatim(orig, lexemes, lexemes[i + 1].pos,
+                    last.pos + last.snippet.len(), false)
+            },
+            Ok((_, _, next)) | Err(next) => return Err(malformed(next)),
+        };
+        args.push(arg);
+        i = end;
+    }
+    if format.is_some() && expect(lexemes, i, Punctuation, Some(",")).is_ok() {
+        i += 1;
+    }
+    expect(lexemes, i, Punctuation, Some(")")).map_err(malformed)?;
+    i += 1;
+    // A semicolon should end the statement, but it can be left out at the end
+    // of input.
+    if expect(lexemes, i, Punctuation, Some(";")).is_ok() { i += 1 }
+    if i < lexemes.len() { return Err(malformed(i)) }
+    let format = match format {
+        Some(format) => format,
+        None => return Ok(format!("console.{}();", method)),
+    };
+    // Each placeholder needs an argument.
+    let pieces = split_format_string(format)
+        .ok_or((3, "Unsupported format placeholder"))?;
+    if pieces.len() != args.len() + 1 {
+        return Err((3, "Wrong number of format arguments"));
+    }
+    // Rebuild each piece as a string literal, like `r#"PIECE"#`, so that
+    // `rust_string_to_ts()` can transpile its escapes.
+    let open = &format[..format.find('"').unwrap_or(0) + 1];
+    let close = &format[format.rfind('"').unwrap_or(0)..];
+    let pieces: Vec<String> = pieces.iter()
+        .map(|piece| format!("{}{}{}", open, piece, close))
+        .map(|piece| rust_string_to_ts(&piece, ts_major))
+        .collect();
+    if args.is_empty() {
+        return Ok(format!("console.{}({});", method, pieces[0]));
+    }
+    // Otherwise, substitute the arguments into a template literal, where "`"
+    // and "${" must be escaped.
+    let mut template = String::from("`");
+    for (n, piece) in pieces.iter().enumerate() {
+        template.push_str(&piece[1..piece.len() - 1]
+            .replace('`', "\\`").replace("${", "\\${"));
+        if let Some(arg) = args.get(n) {
+            template.push_str(&format!("${{{}}}", arg));
+        }
+    }
+    template.push('`');
+    Ok(format!("console.{}({});", method, template))
+}
+
+// Returns the index of the "," or ")" which ends the macro argument that starts
+// at `i`, ignoring any inside brackets, like in `f(a, b)`. Returns the length
+// of `lexemes` if there is no such Lexeme.
+fn argument_end(lexemes: &[&Lexeme], i: usize) -> usize {
+    let mut depth = 0;
+    for (j, lexeme) in lexemes.iter().enumerate().skip(i) {
+        if lexeme.kind != LexemeKind::Punctuation { continue }
+        match lexeme.snippet {
+            "," | ")" if depth == 0 => return j,
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            _ => {},
+        }
+    }
+    lexemes.len()
+}
+
+// Splits a format string literal, like `"a {} b"`, into the Rust source code of
+// the pieces between its `{}` placeholders, like `["a ", " b"]`. The escaped
+// braces `{{` and `}}` become `{` and `}`. Returns `None` if the format string
+// has any other kind of placeholder, like `{name}` or `{:?}`, or a lone brace.
+fn split_format_string(snippet: &str) -> Option<Vec<String>> {
+    let is_raw = snippet.trim_start_matches('b').starts_with('r');
+    let inner = snippet.get(snippet.find('"')? + 1..snippet.rfind('"')?)?;
+    let mut pieces = vec![];
+    let mut piece = String::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // An escape is kept as it is. A unicode escape, like `\u{E9}`,
+            // contains braces which are not placeholders.
+            '\\' if ! is_raw => {
+                piece.push(c);
+                match chars.next() {
+                    Some('u') => {
+                        piece.push('u');
+                        for c in chars.by_ref() {
+                            piece.push(c);
+                            if c == '}' { break }
+                        }
+                    },
+                    Some(escaped) => piece.push(escaped),
+                    None => {},
+                }
+            },
+            '{' if chars.next_if_eq(&'{').is_some() => piece.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => piece.push('}'),
+            '{' if chars.next_if_eq(&'}').is_some()
+                => pieces.push(std::mem::take(&mut piece)),
+            '{' | '}' => return None,
+            c => piece.push(c),
+        }
+    }
+    pieces.push(piece);
+    Some(pieces)
+}
+
+// Settings and findings, which are passed to `expect_type()` and the functions
+// which call it.
+struct TypeContext {
+    // The column and line number of each type which becomes `any`.
+    any_types: Vec<(usize, usize)>,
+    // Whether `f32` becomes `number`, rather than `Number`, and so on.
+    use_primitive_types: bool,
+}
+
+// Returns the TypeScript type of the Rust type which starts at `i`, and the
+// index after it ends. A reference, like `&str` or `&mut [u8]`, has the same
+// TypeScript type as the type it refers to. `Vec<T>`, arrays and slices become
+// `T[]`, and `Option<T>` becomes `T | null`. Any other generic type, like
+// `HashMap<K, V>`, becomes `any`, and its column and line number are added to
+// `types.any_types`. Otherwise, returns the index of the Lexeme which does not
+// fit.
+fn expect_type(
+    lexemes: &[&Lexeme],
+    i: usize,
+    types: &mut TypeContext,
+) -> Result<(String, usize), usize> {
+    // The lexemizer treats the `>>` which ends `Vec<Vec<u8>>` as one Lexeme,
+    // so `split_shr` records when only its first `>` has been used.
+    let mut split_shr = false;
+    let (ts_ty, i) = parse_type(lexemes, i, &mut split_shr, types)?;
+    if split_shr { return Err(i - 1) }
+    Ok((ts_ty, i))
+}
+
+// Does the work of `expect_type()`, calling itself for each nested type.
+fn parse_type(
+    lexemes: &[&Lexeme],
+    i: usize,
+    split_shr: &mut bool,
+    types: &mut TypeContext,
+) -> Result<(String, usize), usize> {
+    use LexemeKind::{Identifier,Keyword,Number,Punctuation};
+    // The unit type, "()".
+    if expect(lexemes, i, Punctuation, Some("(")).is_ok() {
+        expect(lexemes, i + 1, Punctuation, Some(")"))?;
+        return Ok(("void".into(), i + 2));
+    }
+    // A reference. A lifetime, like the `'static` in `&'static str`, and `mut`
+    // are not needed.
+    if expect(lexemes, i, Punctuation, Some("&")).is_ok() {
+        let mut i = i + 1;
+        if expect(lexemes, i, LexemeKind::Lifetime, None).is_ok() { i += 1 }
+        if expect(lexemes, i, Keyword, Some("mut")).is_ok() { i += 1 }
+        return parse_type(lexemes, i, split_shr, types);
+    }
+    // An array, like `[u8; 4]`, or a slice, like `[u8]`. TypeScript arrays have
+    // no fixed length, so the length can be any number or identifier.
+    if expect(lexemes, i, Punctuation, Some("[")).is_ok() {
+        let (item, mut i) = parse_type(lexemes, i + 1, split_shr, types)?;
+        if *split_shr { return Err(i - 1) }
+        if expect(lexemes, i, Punctuation, Some(";")).is_ok() {
+            if expect(lexemes, i + 1, Number, None).is_err() {
+                expect(lexemes, i + 1, Identifier, None)?;
+            }
+            i += 2;
+        }
+        expect(lexemes, i, Punctuation, Some("]"))?;
+        return Ok((array_type(item), i + 1));
+    }
+    // A primitive type, or a struct name.
+    let name = expect(lexemes, i, Identifier, None)?;
+    if expect(lexemes, i + 1, Punctuation, Some("<")).is_err() {
+        let ts_ty = map_primitive_type(name, types.use_primitive_types);
+        return Ok((ts_ty.into(), i + 1));
+    }
+    // A generic type. `Vec` and `Option` have a TypeScript equivalent.
+    if name == "Vec" || name == "Option" {
+        let (inner, next) = parse_type(lexemes, i + 2, split_shr, types)?;
+        let next = expect_generic_end(lexemes, next, split_shr)?;
+        let ts_ty = if name == "Vec" { array_type(inner) }
+            else { format!("{} | null", inner) };
+        return Ok((ts_ty, next));
+    }
+    // Any other generic type is skipped, up to the `>` which ends it.
+    types.any_types.push((lexemes[i].column, lexemes[i].line_number));
+    let mut depth = 1;
+    let mut i = i + 2;
+    while depth > 0 {
+        match expect(lexemes, i, Punctuation, None) {
+            Ok("<") => depth += 1,
+            Ok(">") => depth -= 1,
+            Ok(">>") if depth == 1 => { depth = 0; *split_shr = true },
+            Ok(">>") => depth -= 2,
+            _ if i >= lexemes.len() => return Err(i),
+            _ => {},
+        }
+        i += 1;
+    }
+    Ok(("any".into(), i))
+}
+
+// Returns the index after the `>` which ends a generic type, or the index of
+// the Lexeme which does not fit. If the `>` is the second half of a `>>`, it
+// has already been passed.
+fn expect_generic_end(
+    lexemes: &[&Lexeme],
+    i: usize,
+    split_shr: &mut bool,
+) -> Result<usize, usize> {
+    if *split_shr { *split_shr = false; return Ok(i) }
+    match expect(lexemes, i, LexemeKind::Punctuation, None) {
+        Ok(">") => Ok(i + 1),
+        Ok(">>") => { *split_shr = true; Ok(i + 1) },
+        _ => Err(i),
+    }
+}
+
+// Returns the TypeScript array type of `item`. A union type, like
+// `number | null`, needs brackets, so that the `[]` applies to all of it.
+fn array_type(item: String) -> String {
+    if item.ends_with(" | null") { format!("({})[]", item) }
+    else { format!("{}[]", item) }
+}
+
+// Returns the snippet of the Lexeme at `i` if it is an integer or identifier,
+// without any number suffix. Otherwise, returns `i`.
+fn expect_range_bound<'a>(
+    lexemes: &[&Lexeme<'a>],
+    i: usize,
+) -> Result<String, usize> {
+    match expect(lexemes, i, LexemeKind::Number, None) {
+        Ok(value) if ! is_float_literal(value) => Ok(number_to_ts(value)),
+        Ok(_) => Err(i),
+        Err(_) => expect(lexemes, i, LexemeKind::Identifier, None)
+            .map(String::from),
+    }
+}
+
+// TypeScript has no number suffixes, so "4u8" becomes "4". JavaScript is also
+// stricter about underscores, so "43_.21" becomes "43.21", but "1_000" is kept.
+fn number_to_ts(value: &str) -> String {
+    let digits = match detect_number_detailed(value, 0)
+        .and_then(|info| info.suffix) {
+        Some(suffix) => &value[..value.len() - suffix.len()],
+        None => value,
+    };
+    if float_is_js_compatible(value, 0) { digits.into() }
+        else { digits.replace('_', "") }
+}
+
+// Returns the snippet of the Lexeme at `i`, if it is of the expected kind, and
+// (optionally) has the expected snippet. Otherwise, returns `i`.
+fn expect<'a>(
+    lexemes: &[&Lexeme<'a>],
+    i: usize,
+    kind: LexemeKind,
+    snippet: Option<&str>,
+) -> Result<&'a str, usize> {
+    match lexemes.get(i) {
+        Some(lexeme) if lexeme.kind == kind
+            && (snippet.is_none() || snippet == Some(lexeme.snippet))
+            => Ok(lexeme.snippet),
+        _ => Err(i),
+    }
+}
+
+/// Transpiles a Rust string literal, like `"a\tb"`, to a TypeScript string.
+/// 
+/// Most escapes, like `\n`, `\"` and `\x41`, mean the same in TypeScript, so
+/// they are kept. But `"\01"` would be an octal escape in TypeScript, so a `\0`
+/// followed by a digit becomes `\x00`. A backslash at the end of a line, which
+/// skips the newline and any indentation after it, is removed along with them.
+/// A literal newline inside the string becomes `\n`.
+/// 
+/// A `\u{...}` escape is kept for TypeScript 4, but TypeScript 3 might target
+/// ES5, which does not support it. So for `TsMajor::Ts3`, `\u{E9}` becomes
+/// `\u00E9`, and a char outside the Basic Multilingual Plane, like
+/// `\u{1F600}`, becomes a surrogate pair, like `\uD83D\uDE00`.
+/// 
+/// A raw string, like `r"C:\dir"`, has no escapes, so its contents are kept
+/// verbatim, with backslashes and double quotes escaped, like `"C:\\dir"`. A
+/// byte string, like `b"abc"`, is transpiled to an ordinary string.
+/// 
+/// ### Arguments
+/// * `snippet` A string literal, as detected by `detect_string()`
+/// * `ts_major` The major version of TypeScript to transpile to
+/// 
+/// ### Returns
+/// A double-quoted TypeScript string literal.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// # use opinionated_rust_to_typescript::transpile::config::TsMajor;
+/// let snippet = r#""Hi \u{1F600}""#;
+/// assert_eq!(rust_string_to_ts(snippet, &TsMajor::Ts4), snippet);
+/// assert_eq!(rust_string_to_ts(snippet, &TsMajor::Ts3),
+///     r#""Hi \uD83D\uDE00""#);
+/// ```
+pub fn rust_string_to_ts(snippet: &str, ts_major: &TsMajor) -> String {
+    // A byte string becomes an ordinary string.
+    let snippet = snippet.strip_prefix('b').unwrap_or(snippet);
+    // A raw string’s contents are kept verbatim, so escape them.
+    if let Some(raw) = snippet.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = raw.get(hashes + 1..raw.len() - hashes - 1).unwrap_or("");
+        let mut out = String::from("\"");
+        for c in inner.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        return out;
+    }
+    let inner = snippet.get(1..snippet.len() - 1).unwrap_or("");
+    let mut out = String::from("\"");
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // A TypeScript string cannot contain a literal line break.
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\\' => match chars.next() {
+                // A line continuation, which skips the newline and any
+                // whitespace after it.
+                Some('\n') | Some('\r') =>
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {},
+                // "\01" would be an octal escape in TypeScript.
+                Some('0') if matches!(chars.peek(),
+                    Some(d) if d.is_ascii_digit()) => out.push_str("\\x00"),
+                // A unicode escape, like "\u{1F600}".
+                Some('u') => {
+                    let hex: String = chars.by_ref()
+                        .take_while(|c| *c != '}')
+                        .filter(char::is_ascii_hexdigit)
+                        .collect();
+                    out.push_str(&unicode_escape_to_ts(&hex, ts_major));
+                },
+                // Every other escape means the same in TypeScript.
+                Some(escaped) => { out.push('\\'); out.push(escaped) },
+                None => {},
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Returns a TypeScript escape for the hex digits of a unicode code point.
+// TypeScript 3 might target ES5, which has no `\u{...}` escapes, so a code
+// point above U+FFFF becomes a surrogate pair.
+fn unicode_escape_to_ts(hex: &str, ts_major: &TsMajor) -> String {
+    if *ts_major != TsMajor::Ts3 { return format!("\\u{{{}}}", hex) }
+    let code = u32::from_str_radix(hex, 16).unwrap_or(0xFFFD);
+    if code <= 0xFFFF { return format!("\\u{:04X}", code) }
+    let offset = code - 0x10000;
+    format!("\\u{:04X}\\u{:04X}",
+        0xD800 + (offset >> 10), 0xDC00 + (offset & 0x3FF))
+}
+
+/// Maps a Rust type to a TypeScript type.
+/// 
+/// Numeric types map to `number`, TypeScript’s lowercase primitive type, rather
+/// than `Number`, its wrapper object type. Likewise `bool` maps to `boolean`,
+/// and `char`, `str` and `String` map to `string`. The unit type `()` maps to
+/// `void`. For wrapper object types, use `map_rust_type_to_ts_with_config()`.
+/// 
+/// Compound types are mapped recursively. A reference, like `&str`, maps to the
+/// type it refers to. `Vec<u8>`, `[u8; 4]` and `&[u8]` map to `number[]`, and
+/// `Option<bool>` maps to `boolean | null`. Any other generic type, like
+/// `HashMap<K, V>`, has no TypeScript equivalent yet, so maps to `any`.
+/// 
+/// ### Arguments
+/// * `rust_ty` A Rust type, like `"u8"` or `"Vec<Option<String>>"`
+/// 
+/// ### Returns
+/// The equivalent TypeScript type. An unknown type, like a struct name, or
+/// anything which is not a type, is returned unchanged.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// assert_eq!(map_rust_type_to_ts("f32"), "number");
+/// assert_eq!(map_rust_type_to_ts("MyStruct"), "MyStruct");
+/// assert_eq!(map_rust_type_to_ts("Vec<Vec<u8>>"), "number[][]");
+/// ```
+pub fn map_rust_type_to_ts(rust_ty: &str) -> String {
+    map_rust_type_to_ts_with_config(rust_ty, &Config::new())
+}
+
+/// Maps a Rust type to a TypeScript type, using the `use_primitive_types`
+/// setting of a [`Config`].
+/// 
+/// If `use_primitive_types` is `true`, this is the same as
+/// `map_rust_type_to_ts()`. Otherwise, types map to TypeScript’s wrapper
+/// object types, `Number`, `Boolean` and `String`.
+/// 
+/// ### Arguments
+/// * `rust_ty` A Rust type, like `"u8"` or `"Vec<Option<String>>"`
+/// * `config` The settings to use. Only `use_primitive_types` is used
+/// 
+/// ### Returns
+/// The equivalent TypeScript type, like `map_rust_type_to_ts()` returns.
+/// ```
+/// # use opinionated_rust_to_typescript::rs2018_ts4::rs2018_ts4_gungho::*;
+/// # use opinionated_rust_to_typescript::transpile::config::Config;
+/// let primitive = Config::new();
+/// assert_eq!(map_rust_type_to_ts_with_config("f32", &primitive), "number");
+/// let wrapper = Config::new().use_primitive_types(false);
+/// assert_eq!(map_rust_type_to_ts_with_config("f32", &wrapper), "Number");
+/// assert_eq!(map_rust_type_to_ts_with_config("Option<bool>", &wrapper),
+///     "Boolean | null");
+/// ```
+pub fn map_rust_type_to_ts_with_config(rust_ty: &str, config: &Config)
+    -> String {
+    let lexemes = lexemize(rust_ty);
+    let significant: Vec<&Lexeme> = lexemes.lexemes.iter()
+        .filter(|l| l.kind != LexemeKind::Whitespace)
+        .collect();
+    let mut types = TypeContext {
+        any_types: vec![],
+        use_primitive_types: config.use_primitive_types,
+    };
+    match expect_type(&significant, 0, &mut types) {
+        Ok((ts_ty, i)) if i == significant.len() => ts_ty,
+        _ => rust_ty.into(),
+    }
+}
+
+// Maps the name of a Rust primitive type to the name of a TypeScript type,
+// which is either a primitive type, like `number`, or a wrapper object type,
+// like `Number`. An unknown type, like a struct name, is returned unchanged.
+fn map_primitive_type(rust_ty: &str, use_primitive_types: bool) -> &str {
+    let (primitive, wrapper) = match rust_ty {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => ("number", "Number"),
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => ("number", "Number"),
+        "f32" | "f64" => ("number", "Number"),
+        "bool" => ("boolean", "Boolean"),
+        "char" | "str" | "String" => ("string", "String"),
+        "()" => return "void",
+        _ => return rust_ty,
+    };
+    if use_primitive_types { primitive } else { wrapper }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::rs2018_ts4_gungho as gungho;
+    use super::map_rust_type_to_ts as map;
+    use super::map_rust_type_to_ts_with_config as map_with_config;
+    use super::rust_string_to_ts as string_to_ts;
+    use super::{has_trailing_comma_before,lexemize,Lexeme,LexemeKind};
+    use super::METHOD_POLYFILLS;
+    use crate::transpile::config::{Config,TsMajor};
+
+    #[test]
+    fn rs2018_ts4_gungho_const() {
+        let result = gungho("const X: u8 = 4;");
+        assert_eq!(result.main_lines, vec!["const X: number = 4;"]);
+        assert!(result.errors.is_empty());
+        let result = gungho("const PI: f64 = 3.14;");
+        assert_eq!(result.main_lines, vec!["const PI: number = 3.14;"]);
+        // Whitespace, comments, a missing semicolon and a suffix.
+        let result = gungho("  const\tA /* a */:i8=\n  -12i8 ");
+        assert_eq!(result.main_lines, vec!["const A: number = -12;"]);
+        // Underscores which JavaScript would reject are removed.
+        let result = gungho("const A: u32 = 1_000u32;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1_000;"]);
+        let result = gungho("const A: f64 = 43_.2_1e+_10;");
+        assert_eq!(result.main_lines, vec!["const A: number = 43.21e+10;"]);
+        // Several declarations.
+        let result = gungho("const A: u8 = 1;\nconst B: f32 = 2.5;\n");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 1;", "const B: number = 2.5;"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_let() {
+        // `let` stays `let`, and `mut` disappears.
+        let result = gungho("let x = 1;\nlet mut y = 2;\nconst Z: u8 = 3;");
+        assert_eq!(result.main_lines,
+            vec!["let x = 1;", "let y = 2;", "const Z: number = 3;"]);
+        assert!(result.errors.is_empty());
+        assert!(! result.main_lines.iter().any(|line| line.contains("mut")));
+        // A variable named `mut_y` keeps its name.
+        let result = gungho("let mut mut_y = 2;");
+        assert_eq!(result.main_lines, vec!["let mut_y = 2;"]);
+        // Booleans, chars and byte chars.
+        let result = gungho("let b = true;");
+        assert_eq!(result.main_lines, vec!["let b = true;"]);
+        assert!(result.errors.is_empty());
+        let result = gungho("let c = 'A';");
+        assert_eq!(result.main_lines, vec!["let c = \"A\";"]);
+        let result = gungho("let n = b'A';");
+        assert_eq!(result.main_lines, vec!["let n = 65;"]);
+        // `mut` and a type, which are both optional.
+        let result = gungho("let mut f: bool = false");
+        assert_eq!(result.main_lines, vec!["let f: boolean = false;"]);
+        let result = gungho("let x: u8 = -4i8 as u8;");
+        assert_eq!(result.main_lines, vec!["let x: number = r$t$u8(-4);"]);
+        // Errors.
+        let result = gungho("let = 1;");
+        assert_eq!(result.errors[0].message, "Malformed let statement");
+        assert_eq!(result.errors[0].column, 5); // the "="
+        let result = gungho("let b = -true;");
+        assert_eq!(result.errors[0].column, 10); // the "true"
+        let result = gungho("let b = fn;");
+        assert_eq!(result.errors[0].column, 9); // the "fn"
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_big_integers() {
+        use crate::transpile::config::TsMajor;
+        use super::rs2018_ts4_gungho_with_ts_major as with_ts_major;
+        // TypeScript 4 is the default.
+        let result = gungho("let big: u64 = -0x20_0000_0000_0000;");
+        assert_eq!(result.main_lines,
+            vec!["let big: bigint = -0x20_0000_0000_0000n;"]);
+        let result = gungho("let big = 9007199254740992;");
+        assert_eq!(result.main_lines, vec!["let big = 9007199254740992n;"]);
+        // A cast needs a `number`.
+        let result = gungho("const A: u8 = 9007199254740992 as u8;");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = r$t$u8(9007199254740992);"]);
+        // Floats are always a `number`.
+        let result = gungho("const A: f64 = 9007199254740992.0;");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 9007199254740992.0;"]);
+        // TypeScript 3 warns once per statement.
+        let result = with_ts_major("for i in 0..9007199254740992 {\n\
+            const A: u64 = 9007199254740993; }", &TsMajor::Ts3);
+        assert_eq!(result.main_lines, vec!["for (let i = 0; \
+            i < 9007199254740992; i++) { const A: number = 9007199254740993; }"]);
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].column, 13);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_chars() {
+        let ts = |orig: &str| gungho(orig).main_lines[0].clone();
+        // Escapes which TypeScript shares are kept.
+        assert_eq!(ts(r"const C: char = '\n';"), r#"const C: string = "\n";"#);
+        assert_eq!(ts(r"const C: char = '\t';"), r#"const C: string = "\t";"#);
+        assert_eq!(ts(r"const C: char = '\\';"), r#"const C: string = "\\";"#);
+        assert_eq!(ts(r"const C: char = '\x41';"),
+            r#"const C: string = "\x41";"#);
+        assert_eq!(ts(r"const C: char = '\u{1F600}';"),
+            r#"const C: string = "\u{1F600}";"#);
+        assert_eq!(ts(r"const C: char = '\u{1_F6_00}';"),
+            r#"const C: string = "\u{1F600}";"#);
+        // Quotes.
+        assert_eq!(ts(r#"const C: char = '"';"#), r#"const C: string = "\"";"#);
+        assert_eq!(ts(r"const C: char = '\'';"), r#"const C: string = "'";"#);
+        // Non-ascii.
+        assert_eq!(ts("const C: char = 'é';"), "const C: string = \"é\";");
+        // Byte chars.
+        assert_eq!(ts(r"const N: u8 = b'\n';"), "const N: number = 10;");
+        assert_eq!(ts(r"const N: u8 = b'\xFF';"), "const N: number = 255;");
+        assert_eq!(ts(r"const N: u8 = b'\'';"), "const N: number = 39;");
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_multi_char_errors() {
+        let message =
+            "Chars hold one character, use double quotes for a string";
+        // However the lexemizer splits it, the error is at the first quote.
+        for orig in &["let c = 'ab';", r"let c = 'a\'b';", r"let c = '\n\n';",
+            "let c = '12';"] {
+            let result = gungho(orig);
+            assert!(result.main_lines.is_empty());
+            assert_eq!(result.errors.len(), 1);
+            assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+            assert_eq!(result.errors[0].message, message);
+            assert_eq!(result.errors[0].column, 9);
+        }
+        // Lifetimes and valid chars are not affected.
+        let result = gungho("fn f<'a,'b>(x: &'a u8) {}
+let c = 'a';");
+        assert!(!result.errors.iter().any(|e| e.message == message));
+        assert_eq!(result.main_lines[1], r#"let c = "a";"#);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_strings() {
+        let ts = |orig: &str| gungho(orig).main_lines[0].clone();
+        assert_eq!(ts(r#"const S: &str = "a\nb";"#),
+            r#"const S: string = "a\nb";"#);
+        assert_eq!(ts(r#"const S: &'static str = "\u{1F600}";"#),
+            r#"const S: string = "\u{1F600}";"#);
+        assert_eq!(ts(r#"let s = r"C:\dir";"#), r#"let s = "C:\\dir";"#);
+        assert_eq!(ts(r#"let s: &str = "";"#), r#"let s: string = "";"#);
+        // A string cannot be negated.
+        assert_eq!(gungho(r#"const S: &str = -"a";"#).errors.len(), 1);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_statements() {
+        // Two statements.
+        let result = gungho("const A: u8 = 1;\nconst B: f32 = 2.5;\n");
+        assert_eq!(result.main_lines.len(), 2);
+        // Two statements on one line.
+        let result = gungho("const A: u8 = 1;const B: f32 = 2.5;");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 1;", "const B: number = 2.5;"]);
+        // Blank lines are preserved, but trailing ones are not added.
+        let result = gungho("\nconst A: u8 = 1;\n\n\nconst B: u8 = 2;\n\n");
+        assert_eq!(result.main_lines, vec!["",
+            "const A: number = 1;", "", "", "const B: number = 2;"]);
+        // A statement spanning several lines.
+        let result = gungho("const A:\n  u8 =\n  1;\nconst B: u8 = 2;");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 1;", "", "", "const B: number = 2;"]);
+        // A malformed statement does not stop the next one being transpiled.
+        let result = gungho("const A: u8 = {\n1 };\nconst B: u8 = 2;");
+        assert_eq!(result.main_lines, vec!["", "", "const B: number = 2;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].column, 15); // the "{"
+        // A block ends a statement, even without a semicolon.
+        let result = gungho("const A: u8 = 1;\nmod m { ; }\nconst B: u8 = 2;");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 1;", "", "const B: number = 2;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].line_number, 2); // the "mod"
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_original_whitespace() {
+        // A blank line between two statements is preserved.
+        let result = gungho("const A: u8 = 1;\n\nconst B: u8 = 2;");
+        assert_eq!(result.to_string(),
+            "const A: number = 1;\n\nconst B: number = 2;\n");
+        // So is the indentation of each statement which begins a line.
+        let result = gungho("  const A: u8 = 1;\n\n\tfn f() {\n\t}");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 1;", "", "function f(): void {", "\t}"]);
+        assert_eq!(result.original_whitespace, vec!["  ", "", "\t"]);
+        assert_eq!(result.to_string(),
+            "  const A: number = 1;\n\n\tfunction f(): void {\n\t}\n");
+        // A statement which does not begin its line is not indented.
+        let result = gungho("  const A: u8 = 1; const B: u8 = 2;");
+        assert_eq!(result.original_whitespace, vec!["  ", ""]);
+        let result = gungho("/* a */ const A: u8 = 1;");
+        assert!(result.original_whitespace.iter().all(|w| w.is_empty()));
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_malformed_const() {
+        // The colon is missing.
+        let result = gungho("const X u8 = 4;");
+        assert!(result.main_lines.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].message, "Malformed const declaration");
+        assert_eq!(result.errors[0].line_number, 1);
+        assert_eq!(result.errors[0].column, 9); // the "u8"
+        // The value is missing, on the second line.
+        let result = gungho("const A: u8 = 1;\nconst B: u8 =");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 14); // the end of input
+        // Two declarations, without a semicolon between them.
+        let result = gungho("const A: u8 = 1 const B: u8 = 2;");
+        assert_eq!(result.errors[0].column, 17); // the second "const"
+        // An integer which is too large for Rust.
+        let result = gungho("const A: u128 = 1;\n\
+            const B: u128 = 1234567890123456789012345678901234567890;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "InvalidNumber");
+        assert_eq!(result.errors[0].message,
+            "Integer is larger than u128::MAX");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 17);
+        // The largest u128 is accepted.
+        let result = gungho("const C: u128 = \
+            340_282_366_920_938_463_463_374_607_431_768_211_455;");
+        assert!(result.errors.is_empty());
+        // Unidentifiable characters.
+        let result = gungho("const A: u8 = 1;\nconst B: u8 = ¢2;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message, "Unsupported characters");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 15); // the "¢"
+        // An uppercase radix prefix is malformed, and also gets a hint.
+        let result = gungho("const A: u8 = 0XAB;");
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].message, "Malformed const declaration");
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].to_string(), "warning[Hint] at 1:15: \
+            Radix prefixes are lowercase, did you mean 0x?");
+        assert!(gungho("const A: u8 = 0xAB;").warnings.is_empty());
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_for_range() {
+        // An exclusive range.
+        let result = gungho("for i in 0..n {}");
+        assert_eq!(result.main_lines,
+            vec!["for (let i = 0; i < n; i++) { }"]);
+        assert!(result.errors.is_empty());
+        // An inclusive range, with suffixes and a body.
+        let result = gungho("for i in 1u8..=10u8 { const A: u8 = 1; }");
+        assert_eq!(result.main_lines, vec!["for (let i = 1; i <= 10; i++) \
+            { const A: number = 1; }"]);
+        // Identifiers as both bounds, after a const declaration.
+        let result = gungho("const N: u8 = 3;\nfor x in m..=n {\n}");
+        assert_eq!(result.main_lines, vec!["const N: number = 3;",
+            "for (let x = m; x <= n; x++) { }"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_for_range_errors() {
+        // An unbounded start.
+        let result = gungho("for i in ..n {}");
+        assert!(result.main_lines.is_empty());
+        assert_eq!(result.errors[0].message, "Unsupported range expression");
+        assert_eq!(result.errors[0].column, 10); // the ".."
+        // An unbounded end.
+        let result = gungho("for i in 0.. {}");
+        assert_eq!(result.errors[0].message, "Unsupported range expression");
+        assert_eq!(result.errors[0].column, 14); // the "{"
+        // A bound which is not an integer or identifier.
+        let result = gungho("for i in 0..1.5 {}");
+        assert_eq!(result.errors[0].message, "Unsupported range expression");
+        assert_eq!(result.errors[0].column, 13); // the "1.5"
+        // A missing "in".
+        let result = gungho("for i 0..3 {}");
+        assert_eq!(result.errors[0].message, "Malformed for loop");
+        assert_eq!(result.errors[0].column, 7); // the "0"
+        // A malformed declaration in the body.
+        let result = gungho("for i in 0..3 {\n    const A u8 = 1;\n}");
+        assert_eq!(result.errors[0].message, "Malformed const declaration");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 13); // the "u8"
+        // No closing brace.
+        let result = gungho("for i in 0..3 {");
+        assert_eq!(result.errors[0].message, "Malformed for loop");
+        assert_eq!(result.errors[0].column, 16); // the end of input
+        // The removed `...` range, which is reported before anything else.
+        let result = gungho("const A: u8 = 1;\nfor i in 0...3 {}");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message,
+            "The `...` range was removed, use `..=`");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 11); // the "..."
+        let result = gungho("fn f(n: u8) { match n { 0...9 => {} } }");
+        assert_eq!(result.errors[0].column, 26); // the "..."
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_fn() {
+        let result = gungho("fn f(x: u8) -> bool { x > 1 }");
+        assert_eq!(result.main_lines,
+            vec!["function f(x: number): boolean { return x > 1; }"]);
+        assert!(result.errors.is_empty());
+        // No arguments and no return type.
+        let result = gungho("fn g() {}");
+        assert_eq!(result.main_lines, vec!["function g(): void {}"]);
+        // References, the unit type, a trailing comma and a multiline body.
+        let result = gungho("fn h(a: &str, b: (),) -> String {\n    a\n}");
+        assert_eq!(result.main_lines, vec![
+            "function h(a: string, b: void): string {", "    return a;", "}"]);
+        // A const declaration after a function keeps its line number.
+        let result = gungho("fn g() {\n}\nconst A: u8 = 1;");
+        assert_eq!(result.main_lines,
+            vec!["function g(): void {", "}", "const A: number = 1;"]);
+        // `pub` and attributes are dropped, first or later.
+        let result = gungho("pub fn f() {}");
+        assert_eq!(result.main_lines, vec!["function f(): void {}"]);
+        let result = gungho("const A: u8 = 1;\n#[inline]\npub fn f() {}");
+        assert_eq!(result.main_lines,
+            vec!["const A: number = 1;", "", "function f(): void {}"]);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_fn_returns() {
+        // An implicit return, which TypeScript needs to be explicit.
+        let result = gungho("fn f() -> u8 { 4 }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { return 4; }"]);
+        let result = gungho("fn f(a: u8) -> u8 {\n    let b = a; // c\n    \
+            g(b, [1; 2], |x| { x })\n}");
+        assert_eq!(result.main_lines, vec!["function f(a: number): number {",
+            "    let b = a; // c", "    return g(b, [1; 2], |x| { x });", "}"]);
+        // An explicit return passes through, with or without a semicolon.
+        let result = gungho("fn f() -> u8 { return 4; }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { return 4; }"]);
+        let result = gungho("fn f() -> u8 { if a { return 1; } return 2 }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { if a { return 1; } return 2 }"]);
+        // A body which ends with a statement, or a block, or is empty, does not
+        // get a `return`.
+        let result = gungho("fn f() -> u8 { g(); }");
+        assert_eq!(result.main_lines, vec!["function f(): number { g(); }"]);
+        let result = gungho("fn f() -> u8 { loop { g() } }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { loop { g() } }"]);
+        let result = gungho("fn f() -> u8 {}");
+        assert_eq!(result.main_lines, vec!["function f(): number {}"]);
+        // A "}" which the final expression continues from does not end it.
+        let result = gungho("fn f() -> u8 { S { a: 1 }.a }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { return S { a: 1 }.a; }"]);
+        let result = gungho("fn f() -> String { g(); match x { _ => 1 }\
+            .to_string() }");
+        assert_eq!(result.main_lines, vec!["function f(): string { g(); \
+            return match x { _ => 1 }.to_string(); }"]);
+        let result = gungho("fn f() -> u8 { if a { b() } c() }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { if a { b() } return c(); }"]);
+        // Outer attributes come before the `return`.
+        let result = gungho("fn f() -> u8 { g(); #[allow(x)] 1 }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { g(); #[allow(x)] return 1; }"]);
+        let result = gungho("fn f() -> u8 { #[allow(x)] }");
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { #[allow(x)] }"]);
+        // Nor does a function without a return type.
+        let result = gungho("fn f() { g() }");
+        assert_eq!(result.main_lines, vec!["function f(): void { g() }"]);
+        // Trailing commas can still be stripped.
+        let strip = Config::new().strip_trailing_commas(true);
+        let result = super::rs2018_ts4_gungho_with_config(
+            "fn f() -> u8 { g(1,) }", &strip);
+        assert_eq!(result.main_lines,
+            vec!["function f(): number { return g(1); }"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_if() {
+        // A simple `if`.
+        let result = gungho("if x > 1 { y(); }");
+        assert_eq!(result.main_lines, vec!["if (x > 1) { y(); }"]);
+        assert!(result.errors.is_empty());
+        // An `if/else`, over several lines, which keep their line numbers.
+        let result = gungho("if a {\n    b();\n} else {\n    c();\n}\n\
+            const D: u8 = 1;");
+        assert_eq!(result.main_lines, vec!["if (a) {", "    b();",
+            "} else {", "    c();", "}", "const D: number = 1;"]);
+        assert!(result.errors.is_empty());
+        // An `else if` chain, with brackets and braces in the conditions.
+        let result = gungho("if f(a, [1][0]) {} else if (b) { {} } else {}");
+        assert_eq!(result.main_lines,
+            vec!["if (f(a, [1][0])) {} else if ((b)) { {} } else {}"]);
+        // `else` on the line after a `}`, and a trailing semicolon.
+        let result = gungho("if a { b }\nelse { c };");
+        assert_eq!(result.main_lines, vec!["if (a) { b }", "else { c };"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_trailing_commas() {
+        use super::rs2018_ts4_gungho_with_config as with_config;
+        let strip = Config::new().ts_major(TsMajor::Ts3)
+            .strip_trailing_commas(true);
+        // Trailing commas are kept by default.
+        let result = gungho("if a { f(a, b,); }");
+        assert_eq!(result.main_lines, vec!["if (a) { f(a, b,); }"]);
+        let result = with_config("fn g() { f(a, b,) }", &Config::new());
+        assert_eq!(result.main_lines, vec!["function g(): void { f(a, b,) }"]);
+        // They can be stripped, including before `]` and `}`, and after
+        // whitespace or a comment.
+        let result = with_config("if a { f(a, b,); }", &strip);
+        assert_eq!(result.main_lines, vec!["if (a) { f(a, b); }"]);
+        let result = with_config("fn g() { f(a, b,) }", &strip);
+        assert_eq!(result.main_lines, vec!["function g(): void { f(a, b) }"]);
+        let result = with_config("if f([1, 2,],) { S { x: 1, } }", &strip);
+        assert_eq!(result.main_lines, vec!["if (f([1, 2])) { S { x: 1 } }"]);
+        let result = with_config("fn g() {\n    f(a, b /* c */,\n    )\n}",
+            &strip);
+        assert_eq!(result.main_lines,
+            vec!["function g(): void {", "    f(a, b /* c */", "    )", "}"]);
+        // Other commas are kept.
+        let result = with_config("if a { f(a, b); }", &strip);
+        assert_eq!(result.main_lines, vec!["if (a) { f(a, b); }"]);
+    }
+
+    #[test]
+    fn has_trailing_comma_before_as_expected() {
+        let lexemes = lexemize("f(a,) [1 ,] {, } (a) g(,)");
+        let significant: Vec<&Lexeme> = lexemes.lexemes.iter()
+            .filter(|l| l.kind != LexemeKind::Whitespace)
+            .collect();
+        assert!(has_trailing_comma_before(&significant, 4)); // ")"
+        assert!(has_trailing_comma_before(&significant, 8)); // "]"
+        assert!(has_trailing_comma_before(&significant, 11)); // "}"
+        assert!(! has_trailing_comma_before(&significant, 14)); // "(a)"
+        assert!(! has_trailing_comma_before(&significant, 3)); // ","
+        assert!(has_trailing_comma_before(&significant, 18)); // "g(,)"
+        assert!(! has_trailing_comma_before(&significant, 0));
+        assert!(! has_trailing_comma_before(&significant, 99));
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_if_errors() {
+        // Pattern matching is not supported yet.
+        let result = gungho("if let Some(x) = y { x }");
+        assert!(result.main_lines.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message, "`if let` is not supported yet");
+        assert_eq!(result.errors[0].column, 4); // the "let"
+        let result = gungho("if a {} else if let Some(x) = y { x }");
+        assert_eq!(result.errors[0].column, 17); // the second "let"
+        // A missing condition, body or closing brace.
+        let result = gungho("if { a }");
+        assert_eq!(result.errors[0].message, "Malformed if statement");
+        assert_eq!(result.errors[0].column, 4); // the "{"
+        let result = gungho("if a");
+        assert_eq!(result.errors[0].message, "Malformed if statement");
+        assert_eq!(result.errors[0].column, 5); // the end of input
+        let result = gungho("if a { b } else c");
+        assert_eq!(result.errors[0].column, 17); // the "c"
+        let result = gungho("if a { { b }");
+        assert_eq!(result.errors[0].column, 13); // the end of input
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_fn_errors() {
+        // Generics are not supported yet.
+        let result = gungho("fn h<T>(x: T) {}");
+        assert!(result.main_lines.is_empty());
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message,
+            "Generic functions are not supported yet");
+        assert_eq!(result.errors[0].column, 5); // the "<"
+        // A missing type.
+        let result = gungho("fn f(x) {}");
+        assert_eq!(result.errors[0].message, "Malformed function declaration");
+        assert_eq!(result.errors[0].column, 7); // the ")"
+        // A missing comma.
+        let result = gungho("fn f(x: u8 y: u8) {}");
+        assert_eq!(result.errors[0].column, 12); // the "y"
+        // A missing body.
+        let result = gungho("fn f();");
+        assert_eq!(result.errors[0].column, 7); // the ";"
+        let result = gungho("fn f() -> u8");
+        assert_eq!(result.errors[0].column, 13); // the end of input
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_struct() {
+        // A named-field struct becomes an interface in `type_lines`.
+        let result = gungho("struct Point { x: f64, y: f64 }");
+        assert_eq!(result.type_lines,
+            vec!["interface Point { x: number; y: number; }"]);
+        assert!(result.main_lines.is_empty());
+        assert!(result.errors.is_empty());
+        // `pub`, references, attributes and a trailing comma.
+        let result = gungho("struct User {\n    pub name: &'static str,\n    \
+            pub(crate) age: u8,\n    #[allow(dead_code)]\n    ok: bool,\n}");
+        assert_eq!(result.type_lines, vec![
+            "interface User { name: string; age: number; ok: boolean; }"]);
+        // Empty and unit structs.
+        assert_eq!(gungho("struct E {}").type_lines, vec!["interface E { }"]);
+        assert_eq!(gungho("struct U;").type_lines, vec!["interface U { }"]);
+        // Alongside other statements, which keep their line numbers.
+        let result = gungho("struct A {\n    a: u8,\n}\nconst B: u8 = 1;");
+        assert_eq!(result.main_lines, vec!["", "", "", "const B: number = 1;"]);
+        assert_eq!(result.type_lines, vec!["interface A { a: number; }"]);
+        // Compound field types.
+        let result = gungho("struct S { v: Vec<Vec<u8>>, o: Option<bool> }");
+        assert_eq!(result.type_lines,
+            vec!["interface S { v: number[][]; o: boolean | null; }"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_compound_types() {
+        let result = gungho("const A: [u8; 4] = X;");
+        assert_eq!(result.main_lines, vec!["const A: number[] = X;"]);
+        let result = gungho("let v: Vec<f64> = w;");
+        assert_eq!(result.main_lines, vec!["let v: number[] = w;"]);
+        let result =
+            gungho("fn f(s: &str, o: Option<u8>) -> Vec<bool> { g() }");
+        assert_eq!(result.main_lines, vec![
+            "function f(s: string, o: number | null): boolean[] { return g(); }"
+        ]);
+        assert!(result.errors.is_empty());
+        assert!(result.warnings.is_empty());
+        // An unsupported generic becomes `any`, with a warning at its name.
+        let result = gungho("struct M {
+    m: HashMap<String, u8>,
+}");
+        assert_eq!(result.type_lines, vec!["interface M { m: any; }"]);
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].to_string(), "warning[AnyType] at 2:8: \
+            Unsupported generic type, using `any`");
+        let result = gungho("for i in 0..2 { const A: Box<u8> = i; }");
+        assert_eq!(result.main_lines,
+            vec!["for (let i = 0; i < 2; i++) { const A: any = i; }"]);
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].column, 26);
+        // A malformed generic is an error.
+        let result = gungho("let v: Vec<u8>> = w;");
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].column, 14); // the ">>"
+        let result = gungho("let v: Vec<u8 = w;");
+        assert_eq!(result.errors[0].column, 15); // the "="
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_enum() {
+        // A fieldless enum becomes a TypeScript enum in `type_lines`.
+        let result = gungho("enum Color { Red, Green, Blue }");
+        assert_eq!(result.type_lines, vec!["enum Color { Red, Green, Blue }"]);
+        assert!(result.main_lines.is_empty());
+        assert!(result.errors.is_empty());
+        // A trailing comma, attributes and discriminants.
+        let result = gungho("enum E {\n    #[allow(dead_code)]\n    \
+            A = 1,\n    B = -2i8,\n    C,\n}");
+        assert_eq!(result.type_lines, vec!["enum E { A = 1, B = -2, C }"]);
+        // An empty enum.
+        assert_eq!(gungho("enum Never {}").type_lines, vec!["enum Never { }"]);
+        // Alongside other statements, which keep their line numbers.
+        let result = gungho("enum A {\n    X,\n}\nconst B: u8 = 1;");
+        assert_eq!(result.main_lines, vec!["", "", "", "const B: number = 1;"]);
+        assert_eq!(result.type_lines, vec!["enum A { X }"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_enum_errors() {
+        // Variants with data are not supported yet.
+        let result = gungho("enum E { A(u8), B }");
+        assert!(result.type_lines.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message,
+            "Enum variants with data are not supported yet");
+        assert_eq!(result.errors[0].column, 11); // the "("
+        let result = gungho("enum E { A, B { x: u8 } }");
+        assert_eq!(result.errors[0].message,
+            "Enum variants with data are not supported yet");
+        assert_eq!(result.errors[0].column, 15); // the "{"
+        // Generic enums are not supported yet.
+        let result = gungho("enum E<T> { A }");
+        assert_eq!(result.errors[0].message,
+            "Generic enums are not supported yet");
+        assert_eq!(result.errors[0].column, 7); // the "<"
+        // Malformed enums.
+        let result = gungho("enum E { A B }");
+        assert_eq!(result.errors[0].message, "Malformed enum");
+        assert_eq!(result.errors[0].column, 12); // the "B"
+        let result = gungho("enum E { A = x }");
+        assert_eq!(result.errors[0].column, 14); // the "x"
+        let result = gungho("enum { A }");
+        assert_eq!(result.errors[0].column, 6); // the "{"
+        let result = gungho("enum E { A,");
+        assert_eq!(result.errors[0].column, 12); // the end of input
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_struct_errors() {
+        // Tuple structs are not supported yet.
+        let result = gungho("struct P(f64, f64);");
+        assert!(result.type_lines.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message,
+            "Tuple structs are not supported yet");
+        assert_eq!(result.errors[0].column, 9); // the "("
+        // Nor are generic structs.
+        let result = gungho("struct W<T> { t: T }");
+        assert_eq!(result.errors[0].message,
+            "Generic structs are not supported yet");
+        // Malformed fields.
+        let result = gungho("struct S { a u8 }");
+        assert_eq!(result.errors[0].message, "Malformed struct");
+        assert_eq!(result.errors[0].column, 14); // the "u8"
+        let result = gungho("struct S { a: u8 b: u8 }");
+        assert_eq!(result.errors[0].column, 18); // the "b"
+        let result = gungho("struct S { a: Vec<u8 }");
+        assert_eq!(result.errors[0].column, 22); // the "}"
+        let result = gungho("struct S { a: u8,");
+        assert_eq!(result.errors[0].column, 18); // the end of input
+        let result = gungho("struct S { pub(crate a: u8 }");
+        assert_eq!(result.errors[0].column, 29); // the end of input
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_print() {
+        let ts = |orig: &str| gungho(orig).main_lines.join("\n");
+        // No arguments.
+        assert_eq!(ts(r#"println!("hello");"#), r#"console.log("hello");"#);
+        assert_eq!(ts("println!()"), "console.log();");
+        assert_eq!(ts(r#"eprintln!("oops\n")"#), r#"console.error("oops\n");"#);
+        // One argument, and a trailing comma.
+        assert_eq!(ts(r#"println!("{}", x);"#), "console.log(`${x}`);");
+        assert_eq!(ts(r#"print!("x is {}!", x,);"#),
+            "console.log(`x is ${x}!`);");
+        // Several arguments, which can be any value.
+        assert_eq!(ts(r#"eprint!("{}{} {}", -1, 'c', "s")"#),
+            r#"console.error(`${-1}${"c"} ${"s"}`);"#);
+        assert_eq!(ts(r#"println!("{}", n as u8);"#),
+            "console.log(`${r$t$u8(n)}`);");
+        // Escaped braces, escapes, and chars which a template must escape.
+        assert_eq!(ts(r#"println!("{{{}}} \u{1F600}", x)"#),
+            r"console.log(`{${x}} \u{1F600}`);");
+        assert_eq!(ts(r#"println!("`$`${{{}", x)"#),
+            r"console.log(`\`$\`\${${x}`);");
+        assert_eq!(ts(r#"println!("{{}}")"#), r#"console.log("{}");"#);
+        // A raw format string.
+        assert_eq!(ts(r##"println!(r#"C:\{}"#, dir)"##),
+            r"console.log(`C:\\${dir}`);");
+        assert_eq!(ts(r#"print!("`a` ${{b}} {}", x);"#),
+            r"console.log(`\`a\` \${b} ${x}`);");
+        assert_eq!(ts(r#"println!("{{ {} }}{}}}", a, b)"#),
+            "console.log(`{ ${a} }${b}}`);");
+        // Arguments which are expressions, split at commas outside brackets.
+        assert_eq!(ts(r#"println!("{}", f(a, b));"#),
+            "console.log(`${f(a, b)}`);");
+        assert_eq!(ts(r#"println!("{} {}", a.b(), [1, 2][0],)"#),
+            "console.log(`${a.b()} ${[1, 2][0]}`);");
+        assert_eq!(ts(r#"println!("{}", x + 1);"#), "console.log(`${x + 1}`);");
+        assert_eq!(ts(r#"println!("{}", n.to_string());"#),
+            "console.log(`${String(n)}`);");
+        // Alongside other statements.
+        assert_eq!(ts("let x = 1;\nprintln!(\"{}\", x);"),
+            "let x = 1;\nconsole.log(`${x}`);");
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_print_errors() {
+        // Named and formatted placeholders are not supported yet.
+        let result = gungho(r#"println!("{name}");"#);
+        assert!(result.main_lines.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnsupportedSyntax");
+        assert_eq!(result.errors[0].message, "Unsupported format placeholder");
+        assert_eq!(result.errors[0].column, 10); // the format string
+        let result = gungho(r#"println!("{:?}", x);"#);
+        assert_eq!(result.errors[0].message, "Unsupported format placeholder");
+        let result = gungho(r#"println!("{0}", x);"#);
+        assert_eq!(result.errors[0].message, "Unsupported format placeholder");
+        let result = gungho(r#"println!("}", x);"#);
+        assert_eq!(result.errors[0].message, "Unsupported format placeholder");
+        // The number of arguments must match the placeholders.
+        let wrong = "Wrong number of format arguments";
+        let result = gungho(r#"println!("{} {}", x);"#);
+        assert_eq!(result.errors[0].message, wrong);
+        let result = gungho(r#"println!("hello", x);"#);
+        assert_eq!(result.errors[0].message, wrong);
+        // Malformed macro calls.
+        let result = gungho(r#"println("hello");"#);
+        assert_eq!(result.errors[0].message, "Malformed print macro");
+        assert_eq!(result.errors[0].column, 8); // the "("
+        let result = gungho(r#"println!("{}", x y);"#);
+        assert_eq!(result.errors[0].column, 18); // the "y"
+        let result = gungho(r#"println!("{}", fn);"#);
+        assert_eq!(result.errors[0].column, 16); // the "fn"
+        let result = gungho(r#"println!(x);"#);
+        assert_eq!(result.errors[0].column, 10); // the "x"
+        let result = gungho(r#"println!("{}", f(a, b);"#);
+        assert_eq!(result.errors[0].column, 24); // the end of input
+        let result = gungho(r#"println!("{}", , x);"#);
+        assert_eq!(result.errors[0].column, 16); // the second ","
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_unterminated_string() {
+        // The placeholder is still used, but the error is reported.
+        let result = gungho("let s = \"oops");
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnterminatedString");
+        assert_eq!(result.errors[0].message, "Unterminated string");
+        assert_eq!(result.errors[0].line_number, 1);
+        assert_eq!(result.errors[0].column, 9); // the opening double quote
+        // Statements before the string are still transpiled, and the comment
+        // inside the string is not kept.
+        let result = gungho("const A: u8 = 1;\nconst B: u8 = r#\"a // b\"");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnterminatedString");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 15); // the "r" prefix
+        // A raw identifier is not mistaken for a raw string.
+        let result = gungho("const A: u8 = 1; // r#type");
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_unterminated_comment() {
+        // Statements before the comment are still transpiled.
+        let result = gungho("const A: u8 = 1;\n/* a /* b */\nconst B: u8 = 2;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnterminatedComment");
+        assert_eq!(result.errors[0].message, "Unterminated comment");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 1); // the "/*"
+        // A statement cut short by the comment is not reported as malformed.
+        let result = gungho("const A: u8 = /* 1;");
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind.to_string(), "UnterminatedComment");
+        assert_eq!(result.errors[0].column, 15); // the "/*"
+        // A terminated comment is not an error.
+        let result = gungho("const A: u8 = 1; /* a */");
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_comments() {
+        // An inline comment before a statement.
+        let result = gungho("// hi\nconst FOUR: u8 = 4;");
+        assert_eq!(result.main_lines, vec!["// hi", "const FOUR: number = 4;"]);
+        // A multiline comment, and a comment at the end of a line.
+        let result = gungho("/* a\n  b */\nconst A: u8 = 1; // one\n// end");
+        assert_eq!(result.main_lines, vec!["/* a", "  b */",
+            "const A: number = 1; // one", "// end"]);
+        // A statement on the same line as the end of a multiline comment.
+        let result = gungho("/* a\n b */ const X: u8 = 1;\n// two\n\
+            const Y: u8 = 2; // three");
+        assert_eq!(result.main_lines, vec!["/* a", " b */ const X: number = 1;",
+            "// two", "const Y: number = 2; // three"]);
+        // A comment at the end of the input does not include its newline.
+        let result = gungho("const A: u8 = 1; // one\n");
+        assert_eq!(result.main_lines, vec!["const A: number = 1; // one"]);
+        // A comment inside a statement is skipped.
+        let result = gungho("const A: /* u8? */ u8 = 1;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        // Doc comments become plain comments.
+        let result =
+            gungho("//! Crate\n/// Doc\n//// Not doc\nconst A: u8 = 1;");
+        assert_eq!(result.main_lines, vec!["// Crate", "// Doc",
+            "//// Not doc", "const A: number = 1;"]);
+        // Blank lines between comments and statements are preserved.
+        let result = gungho("const A: u8 = 1;\n\n/* x */\n\nconst B: u8 = 2;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;", "",
+            "/* x */", "", "const B: number = 2;"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_polyfills() {
+        // One `.len()` call.
+        let result = gungho("const A: u8 = 1;\nfn f() -> usize { s.len() }");
+        assert_eq!(result.polyfill_lines,
+            vec!["String.prototype.len=Array.prototype.len=\
+            function(){return this.length}"]);
+        assert_eq!(result.type_lines,
+            vec!["interface String { len(): number } \
+            interface Array<T> { len(): number }"]);
+        assert_eq!(result.main_section_begins, ";r$t$();");
+        assert_eq!(result.polyfill_section_begins, ";function r$t$(){");
+        assert_eq!(result.polyfill_section_ends, "};");
+        // Several `.len()` calls only need one polyfill.
+        let result =
+            gungho("fn f() -> usize { a.len() + b . len ( ) + \"c\".len() }");
+        assert_eq!(result.polyfill_lines.len(), 1);
+        assert_eq!(result.type_lines.len(), 1);
+        // No `.len()` call.
+        let result =
+            gungho("const A: u8 = 1; // s.len()\nfn f() { len(); s.len; }");
+        assert!(result.polyfill_lines.is_empty());
+        assert!(result.type_lines.is_empty());
+        assert_eq!(result.main_section_begins, "");
+        assert_eq!(result.polyfill_section_begins, "");
+        assert_eq!(result.polyfill_section_ends, "");
+        // A statement which could not be transpiled needs no polyfill.
+        let result = gungho("let v = s.len(); const B: u8 = 300.0 as u8 as;");
+        assert_eq!(result.errors.len(), 2);
+        assert!(result.main_lines.is_empty());
+        assert!(result.polyfill_lines.is_empty());
+        assert!(result.type_lines.is_empty());
+        assert_eq!(result.main_section_begins, "");
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_method_polyfills() {
+        let polyfill = |method: &str| METHOD_POLYFILLS.iter()
+            .find(|(m, _)| *m == method).unwrap().1;
+        // `.len()` needs a polyfill, and is passed through unchanged.
+        let result = gungho("fn f(s: &str) -> usize { s.len() }");
+        assert_eq!(result.main_lines,
+            vec!["function f(s: string): number { return s.len(); }"]);
+        assert_eq!(result.polyfill_lines, vec![polyfill("len").unwrap().0]);
+        assert_eq!(result.type_lines,
+            vec!["interface String { len(): number } \
+            interface Array<T> { len(): number }"]);
+        assert_eq!(result.main_section_begins, ";r$t$();");
+        // A `Vec` becomes an array, which needs the same polyfill.
+        let result = gungho("fn f(v: Vec<u8>) -> usize { v.len() }");
+        assert_eq!(result.main_lines,
+            vec!["function f(v: number[]): number { return v.len(); }"]);
+        assert_eq!(result.polyfill_lines, vec![polyfill("len").unwrap().0]);
+        // Several `.len()` calls only need one polyfill.
+        let result = gungho("fn f() -> usize { a.len() + b.len() + c.len() }");
+        assert_eq!(result.polyfill_lines.len(), 1);
+        // `.push()`, `.trim()` and `.to_string()` need no polyfills.
+        assert!(polyfill("push").is_none());
+        assert!(polyfill("to_string").is_none());
+        let result =
+            gungho("fn f() { v.push(x); s.trim(); n.to_string(); push(y); }");
+        assert!(result.polyfill_lines.is_empty());
+        assert!(result.type_lines.is_empty());
+        assert_eq!(result.main_section_begins, "");
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_to_string() {
+        let body = |rust: &str| {
+            let result = gungho(&format!("fn f() -> String {{ {} }}", rust));
+            assert!(result.polyfill_lines.is_empty());
+            result.main_lines[0].clone()
+        };
+        // `x.to_string()` becomes `String(x)`.
+        assert_eq!(body("n.to_string()"),
+            "function f(): string { return String(n); }");
+        assert_eq!(body("let s = 1.5.to_string(); s"),
+            "function f(): string { let s = String(1.5); return s; }");
+        // The receiver can be a chain of fields, calls, indexes and paths.
+        assert_eq!(body("a.b(c, d)[0].e.to_string()"),
+            "function f(): string { return String(a.b(c, d)[0].e); }");
+        assert_eq!(body("m::g().to_string().trim()"),
+            "function f(): string { return String(m::g()).trim(); }");
+        assert_eq!(body("(a + b).to_string()"),
+            "function f(): string { return String((a + b)); }");
+        assert_eq!(body("&self.x.to_string().to_string()"),
+            "function f(): string { return &String(String(self.x)); }");
+        // The rest of the expression is not part of the receiver.
+        assert_eq!(body("a + b.to_string()"),
+            "function f(): string { return a + String(b); }");
+        // Not a `.to_string()` call.
+        assert_eq!(body("to_string(a)"),
+            "function f(): string { return to_string(a); }");
+        assert_eq!(body("a.to_string"),
+            "function f(): string { return a.to_string; }");
+        // In an `if` statement too.
+        let result = gungho("if a.to_string() == b { c.to_string(); }");
+        assert_eq!(result.main_lines,
+            vec!["if (String(a) == b) { String(c); }"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_casts() {
+        // A cast which TypeScript’s `as` can do, so no polyfill is needed.
+        let result = gungho("const A: f64 = y as f64;");
+        assert_eq!(result.main_lines, vec!["const A: number = (y as number);"]);
+        assert!(result.errors.is_empty());
+        assert!(result.polyfill_lines.is_empty());
+        assert!(result.type_lines.is_empty());
+        // A narrowing cast, which needs a polyfill.
+        let result = gungho("const B: u8 = n as u8;\nconst C: u8 = -3 as u8;");
+        assert_eq!(result.main_lines, vec![
+            "const B: number = r$t$u8(n);",
+            "const C: number = r$t$u8(-3);"]);
+        assert_eq!(result.polyfill_lines,
+            vec![";r$t$u8=function(n){return n&255}"]);
+        assert_eq!(result.type_lines,
+            vec!["var r$t$u8: (n: number) => number;"]);
+        assert_eq!(result.main_section_begins, ";r$t$();");
+        // Alongside a `.len()` polyfill. A float saturates, rather than wraps.
+        let result =
+            gungho("const D: i32 = 1.5 as i32;\nfn e() -> usize { s.len() }");
+        assert_eq!(result.main_lines[0], "const D: number = r$t$f2i32(1.5);");
+        assert_eq!(result.polyfill_lines.len(), 2);
+        assert_eq!(result.polyfill_lines[1],
+            ";r$t$f2i32=function(n){\
+            return Math.min(Math.max(n,-2147483648),2147483647)|0}");
+        // Out of range floats, which Rust casts to 255, 0 and -128. A float
+        // literal cannot be NaN, but the `|0` in each helper turns NaN into 0.
+        let result = gungho("const G: u8 = 300.0 as u8;\n\
+            const H: u8 = -1e10 as u8;\nconst I: i8 = -200f32 as i8;");
+        assert_eq!(result.main_lines, vec![
+            "const G: number = r$t$f2u8(300.0);",
+            "const H: number = r$t$f2u8(-1e10);",
+            "const I: number = r$t$f2i8(-200);"]);
+        assert_eq!(result.polyfill_lines, vec![
+            ";r$t$f2u8=function(n){\
+                return Math.min(Math.max(n,0),255)|0}",
+            ";r$t$f2i8=function(n){\
+                return Math.min(Math.max(n,-128),127)|0}"]);
+        assert_eq!(result.type_lines, vec![
+            "var r$t$f2u8: (n: number) => number;",
+            "var r$t$f2i8: (n: number) => number;"]);
+        // An integer and a float cast to the same type need both helpers.
+        let result =
+            gungho("const J: u8 = 300 as u8;\nconst K: u8 = 3e2 as u8;");
+        assert_eq!(result.polyfill_lines.len(), 2);
+        // A missing type after `as`.
+        let result = gungho("const F: u8 = n as;");
+        assert_eq!(result.errors[0].message, "Malformed const declaration");
+        assert_eq!(result.errors[0].column, 19); // the ";"
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_wrapper_types() {
+        use super::rs2018_ts4_gungho_with_config as with_config;
+        let wrapper = Config::new().use_primitive_types(false);
+        // Declared types become wrapper object types.
+        let result = with_config("const A: u8 = 1;", &wrapper);
+        assert_eq!(result.main_lines, vec!["const A: Number = 1;"]);
+        assert!(result.errors.is_empty());
+        let result = with_config("let v: Vec<Option<bool>> = x;", &wrapper);
+        assert_eq!(result.main_lines, vec!["let v: (Boolean | null)[] = x;"]);
+        // Casts always use primitive types.
+        let result = with_config("const B: f64 = y as f64;", &wrapper);
+        assert_eq!(result.main_lines, vec!["const B: Number = (y as number);"]);
+        // The default is primitive types, like `rs2018_ts4_gungho()`.
+        let result = with_config("const C: bool = true;", &Config::new());
+        assert_eq!(result.main_lines, vec!["const C: boolean = true;"]);
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_unsupported_statements() {
+        // Nothing to transpile.
+        let result = gungho("");
+        assert!(result.main_lines.is_empty());
+        assert!(result.errors.is_empty());
+        // A statement which is not recognised, first or later.
+        let result = gungho("FOUR");
+        assert!(result.main_lines.is_empty());
+        assert_eq!(result.errors[0].to_string(),
+            "error[UnsupportedSyntax] at 1:1: Unsupported statement");
+        let result = gungho("const A: u8 = 1;\n  mod m;");
+        assert_eq!(result.main_lines, vec!["const A: number = 1;"]);
+        assert_eq!(result.errors[0].to_string(),
+            "error[UnsupportedSyntax] at 2:3: Unsupported statement");
+        // Attributes and `pub`, with nothing after them.
+        let result = gungho("#[test]");
+        assert_eq!(result.errors[0].message, "Unsupported statement");
+        let result = gungho("pub(crate");
+        assert_eq!(result.errors[0].message, "Unsupported statement");
+    }
+
+    #[test]
+    fn rs2018_ts4_gungho_attributes_and_pub() {
+        // The most common struct forms.
+        let result = gungho("pub struct S { x: u8 }");
+        assert_eq!(result.type_lines, vec!["interface S { x: number; }"]);
+        assert!(result.errors.is_empty());
+        let result = gungho("#[derive(Debug)]\nstruct P { pub x: u8 }");
+        assert_eq!(result.type_lines, vec!["interface P { x: number; }"]);
+        assert!(result.errors.is_empty());
+        // Any item can have attributes and `pub`.
+        let result = gungho("#![no_std]\npub(crate) const A: u8 = 1;");
+        assert_eq!(result.main_lines, vec!["", "const A: number = 1;"]);
+        let result = gungho("#[derive(Clone)] pub enum E { A }");
+        assert_eq!(result.errors.len(), 0);
+        assert_eq!(result.type_lines.len(), 1);
+    }
+
+    #[test]
+    fn map_rust_type_to_ts_numbers() {
+        assert_eq!(map("u8"), "number");
+        assert_eq!(map("u16"), "number");
+        assert_eq!(map("u32"), "number");
+        assert_eq!(map("u64"), "number");
+        assert_eq!(map("u128"), "number");
+        assert_eq!(map("usize"), "number");
+        assert_eq!(map("i8"), "number");
+        assert_eq!(map("i16"), "number");
+        assert_eq!(map("i32"), "number");
+        assert_eq!(map("i64"), "number");
+        assert_eq!(map("i128"), "number");
+        assert_eq!(map("isize"), "number");
+        assert_eq!(map("f32"), "number");
+        assert_eq!(map("f64"), "number");
+    }
+
+    #[test]
+    fn map_rust_type_to_ts_others() {
+        assert_eq!(map("bool"), "boolean");
+        assert_eq!(map("char"), "string");
+        assert_eq!(map("str"), "string");
+        assert_eq!(map("String"), "string");
+        assert_eq!(map("()"), "void");
+    }
+
+    #[test]
+    fn map_rust_type_to_ts_unknown() {
+        assert_eq!(map("MyStruct"), "MyStruct"); // passed through unchanged
+        assert_eq!(map("u256"), "u256"); // not a Rust primitive
+        assert_eq!(map("F32"), "F32"); // case sensitive
+        assert_eq!(map(""), ""); // empty string
+        assert_eq!(map("Vec<u8"), "Vec<u8"); // not a type
+        assert_eq!(map("[u8; 4"), "[u8; 4");
+        assert_eq!(map("Vec<u8>>"), "Vec<u8>>");
+    }
+
+    #[test]
+    fn map_rust_type_to_ts_compound() {
+        // `Vec`, arrays and slices become arrays.
+        assert_eq!(map("Vec<f64>"), "number[]");
+        assert_eq!(map("[u8; 4]"), "number[]");
+        assert_eq!(map("[bool; LEN]"), "boolean[]");
+        assert_eq!(map("&[char]"), "string[]");
+        // `Option` becomes a union with `null`.
+        assert_eq!(map("Option<bool>"), "boolean | null");
+        assert_eq!(map("Option<Vec<String>>"), "string[] | null");
+        assert_eq!(map("Vec<Option<u8>>"), "(number | null)[]");
+        // References, with or without a lifetime or `mut`.
+        assert_eq!(map("&str"), "string");
+        assert_eq!(map("&'static str"), "string");
+        assert_eq!(map("&mut Vec<i32>"), "number[]");
+        assert_eq!(map("Vec<&'a str>"), "string[]");
+        // Nested generics, where the lexemizer sees `>>` as one Lexeme.
+        assert_eq!(map("Vec<Vec<u8>>"), "number[][]");
+        assert_eq!(map("Vec<Vec<Vec<u8>>>"), "number[][][]");
+        assert_eq!(map("[Vec<Option<u8>>; 2]"), "(number | null)[][]");
+        assert_eq!(map("Vec < Vec < u8 > >"), "number[][]");
+        // Other generics fall back to `any`.
+        assert_eq!(map("HashMap<String, u8>"), "any");
+        assert_eq!(map("Vec<HashMap<K, Vec<V>>>"), "any[]");
+        assert_eq!(map("Option<Box<u8>>"), "any | null");
+    }
+
+    #[test]
+    fn map_rust_type_to_ts_with_config_as_expected() {
+        let primitive = Config::new();
+        assert_eq!(map_with_config("f64", &primitive), "number");
+        assert_eq!(map_with_config("&str", &primitive), "string");
+        assert_eq!(map_with_config("Option<bool>", &primitive),
+            "boolean | null");
+        let wrapper = Config::new().use_primitive_types(false);
+        assert_eq!(map_with_config("f64", &wrapper), "Number");
+        assert_eq!(map_with_config("&str", &wrapper), "String");
+        assert_eq!(map_with_config("char", &wrapper), "String");
+        assert_eq!(map_with_config("Option<bool>", &wrapper),
+            "Boolean | null");
+        assert_eq!(map_with_config("Vec<Vec<u8>>", &wrapper), "Number[][]");
+        // `void`, `any` and unknown types are unaffected.
+        assert_eq!(map_with_config("()", &wrapper), "void");
+        assert_eq!(map_with_config("HashMap<K, V>", &wrapper), "any");
+        assert_eq!(map_with_config("MyStruct", &wrapper), "MyStruct");
+    }
+
+    #[test]
+    fn rust_string_to_ts_escapes() {
+        let ts4 = |orig: &str| string_to_ts(orig, &TsMajor::Ts4);
+        // Escapes which TypeScript shares are kept.
+        assert_eq!(ts4(r#""line\nbreak""#), r#""line\nbreak""#);
+        assert_eq!(ts4(r#""\t\r\\\"\x41""#), r#""\t\r\\\"\x41""#);
+        assert_eq!(ts4(r#""\0""#), r#""\0""#);
+        // "\01" would be an octal escape in TypeScript.
+        assert_eq!(ts4(r#""\01""#), r#""\x001""#);
+        // Literal line breaks, and line continuations.
+        assert_eq!(ts4("\"a\nb\r\n\""), r#""a\nb\r\n""#);
+        assert_eq!(ts4("\"a \\\n    b\""), r#""a b""#);
+        // Byte strings, and an empty string.
+        assert_eq!(ts4(r#"b"a\n""#), r#""a\n""#);
+        assert_eq!(ts4(r#""""#), r#""""#);
+    }
+
+    #[test]
+    fn rust_string_to_ts_unicode() {
+        let ts3 = |orig: &str| string_to_ts(orig, &TsMajor::Ts3);
+        let ts4 = |orig: &str| string_to_ts(orig, &TsMajor::Ts4);
+        // Basic Multilingual Plane.
+        assert_eq!(ts4(r#""\u{e9}""#), r#""\u{e9}""#);
+        assert_eq!(ts3(r#""\u{e9}""#), r#""\u00E9""#);
+        assert_eq!(ts3(r#""\u{FFFF}""#), r#""\uFFFF""#);
+        // Astral plane.
+        assert_eq!(ts4(r#""\u{1F600}""#), r#""\u{1F600}""#);
+        assert_eq!(ts3(r#""\u{1F600}""#), r#""\uD83D\uDE00""#);
+        assert_eq!(ts3(r#""\u{10000}""#), r#""\uD800\uDC00""#);
+        assert_eq!(ts3(r#""\u{10FFFF}""#), r#""\uDBFF\uDFFF""#);
+        // Non-ascii chars which are not escaped are kept.
+        assert_eq!(ts3("\"é😀\""), "\"é😀\"");
+    }
+
+    #[test]
+    fn rust_string_to_ts_raw() {
+        let ts4 = |orig: &str| string_to_ts(orig, &TsMajor::Ts4);
+        // Contents are verbatim, so backslashes and quotes are escaped.
+        assert_eq!(ts4(r#"r"C:\n""#), r#""C:\\n""#);
+        assert_eq!(ts4(r###"r##"say "#hi""##"###), r##""say \"#hi\"""##);
+        assert_eq!(ts4("r\"a\nb\""), r#""a\nb""#);
+        assert_eq!(ts4(r#"br"\u{e9}""#), r#""\\u{e9}""#);
+        assert_eq!(string_to_ts(r#"r"\u{1F600}""#, &TsMajor::Ts3),
+            r#""\\u{1F600}""#);
+    }
+
 }
@@ -1,5 +1,7 @@
 //! A configuration object which controls how Rust is transpiled to TypeScript.
 
+use std::str::FromStr;
+
 /// A configuration object which controls how Rust is transpiled to TypeScript.
 /// 
 /// ### The `to_string()` method
@@ -13,7 +15,8 @@
 /// ```
 /// 
 /// ### Modifying `Config`
-/// Use `rs_edition()`, `strategy()` and `ts_major()` to set the parameters.
+/// Use `rs_edition()`, `strategy()`, `ts_major()`, `use_primitive_types()` and
+/// `strip_trailing_commas()` to set the parameters.
 /// ```
 /// # use opinionated_rust_to_typescript::transpile::config::*;
 /// # use opinionated_rust_to_typescript::transpile::rs_to_ts::*;
@@ -31,20 +34,45 @@
 /// .ts_major(TsMajor::Ts4)
 /// .to_string(),
 ///     "Rust edition 2018, TypeScript 4, Cautious");
+/// assert_eq!(Config::new().use_primitive_types(false).to_string(),
+///     "Latest Rust edition (2018), Latest TypeScript (4), Gungho, \
+///     Wrapper types");
+/// assert_eq!(Config::new().ts_major(TsMajor::Ts3).strip_trailing_commas(true)
+///     .to_string(), "Latest Rust edition (2018), TypeScript 3, Gungho, \
+///     Strip trailing commas");
 /// ```
 /// 
 /// ### The Builder Pattern
 /// 
 /// For more information about the Builder Pattern:
 /// <https://doc.rust-lang.org/1.0.0/style/ownership/builders.html>
-///
+/// 
+/// ### The `Default` trait
+/// `Config::default()` is the same as `Config::new()`, so a `Config` can also
+/// be created with struct update syntax.
+/// ```
+/// # use opinionated_rust_to_typescript::transpile::config::*;
+/// let config = Config { ts_major: TsMajor::Ts3, ..Default::default() };
+/// assert_eq!(config.to_string(),
+///     "Latest Rust edition (2018), TypeScript 3, Gungho");
+/// ```
 pub struct Config {
     /// The edition of Rust that the input code is written in.
     pub rs_edition: RsEdition,
+    /// Whether a trailing comma before a `)`, `]` or `}`, like in `f(a, b,)`,
+    /// should be removed from code which is copied verbatim, like a function
+    /// body. Older JavaScript engines reject some trailing commas, so this is
+    /// useful when targeting `TsMajor::Ts3`. Defaults to `false`.
+    pub strip_trailing_commas: bool,
     /// Which strategy to use when transpiling Rust code into TypeScript.
     pub strategy: Strategy,
     /// The major version of TypeScript that `rs_to_ts` should output.
     pub ts_major: TsMajor,
+    /// Whether types should be TypeScript’s lowercase primitive types, like
+    /// `number`, rather than their wrapper object types, like `Number`. Casts,
+    /// like `x as f64`, always use primitive types, because TypeScript does not
+    /// allow arithmetic on wrapper types. Defaults to `true`.
+    pub use_primitive_types: bool,
 }
 
 impl Config {
@@ -52,8 +80,10 @@ impl Config {
     pub fn new() -> Self {
         Config {
             rs_edition: RsEdition::Latest,
+            strip_trailing_commas: false,
             strategy: Strategy::Gungho,
             ts_major: TsMajor::Latest,
+            use_primitive_types: true,
         }
     }
     /// Overrides the configuration’s default ‘Rust edition’.
@@ -66,11 +96,163 @@ impl Config {
         self.strategy = replacement_value;
         return self;
     }
+    /// Overrides the configuration’s default of keeping trailing commas.
+    pub fn strip_trailing_commas(mut self, replacement_value: bool) -> Self {
+        self.strip_trailing_commas = replacement_value;
+        return self;
+    }
     /// Overrides the configuration’s default ‘TypeScript major-version’.
     pub fn ts_major(mut self, replacement_value: TsMajor) -> Self {
         self.ts_major = replacement_value;
         return self;
     }
+    /// Overrides the configuration’s default of using primitive types.
+    pub fn use_primitive_types(mut self, replacement_value: bool) -> Self {
+        self.use_primitive_types = replacement_value;
+        return self;
+    }
+    /// Creates a Config object from command line arguments.
+    /// 
+    /// The `--edition`, `--strategy`, `--ts` and `--primitive-types` flags
+    /// override the defaults. Each flag’s value can follow it, or be joined to
+    /// it with `=`. Values are parsed case-insensitively by [`RsEdition`],
+    /// [`Strategy`] and [`TsMajor`]’s `from_str()`. `--primitive-types` must be
+    /// `true` or `false`.
+    /// ```
+    /// # use opinionated_rust_to_typescript::transpile::config::Config;
+    /// let args = vec!["--edition".into(), "2015".into(), "--ts=TS3".into()];
+    /// assert_eq!(Config::from_args(&args).unwrap().to_string(),
+    ///     "Rust edition 2015, TypeScript 3, Gungho");
+    /// ```
+    /// 
+    /// ### Returns
+    /// The Config, or a message describing the first unknown flag or value.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Config::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // Split "--flag=value" into its flag and value. Otherwise, the
+            // value is the next argument.
+            let (flag, value) = match arg.find('=') {
+                Some(i) => (&arg[..i], &arg[i+1..]),
+                None => (arg.as_str(), match args.next() {
+                    Some(value) => value.as_str(),
+                    None => return Err(format!("Missing value for {}", arg)),
+                }),
+            };
+            config = match flag {
+                "--edition" => config.rs_edition(value.parse()?),
+                "--strategy" => config.strategy(value.parse()?),
+                "--ts" => config.ts_major(value.parse()?),
+                "--primitive-types" => config.use_primitive_types(
+                    value.parse().map_err(|_| format!("Unknown value {:?} \
+                        for --primitive-types, expected true or false",
+                        value))?),
+                _ => return Err(format!("Unknown flag {}, expected \
+                    --edition, --strategy, --ts or --primitive-types", flag)),
+            };
+        }
+        return Ok(config);
+    }
+
+    /// Merges the settings from a directive, like `edition=2018, ts=4`.
+    /// 
+    /// `rs_to_ts()` finds directives in comments at the start of the input,
+    /// like `//! rs2ts: edition=2018, ts=4, strategy=gungho`, and passes the
+    /// part after `rs2ts:` to this method. The `edition`, `strategy` and `ts`
+    /// keys are parsed like the `--edition`, `--strategy` and `--ts` flags of
+    /// `from_args()`.
+    /// 
+    /// Settings which were set explicitly win over the directive, so a setting
+    /// is only replaced if it still has its default value. That means an
+    /// explicit `Strategy::Gungho` cannot be told apart from the default.
+    /// ```
+    /// # use opinionated_rust_to_typescript::transpile::config::*;
+    /// let config = Config::new().merge_directive("edition=2018, ts=3");
+    /// assert_eq!(config.unwrap().to_string(),
+    ///     "Rust edition 2018, TypeScript 3, Gungho");
+    /// let config = Config::new().ts_major(TsMajor::Ts4)
+    ///     .merge_directive("ts=3");
+    /// assert_eq!(config.unwrap().to_string(),
+    ///     "Latest Rust edition (2018), TypeScript 4, Gungho");
+    /// ```
+    /// 
+    /// ### Returns
+    /// The merged Config, or a message describing the first unknown key or
+    /// value.
+    pub fn merge_directive(mut self, directive: &str) -> Result<Self, String> {
+        for setting in directive.split(',') {
+            // Ignore empty settings, like after a trailing comma.
+            let setting = setting.trim();
+            if setting.is_empty() { continue }
+            let (key, value) = match setting.find('=') {
+                Some(i) => (setting[..i].trim(), setting[i+1..].trim()),
+                None => return Err(format!("Missing value for {}", setting)),
+            };
+            match key {
+                "edition" => {
+                    let rs_edition = value.parse()?;
+                    if self.rs_edition == RsEdition::Latest {
+                        self.rs_edition = rs_edition;
+                    }
+                },
+                "strategy" => {
+                    let strategy = value.parse()?;
+                    if self.strategy == Strategy::Gungho {
+                        self.strategy = strategy;
+                    }
+                },
+                "ts" => {
+                    let ts_major = value.parse()?;
+                    if self.ts_major == TsMajor::Latest {
+                        self.ts_major = ts_major;
+                    }
+                },
+                _ => return Err(format!("Unknown directive key {}, expected \
+                    edition, strategy or ts", key)),
+            }
+        }
+        return Ok(self);
+    }
+
+    /// Lists every setting which is not implemented yet.
+    /// 
+    /// `rs_to_ts()` reports each of these as an error, so that all of them
+    /// can be fixed in one pass.
+    /// ```
+    /// # use opinionated_rust_to_typescript::transpile::config::*;
+    /// assert_eq!(Config::new().validate().len(), 0);
+    /// assert_eq!(Config::new().strategy(Strategy::Cautious).validate(),
+    ///     vec!["Strategy::Cautious is not implemented yet"]);
+    /// ```
+    pub fn validate(&self) -> Vec<&'static str> {
+        self.validate_settings().into_iter()
+            .map(|(_, message)| message).collect()
+    }
+
+    /// Lists every setting which is not implemented yet, and which it is.
+    /// 
+    /// This is `validate()`, but each message is paired with the [`Setting`]
+    /// it is about, so callers do not need to parse the message.
+    /// ```
+    /// # use opinionated_rust_to_typescript::transpile::config::*;
+    /// assert!(Config::new().strategy(Strategy::Cautious).validate_settings()
+    ///     == vec![(Setting::Strategy,
+    ///         "Strategy::Cautious is not implemented yet")]);
+    /// ```
+    pub fn validate_settings(&self) -> Vec<(Setting, &'static str)> {
+        let mut settings = vec![];
+        if self.rs_edition == RsEdition::Rs2015 {
+            settings.push((Setting::RsEdition,
+                "RsEdition::Rs2015 is not implemented yet"));
+        }
+        if self.strategy == Strategy::Cautious {
+            settings.push((Setting::Strategy,
+                "Strategy::Cautious is not implemented yet"));
+        }
+        return settings;
+    }
+
     /// Displays the configuration in a human-readable CSV format.
     pub fn to_string(&self) -> String {
         let mut out: String = "".into();
@@ -88,15 +270,25 @@ impl Config {
             Strategy::Cautious => "Cautious",
             Strategy::Gungho => "Gungho",
         }.into());
+        if ! self.use_primitive_types { out.push_str(", Wrapper types") }
+        if self.strip_trailing_commas {
+            out.push_str(", Strip trailing commas");
+        }
         return out;
     }
 }
 
+impl Default for Config {
+    /// Creates a default Config object, like `Config::new()` does.
+    fn default() -> Self { Config::new() }
+}
+
 
 /// The edition of Rust that the input code is written in.
-#[derive(PartialEq)]
+#[derive(Default,PartialEq)]
 pub enum RsEdition {
-    /// The most recent Rust edition that this library supports.
+    /// The most recent Rust edition that this library supports. The default.
+    #[default]
     Latest,
     /// _`Rs2015` is a placeholder. This edition is currently not supported._
     Rs2015,
@@ -104,8 +296,31 @@ pub enum RsEdition {
     Rs2018,
 }
 
+impl FromStr for RsEdition {
+    type Err = String;
+    /// Parses "latest", "2015", "rs2015", "2018" or "rs2018", in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "latest" => Ok(RsEdition::Latest),
+            "2015" | "rs2015" => Ok(RsEdition::Rs2015),
+            "2018" | "rs2018" => Ok(RsEdition::Rs2018),
+            _ => Err(format!("Unknown Rust edition {:?}, expected \
+                latest, 2015 or 2018", s)),
+        }
+    }
+}
+
+/// One of the settings in a `Config`, as reported by `validate_settings()`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Setting {
+    /// The edition of Rust, set by `Config::rs_edition()`.
+    RsEdition,
+    /// The transpilation strategy, set by `Config::strategy()`.
+    Strategy,
+}
+
 /// Which strategy to use when transpiling Rust code into TypeScript.
-#[derive(PartialEq)]
+#[derive(Default,PartialEq)]
 pub enum Strategy {
     /// __Favours safety over readability.__
     /// 
@@ -121,18 +336,189 @@ pub enum Strategy {
     /// Looks very similar to the input Rust code, and attempts to preserve line
     /// numbers.
     /// 
-    /// _Currently the only strategy which `rs_to_ts()` supports._
+    /// _Currently the only strategy which `rs_to_ts()` supports, and the
+    /// default._
+    #[default]
     Gungho,
 }
 
+impl FromStr for Strategy {
+    type Err = String;
+    /// Parses "cautious" or "gungho", in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cautious" => Ok(Strategy::Cautious),
+            "gungho" => Ok(Strategy::Gungho),
+            _ => Err(format!("Unknown strategy {:?}, expected \
+                cautious or gungho", s)),
+        }
+    }
+}
+
 /// The major version of TypeScript that `rs_to_ts` should output.
-#[derive(PartialEq)]
+#[derive(Clone,Copy,Default,PartialEq)]
 pub enum TsMajor {
     /// The most recent TypeScript major-version that this library supports.
+    /// The default.
+    #[default]
     Latest,
-    /// _`Ts3` is a placeholder. This version is currently not supported._
+    /// TypeScript 3, which has no `bigint` literals. The output is the same as
+    /// TypeScript 4, except that huge integers lose precision.
     Ts3,
-    /// Currently, only TypeScript 4 is supported.
+    /// TypeScript 4.
     Ts4,
 }
 
+impl FromStr for TsMajor {
+    type Err = String;
+    /// Parses "latest", "3", "ts3", "4" or "ts4", in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "latest" => Ok(TsMajor::Latest),
+            "3" | "ts3" => Ok(TsMajor::Ts3),
+            "4" | "ts4" => Ok(TsMajor::Ts4),
+            _ => Err(format!("Unknown TypeScript version {:?}, expected \
+                latest, 3 or 4", s)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Converts string slices to the `String`s which `from_args()` expects.
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn rs_edition_from_str() {
+        assert!("latest".parse::<RsEdition>() == Ok(RsEdition::Latest));
+        assert!("2015".parse::<RsEdition>() == Ok(RsEdition::Rs2015));
+        assert!("RS2015".parse::<RsEdition>() == Ok(RsEdition::Rs2015));
+        assert!("2018".parse::<RsEdition>() == Ok(RsEdition::Rs2018));
+        assert!("Rs2018".parse::<RsEdition>() == Ok(RsEdition::Rs2018));
+        assert!("2021".parse::<RsEdition>() == Err("Unknown Rust edition \
+            \"2021\", expected latest, 2015 or 2018".into()));
+    }
+
+    #[test]
+    fn strategy_from_str() {
+        assert!("Gungho".parse::<Strategy>() == Ok(Strategy::Gungho));
+        assert!("gungho".parse::<Strategy>() == Ok(Strategy::Gungho));
+        assert!("GUNGHO".parse::<Strategy>() == Ok(Strategy::Gungho));
+        assert!("cautious".parse::<Strategy>() == Ok(Strategy::Cautious));
+        assert!("".parse::<Strategy>() == Err("Unknown strategy \"\", \
+            expected cautious or gungho".into()));
+    }
+
+    #[test]
+    fn ts_major_from_str() {
+        assert!("LATEST".parse::<TsMajor>() == Ok(TsMajor::Latest));
+        assert!("3".parse::<TsMajor>() == Ok(TsMajor::Ts3));
+        assert!("ts3".parse::<TsMajor>() == Ok(TsMajor::Ts3));
+        assert!("4".parse::<TsMajor>() == Ok(TsMajor::Ts4));
+        assert!("Ts4".parse::<TsMajor>() == Ok(TsMajor::Ts4));
+        assert!("ts 4".parse::<TsMajor>().is_err());
+    }
+
+    #[test]
+    fn config_from_args_round_trip() {
+        // No args gives the default Config.
+        assert_eq!(Config::from_args(&[]).unwrap().to_string(),
+            Config::new().to_string());
+        // Every flag, in both forms.
+        let config = Config::from_args(&args(&["--edition", "rs2015",
+            "--strategy=Cautious", "--ts", "3"])).unwrap();
+        assert_eq!(config.to_string(),
+            "Rust edition 2015, TypeScript 3, Cautious");
+        // Primitive types can be turned off, and on again.
+        let config = Config::from_args(&args(&["--primitive-types",
+            "false"])).unwrap();
+        assert!(! config.use_primitive_types);
+        assert_eq!(config.to_string(),
+            "Latest Rust edition (2018), Latest TypeScript (4), Gungho, \
+            Wrapper types");
+        let config = Config::from_args(&args(&["--primitive-types=false",
+            "--primitive-types=true"])).unwrap();
+        assert!(config.use_primitive_types);
+        // A later flag overrides an earlier one.
+        let config = Config::from_args(&args(&["--edition=2015",
+            "--edition=2018", "--ts=latest"])).unwrap();
+        assert_eq!(config.to_string(),
+            "Rust edition 2018, Latest TypeScript (4), Gungho");
+    }
+
+    #[test]
+    fn config_default_matches_new() {
+        assert_eq!(Config::default().to_string(), Config::new().to_string());
+        assert!(RsEdition::default() == RsEdition::Latest);
+        assert!(Strategy::default() == Strategy::Gungho);
+        assert!(TsMajor::default() == TsMajor::Latest);
+        assert!(! Config::default().strip_trailing_commas);
+        // Struct update syntax.
+        let config = Config { strategy: Strategy::Cautious,
+            ..Default::default() };
+        assert_eq!(config.to_string(),
+            "Latest Rust edition (2018), Latest TypeScript (4), Cautious");
+    }
+
+    #[test]
+    fn config_merge_directive() {
+        // Every key, with or without spaces, and a trailing comma.
+        let config = Config::new()
+            .merge_directive(" edition = 2015,ts=3 , strategy=CAUTIOUS,")
+            .unwrap();
+        assert_eq!(config.to_string(),
+            "Rust edition 2015, TypeScript 3, Cautious");
+        // An empty directive changes nothing.
+        let config = Config::new().merge_directive("").unwrap();
+        assert_eq!(config.to_string(), Config::new().to_string());
+        // Explicit settings win.
+        let config = Config::new().rs_edition(RsEdition::Rs2018)
+            .strategy(Strategy::Cautious)
+            .merge_directive("edition=2015, strategy=gungho, ts=4").unwrap();
+        assert_eq!(config.to_string(),
+            "Rust edition 2018, TypeScript 4, Cautious");
+        // Errors.
+        assert_eq!(Config::new().merge_directive("ts").err().unwrap(),
+            "Missing value for ts");
+        assert_eq!(Config::new().merge_directive("ts=5").err().unwrap(),
+            "Unknown TypeScript version \"5\", expected latest, 3 or 4");
+        assert_eq!(Config::new().merge_directive("tsx=4").err().unwrap(),
+            "Unknown directive key tsx, expected edition, strategy or ts");
+    }
+
+    #[test]
+    fn config_validate() {
+        assert_eq!(Config::new().validate().len(), 0);
+        assert_eq!(Config::new().rs_edition(RsEdition::Rs2018)
+            .ts_major(TsMajor::Ts4).validate().len(), 0);
+        assert_eq!(Config::new().ts_major(TsMajor::Ts3).validate().len(), 0);
+        assert_eq!(Config::new().rs_edition(RsEdition::Rs2015)
+            .strategy(Strategy::Cautious).ts_major(TsMajor::Ts3).validate(),
+            vec!["RsEdition::Rs2015 is not implemented yet",
+                 "Strategy::Cautious is not implemented yet"]);
+        // Each message comes with the setting it is about.
+        let settings: Vec<Setting> = Config::new()
+            .rs_edition(RsEdition::Rs2015).strategy(Strategy::Cautious)
+            .validate_settings().into_iter().map(|(s, _)| s).collect();
+        assert_eq!(settings, vec![Setting::RsEdition, Setting::Strategy]);
+    }
+
+    #[test]
+    fn config_from_args_errors() {
+        assert_eq!(Config::from_args(&args(&["--edition"])).err().unwrap(),
+            "Missing value for --edition");
+        assert_eq!(Config::from_args(&args(&["--ts=5"])).err().unwrap(),
+            "Unknown TypeScript version \"5\", expected latest, 3 or 4");
+        assert_eq!(Config::from_args(&args(&["--tsx", "4"])).err().unwrap(),
+            "Unknown flag --tsx, expected --edition, --strategy, --ts or \
+            --primitive-types");
+        assert_eq!(Config::from_args(&args(&["--primitive-types=no"])).err()
+            .unwrap(), "Unknown value \"no\" for --primitive-types, \
+            expected true or false");
+    }
+}
@@ -1,21 +1,47 @@
 //! Used for describing errors found during transpilation.
 
+use std::{borrow::Cow,error,fmt};
 
 /// Categories of transpilation errors.
+#[derive(Debug)]
 pub enum TranspileErrorKind {
+    /// A type which cannot be transpiled yet, so TypeScript’s `any` is used
+    /// instead. Reported as a warning, not an error.
+    AnyType,
     /// The `opinionated_rust_to_typescript` library does not currently
     /// implement the transpilation specified in `config`.
     ConfigNotImplemented,
+    /// A number literal which cannot be transpiled, eg it is malformed.
+    InvalidNumber,
+    /// A number which TypeScript cannot represent exactly. Reported as a
+    /// warning, not an error.
+    LossOfPrecision,
+    /// A likely mistake, like the uppercase `X` in `0XAB`, with a suggested
+    /// fix. Reported as a warning, not an error.
+    Hint,
     /// Fallback, when no other error fits.
     UnknownError,
+    /// Valid looking Rust code, which the transpiler cannot handle yet.
+    UnsupportedSyntax,
+    /// A multiline comment which has no closing `*/`.
+    UnterminatedComment,
+    /// A string which has no closing quote.
+    UnterminatedString,
 }
 
 impl TranspileErrorKind {
     /// 
     pub fn to_string(&self) -> &str {
         match self {
+            Self::AnyType => "AnyType",
             Self::ConfigNotImplemented => "ConfigNotImplemented",
+            Self::Hint => "Hint",
+            Self::InvalidNumber => "InvalidNumber",
+            Self::LossOfPrecision => "LossOfPrecision",
             Self::UnknownError => "UnknownError",
+            Self::UnsupportedSyntax => "UnsupportedSyntax",
+            Self::UnterminatedComment => "UnterminatedComment",
+            Self::UnterminatedString => "UnterminatedString",
         }
     }
 }
@@ -25,6 +51,7 @@ impl TranspileErrorKind {
 /// Many errors may be encountered while transpiling a given Rust program. These
 /// are converted into `TranspileError`s, and recorded in the `errors` vector of
 /// the [`TranspileResult`](super::result::TranspileResult).
+#[derive(Debug)]
 pub struct TranspileError {
     /// The character position within the line where the error occurred, or 0.
     pub column: usize,
@@ -32,6 +59,129 @@ pub struct TranspileError {
     pub kind: TranspileErrorKind,
     /// The line number of the Rust code which caused the error, or 0.
     pub line_number: usize,
-    /// A short explanation of the error, to help a developer debug it.
-    pub message: &'static str,
+    /// A short explanation of the error, to help a developer debug it. This is
+    /// usually a static string, but some messages include part of the input.
+    pub message: Cow<'static, str>,
+}
+
+impl fmt::Display for TranspileError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        // An `any` type, a hint and a loss of precision are only ever warnings.
+        let level = match self.kind {
+            TranspileErrorKind::AnyType
+            | TranspileErrorKind::Hint
+            | TranspileErrorKind::LossOfPrecision => "warning",
+            _ => "error",
+        };
+        write!(fmt, "{}[{}] at {}:{}: {}", level, self.kind.to_string(),
+            self.line_number, self.column, self.message)
+    }
+}
+
+impl error::Error for TranspileError {}
+
+/// Every error found while transpiling, as a single error type.
+/// 
+/// Returned by [`transpile_to_string()`](super::rs_to_ts::transpile_to_string),
+/// so that application code can use `?`, eg in a function which returns
+/// `Result<_, Box<dyn std::error::Error>>`. The `Display` output lists each
+/// error on its own line.
+/// ```
+/// # use opinionated_rust_to_typescript::transpile::error::*;
+/// let errors = TranspileErrors(vec![TranspileError {
+///     column: 2,
+///     kind: TranspileErrorKind::UnsupportedSyntax,
+///     line_number: 1,
+///     message: "Malformed const declaration".into(),
+/// }]);
+/// assert_eq!(errors.to_string(),
+///     "error[UnsupportedSyntax] at 1:2: Malformed const declaration");
+/// ```
+#[derive(Debug)]
+pub struct TranspileErrors(pub Vec<TranspileError>);
+
+impl fmt::Display for TranspileErrors {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            // A newline goes between errors, but not after the last one.
+            if i != 0 { writeln!(fmt)? }
+            write!(fmt, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for TranspileErrors {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpile_error_to_string_as_expected() {
+        let error = TranspileError {
+            column: 12,
+            kind: TranspileErrorKind::ConfigNotImplemented,
+            line_number: 3,
+            message: "RsEdition::Rs2015 is not implemented yet".into(),
+        };
+        assert_eq!(error.to_string(), "error[ConfigNotImplemented] at 3:12: \
+            RsEdition::Rs2015 is not implemented yet");
+        let error = TranspileError {
+            column: 0,
+            kind: TranspileErrorKind::UnknownError,
+            line_number: 0,
+            message: "".into(),
+        };
+        assert_eq!(error.to_string(), "error[UnknownError] at 0:0: ");
+        let error = TranspileError {
+            column: 5,
+            kind: TranspileErrorKind::LossOfPrecision,
+            line_number: 1,
+            message: "Integer is larger than Number.MAX_SAFE_INTEGER".into(),
+        };
+        assert_eq!(error.to_string(), "warning[LossOfPrecision] at 1:5: \
+            Integer is larger than Number.MAX_SAFE_INTEGER");
+        let error = TranspileError {
+            column: 8,
+            kind: TranspileErrorKind::AnyType,
+            line_number: 2,
+            message: "Unsupported generic type, using `any`".into(),
+        };
+        assert_eq!(error.to_string(), "warning[AnyType] at 2:8: \
+            Unsupported generic type, using `any`");
+    }
+
+    #[test]
+    fn transpile_errors_as_expected() {
+        assert_eq!(TranspileErrors(vec![]).to_string(), "");
+        let errors = TranspileErrors(vec![
+            TranspileError {
+                column: 5,
+                kind: TranspileErrorKind::UnsupportedSyntax,
+                line_number: 2,
+                message: "Malformed const declaration".into(),
+            },
+            TranspileError {
+                column: 17,
+                kind: TranspileErrorKind::InvalidNumber,
+                line_number: 4,
+                message: "Integer is too large".into(),
+            },
+        ]);
+        assert_eq!(errors.to_string(),
+            "error[UnsupportedSyntax] at 2:5: Malformed const declaration\n\
+             error[InvalidNumber] at 4:17: Integer is too large");
+        // Integrates with `Box<dyn Error>`, so application code can use `?`.
+        fn fails(errors: TranspileErrors) -> Result<(), Box<dyn error::Error>> {
+            Err(errors)?;
+            Ok(())
+        }
+        let boxed = fails(errors).unwrap_err();
+        let message = boxed.to_string();
+        assert!(message.contains("Malformed const declaration"));
+        assert!(message.contains("Integer is too large"));
+        assert!(boxed.downcast_ref::<TranspileErrors>().is_some());
+    }
 }
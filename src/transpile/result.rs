@@ -1,5 +1,8 @@
 //! Used for returning the result of transpilation.
 
+use std::borrow::Cow;
+use std::io::{self,Write};
+
 use super::error::*;
 
 /// Used for returning the result of transpilation.
@@ -13,19 +16,29 @@ pub struct TranspileResult {
     /// If there are no transpilation errors, this vector will be empty.
     pub errors: Vec<TranspileError>,
     /// Lines of TypeScript code
-    pub main_lines: Vec<&'static str>,
+    pub main_lines: Vec<String>,
     /// Should be added before `main`, typically `;r$t$();`
     pub main_section_begins: &'static str,
     /// Should be added after `main`
     pub main_section_ends: &'static str,
+    /// The whitespace which indented each of the `main_lines` in the original
+    /// Rust code, if it was captured. `to_string()` adds it to the start of the
+    /// line. This vector can be shorter than `main_lines`.
+    pub original_whitespace: Vec<String>,
     /// For example, `String.prototype.len=function(){return this.length}`
     pub polyfill_lines: Vec<&'static str>,
     /// Typically `;function r$t$(){...};`
     pub polyfill_section_begins: &'static str,
     /// Typically `};`
     pub polyfill_section_ends: &'static str,
-    /// For example, `interface String { len(): Number }`
-    pub type_lines: Vec<&'static str>,
+    /// For example, `interface String { len(): number }`
+    pub type_lines: Vec<String>,
+    /// Problems which do not stop the TypeScript from running, but which may
+    /// change its behaviour. They are not included in `errors`.
+    pub warnings: Vec<TranspileError>,
+    // How many `main_lines` `push_normalised_main_line()` has scanned, and
+    // whether they end inside a `/* */` comment. So each line is scanned once.
+    comment_scan: (usize, bool),
 }
 
 impl TranspileResult {
@@ -37,61 +50,567 @@ impl TranspileResult {
             main_lines: vec![],
             main_section_begins: "",
             main_section_ends: "",
+            original_whitespace: vec![],
             polyfill_lines: vec![],
             polyfill_section_begins: "",
             polyfill_section_ends: "",
+            warnings: vec![],
+            comment_scan: (0, false),
         }
     }
 
     /// Adds a [`ConfigNotImplemented`](super::error::TranspileErrorKind)
     /// [`TranspileError`](super::error::TranspileError) to the `error` vector.
     pub fn push_config_not_implemented_error(
+        self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        let kind = TranspileErrorKind::ConfigNotImplemented;
+        self.push_error(column, kind, line_number, message)
+    }
+
+    /// Adds an [`InvalidNumber`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `error` vector.
+    pub fn push_invalid_number_error(
+        self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        let kind = TranspileErrorKind::InvalidNumber;
+        self.push_error(column, kind, line_number, message)
+    }
+
+    /// Adds an [`UnknownError`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `error` vector.
+    pub fn push_unknown_error(
+        self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        let kind = TranspileErrorKind::UnknownError;
+        self.push_error(column, kind, line_number, message)
+    }
+
+    /// Adds an [`UnsupportedSyntax`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `error` vector.
+    /// 
+    /// Unlike the other methods, `message` can be a `String`, so that it can
+    /// describe part of the input.
+    pub fn push_unsupported_syntax_error(
+        self,
+        column: usize,
+        line_number: usize,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        let kind = TranspileErrorKind::UnsupportedSyntax;
+        self.push_error(column, kind, line_number, message)
+    }
+
+    /// Adds an [`UnterminatedComment`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `error` vector.
+    pub fn push_unterminated_comment_error(
+        self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        let kind = TranspileErrorKind::UnterminatedComment;
+        self.push_error(column, kind, line_number, message)
+    }
+
+    /// Adds an [`UnterminatedString`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `error` vector.
+    pub fn push_unterminated_string_error(
+        self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        let kind = TranspileErrorKind::UnterminatedString;
+        self.push_error(column, kind, line_number, message)
+    }
+
+    /// Adds an [`AnyType`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `warnings`
+    /// vector.
+    pub fn push_any_type_warning(
         mut self,
         column: usize,
         line_number: usize,
         message: &'static str,
+    ) -> Self {
+        self.warnings.push(TranspileError {
+            column,
+            kind: TranspileErrorKind::AnyType,
+            line_number,
+            message: message.into(),
+        });
+        return self;
+    }
+
+    /// Adds a [`Hint`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `warnings`
+    /// vector.
+    pub fn push_hint_warning(
+        mut self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        self.warnings.push(TranspileError {
+            column,
+            kind: TranspileErrorKind::Hint,
+            line_number,
+            message: message.into(),
+        });
+        return self;
+    }
+
+    /// Adds a [`LossOfPrecision`](super::error::TranspileErrorKind)
+    /// [`TranspileError`](super::error::TranspileError) to the `warnings`
+    /// vector.
+    pub fn push_loss_of_precision_warning(
+        mut self,
+        column: usize,
+        line_number: usize,
+        message: &'static str,
+    ) -> Self {
+        self.warnings.push(TranspileError {
+            column,
+            kind: TranspileErrorKind::LossOfPrecision,
+            line_number,
+            message: message.into(),
+        });
+        return self;
+    }
+
+    // Adds a `TranspileError` of any kind to the `error` vector.
+    fn push_error(
+        mut self,
+        column: usize,
+        kind: TranspileErrorKind,
+        line_number: usize,
+        message: impl Into<Cow<'static, str>>,
     ) -> Self {
         self.errors.push(TranspileError {
             column,
-            kind: TranspileErrorKind::ConfigNotImplemented,
+            kind,
             line_number,
-            message,
+            message: message.into(),
         });
         return self;
     }
 
     /// Adds a line to the `main_lines` vector.
     pub fn push_main_line(
+        mut self,
+        line: &str,
+    ) -> Self {
+        self.main_lines.push(line.into());
+        return self;
+    }
+
+    /// Adds a line to the `main_lines` vector, and the whitespace which
+    /// indented it in the original Rust code to `original_whitespace`.
+    pub fn push_indented_main_line(
+        mut self,
+        whitespace: &str,
+        line: &str,
+    ) -> Self {
+        // Lines which were pushed without any whitespace are not indented.
+        self.original_whitespace.resize(self.main_lines.len(), String::new());
+        self.original_whitespace.push(whitespace.into());
+        self.push_main_line(line)
+    }
+
+    /// Adds a line to the `main_lines` vector, after passing it through
+    /// [`normalise_ts_line()`], so that it ends with a semicolon if needed. A
+    /// line inside a `/* */` comment, like ` * note`, is not a statement, so
+    /// only its trailing whitespace is removed.
+    pub fn push_normalised_main_line(
+        mut self,
+        line: &str,
+    ) -> Self {
+        // Only the lines which were added since the last call are scanned.
+        let (scanned, in_comment) = self.comment_scan;
+        let in_comment = self.main_lines.iter().skip(scanned)
+            .fold(in_comment, |in_comment, l| scan_ts_line(l, in_comment).1);
+        self.comment_scan = (self.main_lines.len(), in_comment);
+        // Any statement after the end of the comment is normalised.
+        let line = match line.find("*/") {
+            Some(pos) if in_comment => format!("{}{}",
+                &line[..pos+2], normalise_ts_line(&line[pos+2..])),
+            None if in_comment => line.trim_end().into(),
+            _ => normalise_ts_line(line),
+        };
+        self.push_main_line(&line)
+    }
+
+    /// Adds a line to the `polyfill_lines` vector, unless it is already there.
+    pub fn push_polyfill_line(
         mut self,
         line: &'static str,
     ) -> Self {
-        self.main_lines.push(line);
+        if ! self.polyfill_lines.contains(&line) {
+            self.polyfill_lines.push(line);
+        }
+        return self;
+    }
+
+    /// Adds a line to the `type_lines` vector, unless it is already there.
+    pub fn push_type_line(
+        mut self,
+        line: &str,
+    ) -> Self {
+        if ! self.type_lines.iter().any(|type_line| type_line == line) {
+            self.type_lines.push(line.into());
+        }
         return self;
     }
 
+    /// Formats every error in the `errors` vector, one per line.
+    pub fn format_errors(&self) -> String {
+        let errors: Vec<String> = self.errors.iter()
+            .map(|error| error.to_string()).collect();
+        return errors.join("\n");
+    }
+
     /// Concatenates `TranspileResult` to run as standalone TypeScript.
+    /// 
+    /// The output is what `write_to()` streams. The length is counted first,
+    /// so the `String` is only allocated once. Each of the `main_lines` is
+    /// indented by its `original_whitespace`, if any.
     pub fn to_string(&self) -> String {
-        let mut out: String = "".into();
+        let len: usize = self.main_section_begins.len()
+            + self.main_lines.iter().map(|l| l.len() + 1).sum::<usize>()
+            + self.original_whitespace.iter().map(|w| w.len()).sum::<usize>()
+            + self.main_section_ends.len()
+            + self.polyfill_section_begins.len()
+            + self.polyfill_lines.iter().map(|l| l.len()).sum::<usize>()
+            + self.polyfill_section_ends.len()
+            + self.type_lines.iter().map(|l| l.len()).sum::<usize>();
+        let mut out = Vec::with_capacity(len);
+        // Writing to a `Vec` cannot fail, and every part is valid UTF-8.
+        self.write_to(&mut out).unwrap();
+        return String::from_utf8(out).unwrap();
+    }
 
-        // Add the main section.
-        out.push_str(&self.main_section_begins.to_string());
-        for main_line in &self.main_lines {
-            out.push_str(&main_line.to_string());
+    /// Streams the same TypeScript as `to_string()`, without building a
+    /// `String`, which suits large input code.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        // Write the main section.
+        w.write_all(self.main_section_begins.as_bytes())?;
+        // Each main line ends with a newline, so that an inline comment does
+        // not swallow the next line, and line numbers are preserved.
+        for (i, main_line) in self.main_lines.iter().enumerate() {
+            if let Some(whitespace) = self.original_whitespace.get(i) {
+                w.write_all(whitespace.as_bytes())?;
+            }
+            w.write_all(main_line.as_bytes())?;
+            w.write_all(b"\n")?;
         }
-        out.push_str(&self.main_section_ends.to_string());
+        w.write_all(self.main_section_ends.as_bytes())?;
 
-        // Add the polyfill section.
-        out.push_str(&self.polyfill_section_begins.to_string());
+        // Write the polyfill section.
+        w.write_all(self.polyfill_section_begins.as_bytes())?;
         for polyfill_line in &self.polyfill_lines {
-            out.push_str(&polyfill_line.to_string());
+            w.write_all(polyfill_line.as_bytes())?;
         }
-        out.push_str(&self.polyfill_section_ends.to_string());
+        w.write_all(self.polyfill_section_ends.as_bytes())?;
 
-        // Add the types.
+        // Write the types.
         for type_line in &self.type_lines {
-            out.push_str(&type_line.to_string());
+            w.write_all(type_line.as_bytes())?;
         }
 
-        return out;
+        return Ok(());
+    }
+}
+
+/// Makes sure a line of TypeScript ends with a semicolon, where needed.
+/// 
+/// Rust statements which end without a semicolon can confuse TypeScript’s
+/// automatic semicolon insertion, so a semicolon is appended to a statement.
+/// Trailing whitespace is removed, but indentation is kept.
+/// 
+/// The line is assumed not to begin inside a `/* */` comment, so a line like
+/// `*gen()` is a statement.
+/// 
+/// ### Arguments
+/// * `line` A line of TypeScript, without a trailing newline
+/// 
+/// ### Returns
+/// `line` unchanged, apart from trailing whitespace, if it is blank or just a
+/// comment, or if it already ends with `;`, `{` or `}`. Otherwise, `line` with
+/// a semicolon after the statement, and before any trailing comment.
+/// ```
+/// # use opinionated_rust_to_typescript::transpile::result::normalise_ts_line;
+/// assert_eq!(normalise_ts_line("const X = 4"), "const X = 4;");
+/// assert_eq!(normalise_ts_line("const X = 4 // four"),
+///     "const X = 4; // four");
+/// assert_eq!(normalise_ts_line("function f() {"), "function f() {");
+/// ```
+pub fn normalise_ts_line(line: &str) -> String {
+    let line = line.trim_end();
+    let start = line.trim_start();
+    // Blank lines and comment-only lines are not statements.
+    if start.is_empty() || start.starts_with("//") || start.starts_with("/*") {
+        return line.into()
+    }
+    // The statement ends where a trailing comment, if any, begins.
+    let code = match scan_ts_line(line, false).0 {
+        Some(comment_pos) => &line[..comment_pos],
+        None => line,
+    };
+    let statement = code.trim_end();
+    // Lines which already end a statement or open or close a block.
+    match statement.chars().last() {
+        Some(';') | Some('{') | Some('}') => line.into(),
+        _ => format!("{};{}", statement, &line[statement.len()..]),
+    }
+}
+
+// Scans a line of TypeScript, which begins inside a `/* */` comment if
+// `in_comment` is set. Returns the position of a comment which runs to the end
+// of the line, like `// a` or an unclosed `/* a`, if any, and whether the line
+// ends inside a `/* */` comment. Comment markers inside strings, like the `//`
+// in `"http://"`, are ignored.
+fn scan_ts_line(line: &str, mut in_comment: bool) -> (Option<usize>, bool) {
+    let bytes = line.as_bytes();
+    let mut comment_pos = None;
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let pair = bytes.get(i..i+2);
+        if in_comment {
+            if pair == Some(b"*/") {
+                in_comment = false;
+                comment_pos = None;
+                i += 1;
+            }
+        } else if let Some(q) = quote {
+            // A backslash escapes the next char, which may be the quote.
+            if bytes[i] == b'\\' { i += 1 }
+            else if bytes[i] == q { quote = None }
+        } else if pair == Some(b"//") {
+            return (Some(i), false)
+        } else if pair == Some(b"/*") {
+            in_comment = true;
+            comment_pos = Some(i);
+            i += 1;
+        } else if matches!(bytes[i], b'"' | b'\'' | b'`') {
+            quote = Some(bytes[i])
+        }
+        i += 1;
+    }
+    (comment_pos, in_comment)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the kind, column, line number and message of the only error.
+    fn only_error(result: &TranspileResult) -> (&str, usize, usize, &str) {
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        let message: &str = &error.message;
+        (error.kind.to_string(), error.column, error.line_number, message)
+    }
+
+    #[test]
+    fn push_error_methods_as_expected() {
+        let result = TranspileResult::new()
+            .push_config_not_implemented_error(1, 2, "a");
+        assert_eq!(only_error(&result), ("ConfigNotImplemented", 1, 2, "a"));
+        let result = TranspileResult::new()
+            .push_invalid_number_error(3, 4, "b");
+        assert_eq!(only_error(&result), ("InvalidNumber", 3, 4, "b"));
+        let result = TranspileResult::new()
+            .push_unknown_error(5, 6, "c");
+        assert_eq!(only_error(&result), ("UnknownError", 5, 6, "c"));
+        let result = TranspileResult::new()
+            .push_unsupported_syntax_error(7, 8, "d");
+        assert_eq!(only_error(&result), ("UnsupportedSyntax", 7, 8, "d"));
+        let result = TranspileResult::new()
+            .push_unterminated_comment_error(9, 10, "e");
+        assert_eq!(only_error(&result), ("UnterminatedComment", 9, 10, "e"));
+        let result = TranspileResult::new()
+            .push_unterminated_string_error(11, 12, "f");
+        assert_eq!(only_error(&result), ("UnterminatedString", 11, 12, "f"));
+    }
+
+    #[test]
+    fn push_loss_of_precision_warning_as_expected() {
+        let result = TranspileResult::new()
+            .push_loss_of_precision_warning(1, 2, "a");
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].to_string(),
+            "warning[LossOfPrecision] at 2:1: a");
+    }
+
+    #[test]
+    fn push_any_type_warning_as_expected() {
+        let result = TranspileResult::new()
+            .push_any_type_warning(3, 4, "b");
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].to_string(),
+            "warning[AnyType] at 4:3: b");
+    }
+
+    #[test]
+    fn push_hint_warning_as_expected() {
+        let result = TranspileResult::new()
+            .push_hint_warning(5, 6, "c");
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].to_string(), "warning[Hint] at 6:5: c");
+    }
+
+    #[test]
+    fn push_polyfill_and_type_lines_once() {
+        let result = TranspileResult::new()
+            .push_polyfill_line("a")
+            .push_type_line("b")
+            .push_polyfill_line("a")
+            .push_type_line("c")
+            .push_type_line("b");
+        assert_eq!(result.polyfill_lines, vec!["a"]);
+        assert_eq!(result.type_lines, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn normalise_ts_line_as_expected() {
+        // Statements get a semicolon.
+        assert_eq!(normalise_ts_line("const X = 4"), "const X = 4;");
+        assert_eq!(normalise_ts_line("  f(x)  "), "  f(x);"); // indented
+        assert_eq!(normalise_ts_line("const X = 4;"), "const X = 4;");
+        // Blocks are unchanged.
+        assert_eq!(normalise_ts_line("function f() {"), "function f() {");
+        assert_eq!(normalise_ts_line("}"), "}");
+        // Blank and comment-only lines are unchanged.
+        assert_eq!(normalise_ts_line("// note"), "// note");
+        assert_eq!(normalise_ts_line("  /* note */"), "  /* note */");
+        assert_eq!(normalise_ts_line(""), "");
+        assert_eq!(normalise_ts_line("   "), "");
+        // The semicolon goes before a trailing comment, but not a `//` string.
+        assert_eq!(normalise_ts_line("const X = 4 // four"),
+            "const X = 4; // four");
+        assert_eq!(normalise_ts_line("  f() // a;  "), "  f(); // a;");
+        assert_eq!(normalise_ts_line("f() {} // a"), "f() {} // a");
+        assert_eq!(normalise_ts_line("f() /* a */"), "f() /* a */;");
+        assert_eq!(normalise_ts_line("f() /* a"), "f(); /* a");
+        assert_eq!(normalise_ts_line("f(\"//\", '\\'//') // a"),
+            "f(\"//\", '\\'//'); // a");
+        // A line which starts with `*` is only a comment inside `/* */`.
+        assert_eq!(normalise_ts_line("*gen()"), "*gen();");
+        // `push_normalised_main_line()` uses `normalise_ts_line()`.
+        let result = TranspileResult::new()
+            .push_normalised_main_line("const X = 4")
+            .push_normalised_main_line("// note");
+        assert_eq!(result.main_lines, vec!["const X = 4;", "// note"]);
+        // And knows whether each line is inside a `/* */` comment.
+        let result = TranspileResult::new()
+            .push_normalised_main_line("/*")
+            .push_normalised_main_line(" * note ")
+            .push_normalised_main_line(" */ const X = 4")
+            .push_normalised_main_line("*gen()")
+            .push_normalised_main_line("f() /* a")
+            .push_normalised_main_line("  b */");
+        assert_eq!(result.main_lines, vec!["/*", " * note", " */ const X = 4;",
+            "*gen();", "f(); /* a", "  b */"]);
+        // Including lines which were pushed by `push_main_line()`.
+        let result = TranspileResult::new()
+            .push_normalised_main_line("a()")
+            .push_main_line("/* b")
+            .push_normalised_main_line("c()")
+            .push_main_line("*/")
+            .push_normalised_main_line("d()");
+        assert_eq!(result.main_lines,
+            vec!["a();", "/* b", "c()", "*/", "d();"]);
+    }
+
+    #[test]
+    fn format_errors_as_expected() {
+        assert_eq!(TranspileResult::new().format_errors(), "");
+        let result = TranspileResult::new()
+            .push_unsupported_syntax_error(5, 2, "Malformed const declaration")
+            .push_invalid_number_error(17, 4, "Integer is too large");
+        assert_eq!(result.format_errors(),
+            "error[UnsupportedSyntax] at 2:5: Malformed const declaration\n\
+             error[InvalidNumber] at 4:17: Integer is too large");
+    }
+
+    #[test]
+    fn write_to_matches_to_string() {
+        // Empty.
+        let result = TranspileResult::new();
+        let mut out: Vec<u8> = vec![];
+        result.write_to(&mut out).unwrap();
+        assert_eq!(out, b"");
+        assert_eq!(result.to_string(), "");
+        // Every section.
+        let mut result = TranspileResult::new()
+            .push_main_line("const A: number = 1;")
+            .push_main_line("const B: number = \"é\".len();")
+            .push_polyfill_line("P;")
+            .push_type_line("T;");
+        result.main_section_begins = "<m>";
+        result.main_section_ends = "</m>";
+        result.polyfill_section_begins = "<p>";
+        result.polyfill_section_ends = "</p>";
+        let mut out: Vec<u8> = vec![];
+        result.write_to(&mut out).unwrap();
+        assert_eq!(out, result.to_string().as_bytes());
+        assert_eq!(result.to_string(), "<m>const A: number = 1;\n\
+            const B: number = \"é\".len();\n</m><p>P;</p>T;");
+        // Many lines, and no polyfill section.
+        let mut result = TranspileResult::new()
+            .push_type_line("T1;")
+            .push_type_line("T2;");
+        for i in 0..1000 {
+            let line = format!("const A{}: number = {};", i, i);
+            result = result.push_main_line(&line);
+        }
+        let mut out: Vec<u8> = vec![];
+        result.write_to(&mut out).unwrap();
+        let string = result.to_string();
+        assert_eq!(out, string.as_bytes());
+        assert!(string.ends_with("const A999: number = 999;\nT1;T2;"));
+        assert_eq!(string.len(), 25_786);
+    }
+
+    #[test]
+    fn push_indented_main_line_as_expected() {
+        let result = TranspileResult::new()
+            .push_main_line("a")
+            .push_main_line("")
+            .push_indented_main_line("    ", "b")
+            .push_main_line("c")
+            .push_indented_main_line("\t", "d");
+        assert_eq!(result.main_lines, vec!["a", "", "b", "c", "d"]);
+        assert_eq!(result.original_whitespace, vec!["", "", "    ", "", "\t"]);
+        let mut out: Vec<u8> = vec![];
+        result.write_to(&mut out).unwrap();
+        assert_eq!(out, result.to_string().as_bytes());
+        assert_eq!(result.to_string(), "a\n\n    b\nc\n\td\n");
+    }
+
+    #[test]
+    fn push_error_methods_accumulate() {
+        let result = TranspileResult::new()
+            .push_unsupported_syntax_error(1, 1, "first")
+            .push_invalid_number_error(2, 1, "second");
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(result.errors[0].message, "first");
+        assert_eq!(result.errors[1].message, "second");
+        assert!(result.main_lines.is_empty());
     }
 }
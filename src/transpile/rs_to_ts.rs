@@ -1,7 +1,10 @@
 //! Contains the library’s main function, `rs_to_ts()`.
 
-use super::config::{Config,RsEdition,Strategy,TsMajor};
+use super::config::{Config,Setting};
+use super::error::TranspileErrors;
 use super::result::TranspileResult;
+use crate::rs2018_ts4::lexemize::lexeme::LexemeKind;
+use crate::rs2018_ts4::lexemize::lexemize::Lexemizer;
 
 /// Transpiles Rust code to TypeScript.
 /// 
@@ -22,20 +25,23 @@ use super::result::TranspileResult;
 /// assert_eq!(rs_to_ts(
 ///     "const ROUGHLY_PI: f32 = 3.14;",
 ///     Config::new()).main_lines[0],
-///     "const ROUGHLY_PI: Number = 3.14;");
+///     "const ROUGHLY_PI: number = 3.14;");
 /// ```
 /// The Builder Pattern lets you can modify your `Config` quite easily, and you
 /// can use `to_string()` to inspect it. See the [Config] docs.
 /// 
 /// ### Placeholder config
 /// Currently `rs_to_ts()` only supports input code in the 2018 edition of Rust,
-/// and will only output TypeScript 4 code using the ‘Gungho’ strategy. The
+/// and will only output TypeScript 3 or 4 code using the ‘Gungho’ strategy. The
 /// following enum values are placeholders, and may be implementated one day:
 /// * `RsEdition::Rs2015`
 /// * `Strategy::Cautious`
-/// * `TsMajor::Ts3`
 /// 
-/// Attempting to use placeholder config values leads to an error.
+/// Attempting to use placeholder config values leads to one error for each,
+/// as listed by `Config::validate()`. If `orig` contains an identifier which
+/// mentions the config, like `edition` in `#![edition = "2015"]`, the error
+/// points at its line number and column. Otherwise, the line number and column
+/// are both zero.
 /// ```
 /// # use opinionated_rust_to_typescript::transpile::config::*;
 /// # use opinionated_rust_to_typescript::transpile::rs_to_ts::*;
@@ -45,31 +51,275 @@ use super::result::TranspileResult;
 /// assert_eq!(rs_to_ts("Nope",
 ///     Config::new().strategy(Strategy::Cautious)).errors[0].message,
 ///     "Strategy::Cautious is not implemented yet");
-/// assert_eq!(rs_to_ts("Nope",
-///     Config::new().ts_major(TsMajor::Ts3)).errors[0].message,
-///     "TsMajor::Ts3 is not implemented yet");
+/// let result = rs_to_ts("#![edition = \"2015\"]",
+///     Config::new().rs_edition(RsEdition::Rs2015));
+/// assert_eq!(result.errors[0].line_number, 1);
+/// assert_eq!(result.errors[0].column, 4);
 /// ```
 /// 
+/// ### Directive comments
+/// Settings can also be pinned in `orig` itself, by a comment before any code
+/// which begins `rs2ts:`, like `//! rs2ts: edition=2018, ts=4` or
+/// `/* rs2ts: ts=3 */`. The directive is merged into `config` by
+/// `Config::merge_directive()`, so explicit `config` values win. A malformed
+/// directive is reported as an `UnsupportedSyntax` error which explains what is
+/// wrong with it, and nothing is transpiled.
+/// ```
+/// # use opinionated_rust_to_typescript::transpile::config::*;
+/// # use opinionated_rust_to_typescript::transpile::rs_to_ts::*;
+/// let orig = "//! rs2ts: ts=3\nconst A: u64 = 9007199254740993;";
+/// assert_eq!(rs_to_ts(orig, Config::new()).main_lines[1],
+///     "const A: number = 9007199254740993;");
+/// assert_eq!(rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts4))
+///     .main_lines[1], "const A: bigint = 9007199254740993n;");
+/// ```
 pub fn rs_to_ts(
     orig: &str,
     config: Config,
 ) -> TranspileResult {
-    if config.rs_edition == RsEdition::Rs2015 {
-        return make_not_implemented_result(
+    // Merge any directive comment into `config`. `merge_directive()` explains
+    // what is wrong with a malformed directive.
+    let config = match find_directive(orig) {
+        Some((directive, column, line_number)) =>
+            match config.merge_directive(directive) {
+                Ok(merged) => merged,
+                Err(message) => return TranspileResult::new()
+                    .push_unsupported_syntax_error(column, line_number,
+                        format!("Malformed `rs2ts:` directive: {}", message)),
+            },
+        None => config,
+    };
+    let settings = config.validate_settings();
+    if ! settings.is_empty() {
+        let mut result = TranspileResult::new();
+        for (setting, message) in settings {
+            let (column, line_number) =
+                locate_telltale(orig, telltale_for(setting));
+            result = result.push_config_not_implemented_error(
+                column, line_number, message);
+        }
+        return result;
+    }
+    crate::rs2018_ts4::rs2018_ts4_gungho::rs2018_ts4_gungho_with_config(
+        orig, &config)
+}
+
+/// Transpiles Rust code to a TypeScript string, or returns the errors.
+/// 
+/// This is a convenient alternative to `rs_to_ts()`, for when the
+/// [`TranspileResult`] itself is not needed.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code
+/// * `config` Defines code versions and transpilation strategy
+/// 
+/// ### Returns
+/// The TypeScript code, from `TranspileResult::to_string()`, or every error if
+/// there were any, wrapped in [`TranspileErrors`] so that `?` works with
+/// `Box<dyn std::error::Error>`.
+/// ```
+/// # use opinionated_rust_to_typescript::transpile::config::*;
+/// # use opinionated_rust_to_typescript::transpile::rs_to_ts::*;
+/// assert_eq!(transpile_to_string("const A: u8 = 1;", Config::new()).ok(),
+///     Some("const A: number = 1;\n".to_string()));
+/// let errors = transpile_to_string("const A: u8 = 1;",
+///     Config::new().strategy(Strategy::Cautious)).err().unwrap();
+/// assert_eq!(errors.0.len(), 1);
+/// assert_eq!(errors.0[0].message,
+///     "Strategy::Cautious is not implemented yet");
+/// ```
+pub fn transpile_to_string(
+    orig: &str,
+    config: Config,
+) -> Result<String, TranspileErrors> {
+    let result = rs_to_ts(orig, config);
+    if result.errors.is_empty() { Ok(result.to_string()) }
+    else { Err(TranspileErrors(result.errors)) }
+}
+
+// Finds a directive comment, like `//! rs2ts: ts=4` or `/* rs2ts: ts=4 */`,
+// before any code in `orig`. Returns the part after "rs2ts:", and the comment’s
+// column and line number.
+fn find_directive(orig: &str) -> Option<(&str, usize, usize)> {
+    for lexeme in Lexemizer::new(orig) {
+        match lexeme.kind {
+            LexemeKind::Comment | LexemeKind::DocComment => {},
+            LexemeKind::Whitespace => continue,
+            _ => return None,
+        }
+        // Remove the "//", "//!", "///", "/*", "/*!" or "/**" introducer, any
+        // "*/" terminator, and any whitespace.
+        let snippet = lexeme.snippet;
+        let text = match snippet.strip_prefix("/*") {
+            Some(block) => block.strip_suffix("*/").unwrap_or(block)
+                .trim_start_matches(&['!', '*'][..]),
+            None => snippet.trim_start_matches('/').trim_start_matches('!'),
+        }.trim_start();
+        if let Some(directive) = text.strip_prefix("rs2ts:") {
+            return Some((directive.trim_end(), lexeme.column,
+                lexeme.line_number));
+        }
+    }
+    None
+}
+
+// Returns the column and line number of the first identifier in `orig` which
+// matches `telltale`, or `(0, 0)` if there is no such identifier. Identifiers
+// inside attributes, like `#![edition = "2015"]`, are also found.
+fn locate_telltale(orig: &str, telltale: &str) -> (usize, usize) {
+    for lexeme in Lexemizer::new(orig) {
+        if lexeme.kind == LexemeKind::Identifier && lexeme.snippet == telltale {
+            return (lexeme.column, lexeme.line_number)
+        }
+        if lexeme.kind != LexemeKind::Attribute { continue }
+        // Look inside the attribute, after its ascii "#[" or "#![" introducer.
+        let open = lexeme.snippet.find('[').unwrap_or(0) + 1;
+        let inner = &lexeme.snippet[open..];
+        let (column, line_number) = locate_telltale(inner, telltale);
+        if line_number == 0 { continue }
+        // Convert the position within the attribute to a position in `orig`.
+        return if line_number == 1 {
+            (lexeme.column + open + column - 1, lexeme.line_number)
+        } else {
+            (column, lexeme.line_number + line_number - 1)
+        }
+    }
+    (0, 0)
+}
+
+// Returns the identifier which hints at a setting, in `orig`.
+fn telltale_for(setting: Setting) -> &'static str {
+    match setting {
+        Setting::RsEdition => "edition",
+        Setting::Strategy => "strategy",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::config::{RsEdition,Strategy,TsMajor};
+
+    #[test]
+    fn rs_to_ts_not_implemented_position() {
+        // The telltale identifier is found.
+        let result = rs_to_ts("// Config\n#![edition = \"2015\"]",
+            Config::new().rs_edition(RsEdition::Rs2015));
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 4);
+        let result = rs_to_ts("  strategy",
+            Config::new().strategy(Strategy::Cautious));
+        assert_eq!(result.errors[0].column, 3);
+        // An attribute which spans several lines.
+        let result = rs_to_ts("\n  #![a(b,\n  edition)]",
+            Config::new().rs_edition(RsEdition::Rs2015));
+        assert_eq!(result.errors[0].line_number, 3);
+        assert_eq!(result.errors[0].column, 3);
+        // A string or comment which contains the telltale is ignored.
+        let result = rs_to_ts("\"strategy\" // strategy",
+            Config::new().strategy(Strategy::Cautious));
+        assert_eq!(result.errors[0].line_number, 0);
+        assert_eq!(result.errors[0].column, 0);
+    }
+
+    #[test]
+    fn rs_to_ts_reports_every_unsupported_option() {
+        let result = rs_to_ts("#![edition = \"2015\"]\nstrategy",
+            Config::new().rs_edition(RsEdition::Rs2015)
+                .strategy(Strategy::Cautious));
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(result.errors[0].message,
             "RsEdition::Rs2015 is not implemented yet");
+        assert_eq!(result.errors[0].line_number, 1);
+        assert_eq!(result.errors[0].column, 4);
+        assert_eq!(result.errors[1].message,
+            "Strategy::Cautious is not implemented yet");
+        assert_eq!(result.errors[1].line_number, 2);
+        assert_eq!(result.errors[1].column, 1);
+        assert_eq!(result.main_lines.len(), 0); // nothing is transpiled
     }
-    if config.strategy == Strategy::Cautious {
-        return make_not_implemented_result(
+
+    #[test]
+    fn rs_to_ts_directive_only() {
+        // A file which contains only a directive.
+        let orig = "//! rs2ts: edition=2018, ts=4, strategy=gungho";
+        let result = rs_to_ts(orig, Config::new());
+        assert!(result.errors.is_empty());
+        assert!(result.warnings.is_empty());
+        // A directive for a placeholder setting.
+        let result = rs_to_ts("// rs2ts: strategy=cautious", Config::new());
+        assert_eq!(result.errors[0].message,
             "Strategy::Cautious is not implemented yet");
+        // A malformed directive.
+        let result = rs_to_ts("\n  // rs2ts: ts=5", Config::new());
+        assert_eq!(result.errors[0].message, "Malformed `rs2ts:` directive: \
+            Unknown TypeScript version \"5\", expected latest, 3 or 4");
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(result.errors[0].column, 3);
+        assert!(result.main_lines.is_empty());
+        let result = rs_to_ts("//! rs2ts: ts", Config::new());
+        assert_eq!(result.errors[0].message,
+            "Malformed `rs2ts:` directive: Missing value for ts");
     }
-    if config.ts_major == TsMajor::Ts3 {
-        return make_not_implemented_result(
-            "TsMajor::Ts3 is not implemented yet");
+
+    #[test]
+    fn rs_to_ts_directive_in_block_comment() {
+        let orig = "/* rs2ts: ts=3 */\nconst A: u64 = 9007199254740993;";
+        let result = rs_to_ts(orig, Config::new());
+        assert_eq!(result.main_lines[1], "const A: number = 9007199254740993;");
+        // Doc comments, and a directive over several lines.
+        let result = rs_to_ts("/*! rs2ts: ts=3 */", Config::new());
+        assert!(result.errors.is_empty());
+        let result = rs_to_ts("/** rs2ts:\n  edition=2018,\n  ts=5\n*/",
+            Config::new());
+        assert_eq!(result.errors[0].message, "Malformed `rs2ts:` directive: \
+            Unknown TypeScript version \"5\", expected latest, 3 or 4");
+        assert_eq!(result.errors[0].line_number, 1);
     }
-    crate::rs2018_ts4::rs2018_ts4_gungho::rs2018_ts4_gungho(orig)
-}
 
-fn make_not_implemented_result(message: &'static str) -> TranspileResult {
-    TranspileResult::new()
-        .push_config_not_implemented_error(0, 0, message)
-}
\ No newline at end of file
+    #[test]
+    fn rs_to_ts_directive_overridden_by_config() {
+        let orig = "//! rs2ts: ts=3, edition=2015\n\
+            const BIG: u64 = 18_446_744_073_709_551_615u64;";
+        // Explicit `Config` values win.
+        let result = rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts4)
+            .rs_edition(RsEdition::Rs2018));
+        assert!(result.errors.is_empty());
+        assert_eq!(result.main_lines[1],
+            "const BIG: bigint = 18_446_744_073_709_551_615n;");
+        // Otherwise, the directive’s values are used.
+        let result = rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts3));
+        assert_eq!(result.errors[0].message,
+            "RsEdition::Rs2015 is not implemented yet");
+        // A directive after any code is ignored.
+        let result = rs_to_ts("const A: u8 = 1;\n// rs2ts: ts=5",
+            Config::new());
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn rs_to_ts_ts3_and_ts4_big_integers() {
+        let orig = "const BIG: u64 = 18_446_744_073_709_551_615u64;";
+        // TypeScript 4 has `bigint`.
+        let result = rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts4));
+        assert_eq!(result.main_lines,
+            vec!["const BIG: bigint = 18_446_744_073_709_551_615n;"]);
+        assert!(result.errors.is_empty());
+        assert!(result.warnings.is_empty());
+        // TypeScript 3 does not, so there is a warning instead.
+        let result = rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts3));
+        assert_eq!(result.main_lines,
+            vec!["const BIG: number = 18_446_744_073_709_551_615;"]);
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings[0].to_string(),
+            "warning[LossOfPrecision] at 1:18: \
+            Integer is larger than Number.MAX_SAFE_INTEGER");
+        // Safe integers are the same in both.
+        let orig = "const A: u64 = 9007199254740991;";
+        let ts3 = rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts3));
+        let ts4 = rs_to_ts(orig, Config::new().ts_major(TsMajor::Ts4));
+        assert_eq!(ts3.main_lines, ts4.main_lines);
+        assert!(ts3.warnings.is_empty());
+    }
+}